@@ -0,0 +1,122 @@
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use FileSystem;
+#[cfg(unix)]
+use UnixFileSystem;
+
+/// What a path refers to, as seen by a `Backend`.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Kind {
+    Missing,
+    File,
+    Dir,
+    Symlink(PathBuf),
+}
+
+/// The operations copying and syncing between file systems need beyond
+/// `FileSystem`, which only Unix implementations can provide.
+pub(crate) trait Backend {
+    type Fs: FileSystem;
+
+    fn fs(&self) -> &Self::Fs;
+    fn kind(&self, path: &Path, follow_symlinks: bool) -> Result<Kind>;
+    fn mode(&self, path: &Path) -> Result<Option<u32>>;
+    fn set_mode(&self, path: &Path, mode: u32) -> Result<()>;
+    fn symlink(&self, src: &Path, dst: &Path) -> Result<()>;
+}
+
+/// A path whose parent is a file is missing, rather than an error.
+fn followed_kind<F: FileSystem>(fs: &F, path: &Path) -> Result<Kind> {
+    if fs.is_dir(path) {
+        return Ok(Kind::Dir);
+    }
+    match fs.try_exists(path) {
+        Ok(true) => Ok(Kind::File),
+        Ok(false) => Ok(Kind::Missing),
+        Err(ref err) if err.kind() == ErrorKind::NotADirectory => Ok(Kind::Missing),
+        Err(err) => Err(err),
+    }
+}
+
+pub(crate) struct Plain<'a, F: 'a>(pub(crate) &'a F);
+
+impl<'a, F: FileSystem> Backend for Plain<'a, F> {
+    type Fs = F;
+
+    fn fs(&self) -> &F {
+        self.0
+    }
+
+    fn kind(&self, path: &Path, _: bool) -> Result<Kind> {
+        followed_kind(self.0, path)
+    }
+
+    fn mode(&self, _: &Path) -> Result<Option<u32>> {
+        Ok(None)
+    }
+
+    fn set_mode(&self, _: &Path, _: u32) -> Result<()> {
+        Ok(())
+    }
+
+    fn symlink(&self, _: &Path, _: &Path) -> Result<()> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "symlinks require UnixFileSystem",
+        ))
+    }
+}
+
+#[cfg(unix)]
+pub(crate) struct Unix<'a, F: 'a>(pub(crate) &'a F);
+
+#[cfg(unix)]
+impl<'a, F: FileSystem + UnixFileSystem> Backend for Unix<'a, F> {
+    type Fs = F;
+
+    fn fs(&self) -> &F {
+        self.0
+    }
+
+    fn kind(&self, path: &Path, follow_symlinks: bool) -> Result<Kind> {
+        if !follow_symlinks && self.0.is_symlink(path) {
+            self.0.get_symlink_src(path).map(Kind::Symlink)
+        } else {
+            followed_kind(self.0, path)
+        }
+    }
+
+    fn mode(&self, path: &Path) -> Result<Option<u32>> {
+        self.0.mode(path).map(|mode| Some(mode & 0o7777))
+    }
+
+    fn set_mode(&self, path: &Path, mode: u32) -> Result<()> {
+        self.0.set_mode(path, mode)
+    }
+
+    fn symlink(&self, src: &Path, dst: &Path) -> Result<()> {
+        self.0.symlink(src, dst)
+    }
+}
@@ -0,0 +1,362 @@
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::fmt;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+use std::sync::Arc;
+
+#[cfg(unix)]
+use backend::Unix;
+use backend::{Backend, Kind, Plain};
+#[cfg(unix)]
+use UnixFileSystem;
+use {DirEntry, FileSystem};
+
+/// What to do when a file or symlink being copied already exists at the destination.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum CopyConflict {
+    /// Replace the existing node.
+    Overwrite,
+    /// Leave the existing node untouched and carry on.
+    Skip,
+    /// Fail with `ErrorKind::AlreadyExists`.
+    #[default]
+    Error,
+}
+
+/// Reported to the progress callback after each node is copied.
+#[derive(Debug)]
+pub struct CopyProgress<'a> {
+    /// The node that was copied.
+    pub from: &'a Path,
+    /// Where it was copied to.
+    pub to: &'a Path,
+    /// Number of files, directories and symlinks copied so far.
+    pub copied_entries: u64,
+    /// Number of file bytes copied so far.
+    pub copied_bytes: u64,
+}
+
+type ProgressCallback = Arc<dyn Fn(&CopyProgress) + Send + Sync>;
+
+/// Options for [`FileSystem::copy_dir_all`] and [`copy_dir_all_between`].
+///
+/// By default, conflicting files fail the copy, modes are preserved, timestamps
+/// are not, and symlinks are recreated rather than followed.
+///
+/// [`FileSystem::copy_dir_all`]: trait.FileSystem.html#tymethod.copy_dir_all
+/// [`copy_dir_all_between`]: fn.copy_dir_all_between.html
+#[derive(Clone)]
+pub struct CopyOptions {
+    pub(crate) on_conflict: CopyConflict,
+    pub(crate) preserve_mode: bool,
+    pub(crate) preserve_timestamps: bool,
+    pub(crate) follow_symlinks: bool,
+    pub(crate) progress: Option<ProgressCallback>,
+}
+
+impl CopyOptions {
+    pub fn new() -> Self {
+        CopyOptions {
+            on_conflict: CopyConflict::default(),
+            preserve_mode: true,
+            preserve_timestamps: false,
+            follow_symlinks: false,
+            progress: None,
+        }
+    }
+
+    /// Sets what to do when a file or symlink already exists at the destination.
+    /// Existing directories are always merged into.
+    pub fn on_conflict(mut self, on_conflict: CopyConflict) -> Self {
+        self.on_conflict = on_conflict;
+        self
+    }
+
+    /// Sets whether the copies get the mode bits of the originals.
+    pub fn preserve_mode(mut self, preserve_mode: bool) -> Self {
+        self.preserve_mode = preserve_mode;
+        self
+    }
+
+    /// Sets whether the copies get the modification times of the originals.
+    pub fn preserve_timestamps(mut self, preserve_timestamps: bool) -> Self {
+        self.preserve_timestamps = preserve_timestamps;
+        self
+    }
+
+    /// Sets whether symlinks are copied as the node they point to, instead of
+    /// as symlinks with the same source.
+    pub fn follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Sets a callback invoked after each node is copied.
+    ///
+    /// `FakeFileSystem` runs the callback while the file system is locked, so it
+    /// must not call back into the same file system.
+    pub fn progress<F>(mut self, progress: F) -> Self
+    where
+        F: Fn(&CopyProgress) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for CopyOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CopyOptions")
+            .field("on_conflict", &self.on_conflict)
+            .field("preserve_mode", &self.preserve_mode)
+            .field("preserve_timestamps", &self.preserve_timestamps)
+            .field("follow_symlinks", &self.follow_symlinks)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+/// Keeps the running totals handed to the progress callback.
+#[derive(Debug, Default)]
+pub(crate) struct CopyCounter {
+    copied_entries: u64,
+    copied_bytes: u64,
+}
+
+impl CopyCounter {
+    pub(crate) fn record(&mut self, options: &CopyOptions, from: &Path, to: &Path, bytes: u64) {
+        self.copied_entries += 1;
+        self.copied_bytes += bytes;
+
+        if let Some(ref progress) = options.progress {
+            progress(&CopyProgress {
+                from,
+                to,
+                copied_entries: self.copied_entries,
+                copied_bytes: self.copied_bytes,
+            });
+        }
+    }
+}
+
+/// Recursively copies the directory `from` in `src_fs` to `to` in `dst_fs`.
+///
+/// `src_fs` and `dst_fs` may be different implementations, e.g. to copy a tree
+/// from a `FakeFileSystem` onto the disk. If `to` already exists, the contents of
+/// `from` are merged into it. Symlinks in the source are followed and modes are
+/// not copied, so `options` must set `follow_symlinks(true)` and
+/// `preserve_mode(false)`; use [`copy_dir_all_between_unix`] to preserve them.
+///
+/// Named pipes, sockets and devices, and followed symlinks whose target does
+/// not exist, are skipped.
+///
+/// # Errors
+///
+/// * `options` asks to recreate symlinks or preserve modes
+///   (`ErrorKind::Unsupported`).
+/// * `from` does not exist or is not a directory.
+/// * The parent of `to` does not exist.
+/// * A node already exists at the destination and `CopyConflict::Error` is set.
+/// * Current user has insufficient permissions.
+///
+/// [`copy_dir_all_between_unix`]: fn.copy_dir_all_between_unix.html
+pub fn copy_dir_all_between<S, D, P, Q>(
+    src_fs: &S,
+    from: P,
+    dst_fs: &D,
+    to: Q,
+    options: &CopyOptions,
+) -> Result<()>
+where
+    S: FileSystem,
+    D: FileSystem,
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    if !options.follow_symlinks || options.preserve_mode {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            "copying symlinks and modes requires UnixFileSystem",
+        ));
+    }
+
+    copy_between(
+        &Plain(src_fs),
+        from.as_ref(),
+        &Plain(dst_fs),
+        to.as_ref(),
+        options,
+    )
+}
+
+/// Like [`copy_dir_all_between`], but also copies symlinks and mode bits as
+/// set by `CopyOptions`, and accepts any `CopyOptions`.
+///
+/// [`copy_dir_all_between`]: fn.copy_dir_all_between.html
+#[cfg(unix)]
+pub fn copy_dir_all_between_unix<S, D, P, Q>(
+    src_fs: &S,
+    from: P,
+    dst_fs: &D,
+    to: Q,
+    options: &CopyOptions,
+) -> Result<()>
+where
+    S: FileSystem + UnixFileSystem,
+    D: FileSystem + UnixFileSystem,
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    copy_between(
+        &Unix(src_fs),
+        from.as_ref(),
+        &Unix(dst_fs),
+        to.as_ref(),
+        options,
+    )
+}
+
+fn copy_between<S: Backend, D: Backend>(
+    src: &S,
+    from: &Path,
+    dst: &D,
+    to: &Path,
+    options: &CopyOptions,
+) -> Result<()> {
+    if !src.fs().is_dir(from) {
+        return Err(if src.fs().exists(from) {
+            Error::new(ErrorKind::NotADirectory, "source is not a directory")
+        } else {
+            Error::new(ErrorKind::NotFound, "source does not exist")
+        });
+    }
+
+    copy_dir_between(src, from, dst, to, options, &mut CopyCounter::default())
+}
+
+fn copy_dir_between<S: Backend, D: Backend>(
+    src: &S,
+    from: &Path,
+    dst: &D,
+    to: &Path,
+    options: &CopyOptions,
+    counter: &mut CopyCounter,
+) -> Result<()> {
+    if !dst.fs().is_dir(to) {
+        if !replace_existing(dst, to, options)? {
+            return Ok(());
+        }
+        dst.fs().create_dir(to)?;
+    }
+
+    let mut entries = Vec::new();
+    for entry in src.fs().read_dir(from)? {
+        let entry = entry?;
+        let special = matches!(entry.file_type(), Ok(file_type) if file_type.is_special());
+        if !special {
+            entries.push(entry.file_name());
+        }
+    }
+    entries.sort();
+
+    for name in entries {
+        let from = from.join(&name);
+        let to = to.join(&name);
+
+        match src.kind(&from, options.follow_symlinks)? {
+            // A followed symlink whose target does not exist.
+            Kind::Missing => (),
+            Kind::Symlink(source) => {
+                if replace_existing(dst, &to, options)? {
+                    dst.symlink(&source, &to)?;
+                    counter.record(options, &from, &to, 0);
+                }
+            }
+            Kind::Dir => copy_dir_between(src, &from, dst, &to, options, counter)?,
+            Kind::File => {
+                let contents = src.fs().read_file(&from)?;
+                if replace_existing(dst, &to, options)? {
+                    dst.fs().create_file(&to, &contents)?;
+                    copy_attributes(src, &from, dst, &to, options)?;
+                    counter.record(options, &from, &to, contents.len() as u64);
+                }
+            }
+        }
+    }
+
+    copy_attributes(src, from, dst, to, options)?;
+    counter.record(options, from, to, 0);
+
+    Ok(())
+}
+
+/// Clears the way for a non-directory node at `to`. Returns `false` if the
+/// node should be skipped instead.
+fn replace_existing<D: Backend>(dst: &D, to: &Path, options: &CopyOptions) -> Result<bool> {
+    match dst.kind(to, false)? {
+        Kind::Missing => return Ok(true),
+        Kind::Dir => {
+            return Err(Error::new(
+                ErrorKind::IsADirectory,
+                "destination is a directory",
+            ))
+        }
+        _ => (),
+    }
+
+    match options.on_conflict {
+        CopyConflict::Skip => Ok(false),
+        CopyConflict::Error => Err(Error::new(
+            ErrorKind::AlreadyExists,
+            "destination already exists",
+        )),
+        CopyConflict::Overwrite => dst.fs().remove_file(to).and(Ok(true)),
+    }
+}
+
+/// Copies the modification time and, where both backends have them, the mode
+/// bits of `from` to `to`, as set by `options`.
+fn copy_attributes<S: Backend, D: Backend>(
+    src: &S,
+    from: &Path,
+    dst: &D,
+    to: &Path,
+    options: &CopyOptions,
+) -> Result<()> {
+    if options.preserve_timestamps {
+        dst.fs().set_modified(to, src.fs().modified(from)?)?;
+    }
+    if options.preserve_mode {
+        if let Some(mode) = src.mode(from)? {
+            dst.set_mode(to, mode)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,102 @@
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::ffi::OsStr;
+use std::fmt;
+use std::sync::Arc;
+
+type Folding = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
+/// Whether `FakeFileSystem` tells apart names that differ only in case.
+/// See `FakeFileSystem::set_case_sensitivity`.
+#[derive(Clone, Debug, Default)]
+pub enum CaseSensitivity {
+    /// Names are compared as is, as on Linux.
+    #[default]
+    Sensitive,
+    /// Names that fold to the same string refer to the same node, which
+    /// keeps the spelling it was created with, as on macOS and Windows.
+    Insensitive(CaseFolding),
+}
+
+/// How names are folded before being compared by a case-insensitive
+/// `FakeFileSystem`.
+#[derive(Clone)]
+pub enum CaseFolding {
+    /// Only ASCII letters are folded, so `É` and `é` are different names.
+    Ascii,
+    /// Names are folded with the Unicode lowercase mapping of
+    /// [`str::to_lowercase`].
+    ///
+    /// [`str::to_lowercase`]: https://doc.rust-lang.org/std/primitive.str.html#method.to_lowercase
+    Unicode,
+    /// Names are folded by a closure, e.g. to apply full Unicode case folding
+    /// or the upcase table of a particular file system.
+    Custom(Folding),
+}
+
+impl CaseFolding {
+    /// Wraps `f` in a `CaseFolding::Custom`.
+    pub fn custom<F>(f: F) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        CaseFolding::Custom(Arc::new(f))
+    }
+
+    fn fold(&self, name: &str) -> String {
+        match *self {
+            CaseFolding::Ascii => name.to_ascii_lowercase(),
+            CaseFolding::Unicode => name.to_lowercase(),
+            CaseFolding::Custom(ref f) => f(name),
+        }
+    }
+}
+
+impl fmt::Debug for CaseFolding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CaseFolding::Ascii => f.write_str("Ascii"),
+            CaseFolding::Unicode => f.write_str("Unicode"),
+            CaseFolding::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+impl CaseSensitivity {
+    pub(crate) fn is_sensitive(&self) -> bool {
+        matches!(*self, CaseSensitivity::Sensitive)
+    }
+
+    /// Determines whether `a` and `b` name the same node. Names that are not
+    /// valid Unicode are only equal to themselves.
+    pub(crate) fn matches(&self, a: &OsStr, b: &OsStr) -> bool {
+        if a == b {
+            return true;
+        }
+        match (self, a.to_str(), b.to_str()) {
+            (CaseSensitivity::Insensitive(folding), Some(a), Some(b)) => {
+                folding.fold(a) == folding.fold(b)
+            }
+            _ => false,
+        }
+    }
+}
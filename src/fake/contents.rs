@@ -0,0 +1,359 @@
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{Error, ErrorKind, Result};
+use std::sync::Arc;
+
+type Generator = Arc<dyn Fn(u64, &mut [u8]) + Send + Sync>;
+type ReadCallback = Arc<dyn Fn() -> Vec<u8> + Send + Sync>;
+type WriteCallback = Arc<dyn Fn(&[u8]) -> Result<()> + Send + Sync>;
+
+/// A file whose contents are computed on every read, like the files under
+/// `/proc` and `/sys`. See `FakeFileSystem::create_dynamic_file`.
+#[derive(Clone)]
+pub struct DynamicFile {
+    read: ReadCallback,
+    write: Option<WriteCallback>,
+}
+
+impl DynamicFile {
+    /// Creates a read-only file whose contents are returned by `read`.
+    pub fn new<F>(read: F) -> Self
+    where
+        F: Fn() -> Vec<u8> + Send + Sync + 'static,
+    {
+        DynamicFile {
+            read: Arc::new(read),
+            write: None,
+        }
+    }
+
+    /// Makes the file writable, handing every write to `write` instead of
+    /// storing it. Errors returned by `write` are returned by the write.
+    pub fn on_write<F>(mut self, write: F) -> Self
+    where
+        F: Fn(&[u8]) -> Result<()> + Send + Sync + 'static,
+    {
+        self.write = Some(Arc::new(write));
+        self
+    }
+
+    pub(crate) fn is_writable(&self) -> bool {
+        self.write.is_some()
+    }
+
+    pub(crate) fn read(&self) -> Contents {
+        Contents::from(&(self.read)()[..])
+    }
+
+    pub(crate) fn write(&self, buf: &[u8]) -> Result<()> {
+        match self.write {
+            Some(ref write) => write(buf),
+            None => Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "dynamic file has no write handler",
+            )),
+        }
+    }
+}
+
+impl fmt::Debug for DynamicFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DynamicFile")
+            .field("writable", &self.is_writable())
+            .finish()
+    }
+}
+
+/// Produces the bytes of a fake file on demand, so that large files do not need
+/// to be kept in memory. See `FakeFileSystem::create_generated_file`.
+#[derive(Clone)]
+pub enum ContentSource {
+    /// Only zeros, like a sparse file that is one big hole.
+    Zeros,
+    /// The given bytes, repeated over and over.
+    Pattern(Vec<u8>),
+    /// Pseudo-random bytes that are always the same for a given seed.
+    Random { seed: u64 },
+    /// Bytes computed by a closure, which is handed an offset into the file
+    /// and a buffer to fill with the bytes starting at that offset.
+    Generator(Generator),
+}
+
+impl ContentSource {
+    /// Wraps `f` in a `ContentSource::Generator`.
+    pub fn generator<F>(f: F) -> Self
+    where
+        F: Fn(u64, &mut [u8]) + Send + Sync + 'static,
+    {
+        ContentSource::Generator(Arc::new(f))
+    }
+
+    fn fill(&self, offset: u64, buf: &mut [u8]) {
+        match self {
+            ContentSource::Zeros => buf.iter_mut().for_each(|b| *b = 0),
+            ContentSource::Pattern(pattern) if pattern.is_empty() => {
+                buf.iter_mut().for_each(|b| *b = 0)
+            }
+            ContentSource::Pattern(pattern) => {
+                for (i, b) in buf.iter_mut().enumerate() {
+                    *b = pattern[((offset + i as u64) % pattern.len() as u64) as usize];
+                }
+            }
+            ContentSource::Random { seed } => {
+                for (i, b) in buf.iter_mut().enumerate() {
+                    let offset = offset + i as u64;
+                    let block = mix(*seed ^ mix(offset / 8));
+                    *b = block.to_le_bytes()[(offset % 8) as usize];
+                }
+            }
+            ContentSource::Generator(generate) => generate(offset, buf),
+        }
+    }
+}
+
+impl fmt::Debug for ContentSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ContentSource::Zeros => f.write_str("Zeros"),
+            ContentSource::Pattern(pattern) => f.debug_tuple("Pattern").field(pattern).finish(),
+            ContentSource::Random { seed } => f.debug_struct("Random").field("seed", seed).finish(),
+            ContentSource::Generator(_) => f.write_str("Generator(..)"),
+        }
+    }
+}
+
+impl PartialEq for ContentSource {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ContentSource::Zeros, ContentSource::Zeros) => true,
+            (ContentSource::Pattern(a), ContentSource::Pattern(b)) => a == b,
+            (ContentSource::Random { seed: a }, ContentSource::Random { seed: b }) => a == b,
+            (ContentSource::Generator(a), ContentSource::Generator(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// Length of the chunks `same_bytes` and `torn` read at a time.
+const CHUNK_LEN: u64 = 64 * 1024;
+
+/// The contents of a fake file: bytes from a `ContentSource`, overlaid with the
+/// extents that have been written since. Only written extents take up memory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Contents {
+    len: u64,
+    source: ContentSource,
+    /// How much of the file still comes from `source`. Bytes past it that are
+    /// not in an extent were cut off by a truncation, and read as zeros.
+    source_len: u64,
+    /// Written bytes by offset. Extents never overlap or touch.
+    extents: BTreeMap<u64, Vec<u8>>,
+}
+
+impl Contents {
+    pub fn generated(len: u64, source: ContentSource) -> Self {
+        Contents {
+            len,
+            source,
+            source_len: len,
+            extents: BTreeMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns up to `len` bytes starting at `offset`.
+    pub fn read(&self, offset: u64, len: u64) -> Vec<u8> {
+        let start = offset.min(self.len);
+        let end = offset.saturating_add(len).min(self.len);
+        let mut buf = vec![0; (end - start) as usize];
+
+        let source_end = end.min(self.source_len);
+        if start < source_end {
+            self.source
+                .fill(start, &mut buf[..(source_end - start) as usize]);
+        }
+
+        for (&extent_start, extent) in self.extents.range(..end).rev() {
+            let extent_end = extent_start + extent.len() as u64;
+            if extent_end <= start {
+                break;
+            }
+            let from = extent_start.max(start);
+            let to = extent_end.min(end);
+            buf[(from - start) as usize..(to - start) as usize].copy_from_slice(
+                &extent[(from - extent_start) as usize..(to - extent_start) as usize],
+            );
+        }
+
+        buf
+    }
+
+    /// Returns the whole file.
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.read(0, self.len)
+    }
+
+    /// Writes `buf` at `offset`, extending the file if needed. Fails with
+    /// `FileTooLarge` if the end of the write does not fit in a `u64`.
+    pub fn write(&mut self, offset: u64, buf: &[u8]) -> Result<()> {
+        if buf.is_empty() {
+            return Ok(());
+        }
+        let end = offset
+            .checked_add(buf.len() as u64)
+            .ok_or_else(|| Error::new(ErrorKind::FileTooLarge, "file too large"))?;
+
+        // Merge every extent that overlaps or touches the new one.
+        let touching: Vec<u64> = self
+            .extents
+            .range(..=end)
+            .rev()
+            .take_while(|&(&start, extent)| start + extent.len() as u64 >= offset)
+            .map(|(&start, _)| start)
+            .collect();
+        let merged_start = touching.last().map_or(offset, |&start| start.min(offset));
+        let mut merged = Vec::new();
+        for start in touching.into_iter().rev() {
+            let extent = self.extents.remove(&start).unwrap_or_default();
+            let at = (start - merged_start) as usize;
+            if merged.len() < at + extent.len() {
+                merged.resize(at + extent.len(), 0);
+            }
+            merged[at..at + extent.len()].copy_from_slice(&extent);
+        }
+        let at = (offset - merged_start) as usize;
+        if merged.len() < at + buf.len() {
+            merged.resize(at + buf.len(), 0);
+        }
+        merged[at..at + buf.len()].copy_from_slice(buf);
+
+        self.extents.insert(merged_start, merged);
+        self.len = self.len.max(end);
+
+        Ok(())
+    }
+
+    /// Returns whether both hold the same bytes, however they are stored.
+    pub fn same_bytes(&self, other: &Contents) -> bool {
+        self == other
+            || self.len == other.len
+                && self
+                    .differing_ranges(other)
+                    .into_iter()
+                    .all(|(start, end)| {
+                        chunks(start, end.min(self.len))
+                            .all(|(offset, len)| self.read(offset, len) == other.read(offset, len))
+                    })
+    }
+
+    /// Returns the first `cut` bytes of these contents followed by the bytes of
+    /// `rest` past `cut`, as a write torn at `cut` leaves the file. Only the
+    /// chunks of the prefix that differ from `rest` are copied.
+    pub fn torn(&self, rest: &Contents, cut: u64) -> Contents {
+        let mut torn = rest.clone();
+        torn.set_len(rest.len.max(cut));
+        for (start, end) in self.differing_ranges(rest) {
+            for (offset, len) in chunks(start, end.min(cut)) {
+                let chunk = self.read(offset, len);
+                if chunk != torn.read(offset, len) {
+                    torn.write(offset, &chunk)
+                        .expect("a write within the file cannot overflow");
+                }
+            }
+        }
+
+        torn
+    }
+
+    /// Returns the ranges outside of which both read the same bytes, as far as
+    /// both are long enough.
+    fn differing_ranges(&self, other: &Contents) -> Vec<(u64, u64)> {
+        let mut ranges: Vec<(u64, u64)> = self
+            .extents
+            .iter()
+            .chain(&other.extents)
+            .map(|(&start, extent)| (start, start + extent.len() as u64))
+            .collect();
+        let (shorter, longer) = if self.source_len <= other.source_len {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        if self.source != other.source {
+            ranges.push((0, shorter.source_len));
+        }
+        // Past the shorter source, the other one reads as zeros.
+        if longer.source != ContentSource::Zeros {
+            ranges.push((shorter.source_len, longer.source_len));
+        }
+
+        ranges
+    }
+
+    /// Truncates or extends the file to `len` bytes. New bytes read as zeros.
+    pub fn set_len(&mut self, len: u64) {
+        if len < self.len {
+            self.extents.retain(|&start, _| start < len);
+            if let Some((&start, extent)) = self.extents.iter_mut().next_back() {
+                extent.truncate((len - start).min(extent.len() as u64) as usize);
+            }
+            self.source_len = self.source_len.min(len);
+        }
+        self.len = len;
+    }
+}
+
+impl Default for Contents {
+    fn default() -> Self {
+        Contents::generated(0, ContentSource::Zeros)
+    }
+}
+
+impl From<&[u8]> for Contents {
+    fn from(buf: &[u8]) -> Self {
+        let mut contents = Contents::default();
+        contents
+            .write(0, buf)
+            .expect("a write at offset 0 cannot overflow");
+        contents
+    }
+}
+
+/// Splits `start..end` into `(offset, len)` chunks of at most `CHUNK_LEN`.
+fn chunks(start: u64, end: u64) -> impl Iterator<Item = (u64, u64)> {
+    (start..end)
+        .step_by(CHUNK_LEN as usize)
+        .map(move |offset| (offset, CHUNK_LEN.min(end - offset)))
+}
+
+/// Scrambles `x`, as the finalizer of the SplitMix64 generator does.
+pub fn mix(x: u64) -> u64 {
+    let mut z = x;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
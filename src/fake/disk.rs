@@ -0,0 +1,256 @@
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use super::contents::{mix, Contents};
+use super::node::{Dir, File, Node, Special, Symlink};
+
+/// What `FakeFileSystem::crash` does with changes that were never synced.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum CrashPolicy {
+    /// Every unsynced change is lost.
+    #[default]
+    DiscardUnsynced,
+    /// Each unsynced change is independently kept or lost, and unsynced file
+    /// contents may also be torn, i.e. only partially written. The choices are
+    /// made by a pseudo-random generator seeded with `seed`, so a given seed
+    /// always produces the same outcome.
+    Random { seed: u64 },
+}
+
+/// A directory entry as it is stored on disk. Files refer to their contents by
+/// inode number, so that syncing a file and syncing its directory are separate.
+#[derive(Debug, Clone)]
+enum Entry {
+    File(u64),
+    Dir(Dir),
+    Symlink(Symlink),
+    Special(Special),
+}
+
+impl Entry {
+    fn new(node: &Node) -> Self {
+        match node {
+            Node::File(file) => Entry::File(file.ino),
+            Node::Dir(dir) => Entry::Dir(dir.clone()),
+            Node::Symlink(symlink) => Entry::Symlink(symlink.clone()),
+            Node::Special(special) => Entry::Special(special.clone()),
+        }
+    }
+
+    fn matches(&self, node: &Node) -> bool {
+        match (self, node) {
+            (Entry::File(ino), Node::File(file)) => *ino == file.ino,
+            (Entry::Dir(_), Node::Dir(_)) => true,
+            (Entry::Symlink(durable), Node::Symlink(symlink)) => durable.source == symlink.source,
+            (Entry::Special(durable), Node::Special(special)) => {
+                durable.file_type == special.file_type
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The durable state of a `Registry`: what would survive a crash.
+#[derive(Debug, Clone)]
+pub struct Disk {
+    entries: HashMap<PathBuf, Entry>,
+    contents: HashMap<u64, File>,
+    policy: CrashPolicy,
+    rng: SplitMix64,
+}
+
+impl Disk {
+    /// Returns a disk holding exactly `files`.
+    pub fn new(files: &HashMap<PathBuf, Node>) -> Self {
+        let mut disk = Disk {
+            entries: HashMap::new(),
+            contents: HashMap::new(),
+            policy: CrashPolicy::default(),
+            rng: SplitMix64(0),
+        };
+        disk.sync_all(files);
+        disk
+    }
+
+    pub fn set_policy(&mut self, policy: CrashPolicy) {
+        self.policy = policy;
+        if let CrashPolicy::Random { seed } = policy {
+            self.rng = SplitMix64(seed);
+        }
+    }
+
+    pub fn sync_all(&mut self, files: &HashMap<PathBuf, Node>) {
+        self.entries = files
+            .iter()
+            .map(|(path, node)| (path.clone(), Entry::new(node)))
+            .collect();
+        self.contents = files
+            .values()
+            .filter_map(|node| match node {
+                Node::File(file) => Some((file.ino, file.clone())),
+                _ => None,
+            })
+            .collect();
+    }
+
+    /// Makes the contents and metadata of `file` durable, but not its name.
+    pub fn sync_file(&mut self, file: &File) {
+        self.contents.insert(file.ino, file.clone());
+    }
+
+    /// Makes the entries of the directory `dir` durable, but not the contents
+    /// of the files it holds.
+    pub fn sync_dir(&mut self, files: &HashMap<PathBuf, Node>, dir: &Path) {
+        let stale: Vec<PathBuf> = self
+            .entries
+            .iter()
+            .filter(|&(path, entry)| {
+                path.parent() == Some(dir)
+                    && files.get(path).is_none_or(|node| !entry.matches(node))
+            })
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in stale {
+            self.entries.retain(|other, _| !other.starts_with(&path));
+        }
+
+        for (path, node) in files {
+            if path.parent() == Some(dir) || (path == dir && self.entries.contains_key(path)) {
+                self.entries.insert(path.clone(), Entry::new(node));
+            }
+        }
+    }
+
+    /// Returns what is left of `files` after a crash, according to the policy,
+    /// and makes it the new durable state.
+    pub fn crash(&mut self, files: &HashMap<PathBuf, Node>) -> HashMap<PathBuf, Node> {
+        let paths: BTreeSet<PathBuf> = self.entries.keys().chain(files.keys()).cloned().collect();
+        let mut survivors: BTreeMap<PathBuf, Entry> = BTreeMap::new();
+
+        for path in paths {
+            let entry = match (self.entries.get(&path).cloned(), files.get(&path)) {
+                (Some(entry), Some(node)) if entry.matches(node) => Entry::new(node),
+                (durable, volatile) => {
+                    let entry = if self.keep_unsynced() {
+                        volatile.map(Entry::new)
+                    } else {
+                        durable
+                    };
+                    match entry {
+                        Some(entry) => entry,
+                        None => continue,
+                    }
+                }
+            };
+
+            // Parents sort before their children, so orphans can be spotted
+            // in a single pass.
+            let orphaned = match path.parent() {
+                Some(parent) => !matches!(survivors.get(parent), Some(Entry::Dir(_))),
+                None => false,
+            };
+            if !orphaned {
+                survivors.insert(path, entry);
+            }
+        }
+
+        let volatile: HashMap<u64, &File> = files
+            .values()
+            .filter_map(|node| match node {
+                Node::File(file) => Some((file.ino, file)),
+                _ => None,
+            })
+            .collect();
+
+        let mut result = HashMap::new();
+        for (path, entry) in survivors {
+            let node = match entry {
+                Entry::File(ino) => Node::File(self.recover(ino, volatile.get(&ino).cloned())),
+                Entry::Dir(dir) => Node::Dir(dir),
+                Entry::Symlink(symlink) => Node::Symlink(symlink),
+                Entry::Special(special) => Node::Special(special),
+            };
+            result.insert(path, node);
+        }
+
+        self.sync_all(&result);
+        result
+    }
+
+    /// Returns the contents of inode `ino` after a crash.
+    fn recover(&mut self, ino: u64, volatile: Option<&File>) -> File {
+        let durable = match self.contents.get(&ino) {
+            Some(file) => file.clone(),
+            // The name was synced, but the contents never were.
+            None => {
+                let mut file = volatile
+                    .cloned()
+                    .unwrap_or_else(|| File::new(Contents::default()));
+                file.ino = ino;
+                file.contents = Contents::default();
+                file
+            }
+        };
+        let volatile = match volatile {
+            Some(file) if !file.contents.same_bytes(&durable.contents) => file,
+            _ => return durable,
+        };
+
+        match self.policy {
+            CrashPolicy::DiscardUnsynced => durable,
+            CrashPolicy::Random { .. } => match self.rng.next() % 3 {
+                0 => durable,
+                1 => volatile.clone(),
+                _ => {
+                    // A torn write: a prefix of the new contents made it to
+                    // disk, the rest still holds the old contents.
+                    let cut = self.rng.next() % (volatile.contents.len() + 1);
+                    File {
+                        contents: volatile.contents.torn(&durable.contents, cut),
+                        ..volatile.clone()
+                    }
+                }
+            },
+        }
+    }
+
+    fn keep_unsynced(&mut self) -> bool {
+        match self.policy {
+            CrashPolicy::DiscardUnsynced => false,
+            CrashPolicy::Random { .. } => self.rng.next() % 2 == 0,
+        }
+    }
+}
+
+/// A small, seedable pseudo-random generator, so that the fake does not need
+/// the `rand` crate.
+#[derive(Debug, Clone)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        mix(self.0)
+    }
+}
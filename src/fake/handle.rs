@@ -0,0 +1,53 @@
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, Weak};
+
+use super::Registry;
+
+/// An open handle on a node of a `FakeFileSystem`, which is closed when
+/// dropped. See `FakeFileSystem::open_handle`.
+#[derive(Debug)]
+pub struct FakeHandle {
+    registry: Weak<Mutex<Registry>>,
+    path: PathBuf,
+}
+
+impl FakeHandle {
+    pub(crate) fn new(registry: Weak<Mutex<Registry>>, path: PathBuf) -> Self {
+        FakeHandle { registry, path }
+    }
+
+    /// Returns the path of the node the handle is open on, with every symlink
+    /// resolved.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for FakeHandle {
+    fn drop(&mut self) {
+        if let Some(registry) = self.registry.upgrade() {
+            registry.lock().unwrap().close_handle(&self.path);
+        }
+    }
+}
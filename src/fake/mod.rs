@@ -19,29 +19,45 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use std::borrow::Cow;
 use std::env;
 use std::ffi::{OsStr, OsString};
 use std::io::Result;
 use std::iter::Iterator;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
 use std::vec::IntoIter;
 
-use FileSystem;
 #[cfg(unix)]
 use UnixFileSystem;
+use {CopyOptions, FileSystem, FileType, Space};
 #[cfg(feature = "temp")]
 use {TempDir, TempFileSystem};
 
+pub use self::case::{CaseFolding, CaseSensitivity};
+pub use self::contents::{ContentSource, DynamicFile};
+pub use self::disk::CrashPolicy;
+pub use self::handle::FakeHandle;
+pub use self::normalization::Normalization;
 #[cfg(feature = "temp")]
 pub use self::tempdir::FakeTempDir;
+pub use self::volume::FakeVolume;
+pub use self::windows::Flavor;
 
 use self::registry::Registry;
 
+mod case;
+mod contents;
+mod disk;
+mod handle;
 mod node;
+mod normalization;
 mod registry;
 #[cfg(feature = "temp")]
 mod tempdir;
+mod volume;
+mod windows;
 
 /// An in-memory file system.
 #[derive(Clone, Debug, Default)]
@@ -57,23 +73,213 @@ impl FakeFileSystem {
         }
     }
 
+    /// Returns the maximum number of symlinks followed while resolving a path.
+    pub fn max_symlink_depth(&self) -> usize {
+        self.registry.lock().unwrap().max_symlink_depth()
+    }
+
+    /// Sets the maximum number of symlinks followed while resolving a path.
+    /// Exceeding it fails with `ErrorKind::FilesystemLoop`. Defaults to 40, as on Linux.
+    pub fn set_max_symlink_depth(&self, depth: usize) {
+        self.registry.lock().unwrap().set_max_symlink_depth(depth)
+    }
+
+    /// Sets the maximum length of a name in bytes. Looking up or creating a
+    /// longer name fails with `ErrorKind::InvalidFilename`. Defaults to 255,
+    /// as on Linux.
+    pub fn set_max_name_len(&self, len: usize) {
+        self.registry.lock().unwrap().set_max_name_len(len)
+    }
+
+    /// Sets the maximum length of an absolute path in bytes, including the
+    /// terminating NUL as `PATH_MAX` does. Longer paths fail with
+    /// `ErrorKind::InvalidFilename`. Defaults to 4096, as on Linux.
+    pub fn set_max_path_len(&self, len: usize) {
+        self.registry.lock().unwrap().set_max_path_len(len)
+    }
+
+    /// Creates a file of `len` bytes whose contents come from `source`.
+    ///
+    /// The contents are never stored as a whole: `len` and reads only compute
+    /// the bytes asked for, and writes store just the extents they touch. This
+    /// makes it possible to test code against files far larger than memory,
+    /// as long as it reads them in ranges.
+    ///
+    /// # Errors
+    ///
+    /// Same as `create_file`.
+    pub fn create_generated_file<P: AsRef<Path>>(
+        &self,
+        path: P,
+        len: u64,
+        source: ContentSource,
+    ) -> Result<()> {
+        self.apply_mut(path.as_ref(), |r, p| {
+            r.create_generated_file(p, len, source.clone())
+        })
+    }
+
+    /// Creates a file whose contents are computed anew on every access, to
+    /// emulate files such as those under `/proc`, `/sys` or `/dev`.
+    ///
+    /// The file is read-only unless `dynamic` has a write handler. Writes are
+    /// handed to the handler instead of being stored, and truncating the file
+    /// has no effect. The callbacks run while the file system is locked, so
+    /// they must not call back into it.
+    ///
+    /// # Errors
+    ///
+    /// Same as `create_file`.
+    pub fn create_dynamic_file<P: AsRef<Path>>(&self, path: P, dynamic: DynamicFile) -> Result<()> {
+        self.apply_mut(path.as_ref(), |r, p| {
+            r.create_dynamic_file(p, dynamic.clone())
+        })
+    }
+
+    /// Sets whether names that differ only in case refer to the same node.
+    /// Defaults to `CaseSensitivity::Sensitive`.
+    ///
+    /// Case-insensitive names keep the spelling they were created with, which
+    /// `read_dir` reports. Set this before creating nodes whose names would
+    /// collide, since only one of them can be reached afterwards.
+    pub fn set_case_sensitivity(&self, case: CaseSensitivity) {
+        self.registry.lock().unwrap().set_case_sensitivity(case)
+    }
+
+    /// Sets how names that only differ in Unicode normalization are treated.
+    /// Defaults to `Normalization::Preserve`.
+    ///
+    /// Set this before creating nodes, since names stored under one policy
+    /// are not converted when switching to another.
+    pub fn set_normalization(&self, normalization: Normalization) {
+        self.registry
+            .lock()
+            .unwrap()
+            .set_normalization(normalization)
+    }
+
+    /// Sets whose rules paths follow and nodes are removed by.
+    /// Defaults to `Flavor::Unix`.
+    ///
+    /// With `Flavor::Windows`, drives are top-level directories that must be
+    /// created before use, as in `create_dir("C:\\")`. Windows file systems
+    /// are usually case-insensitive too, which `set_case_sensitivity` sets.
+    pub fn set_flavor(&self, flavor: Flavor) {
+        self.registry.lock().unwrap().set_flavor(flavor)
+    }
+
+    /// Sets the uid and gid of the user accessing the file system, which own
+    /// the nodes it creates from then on. Permissions are checked against the
+    /// owner, group or other bits of a node's mode depending on them. Defaults
+    /// to uid 1000 and gid 1000, which own the root directory. Uid 0 is root,
+    /// which bypasses every check but executing files without an execute bit
+    /// and may give nodes away with `chown`.
+    pub fn set_user(&self, uid: u32, gid: u32) {
+        self.registry.lock().unwrap().set_user(uid, gid)
+    }
+
+    /// Returns the device number a character or block device at `path` was
+    /// created with by `mknod`, following symlinks, or 0 for other nodes.
+    /// This is like the `st_rdev` field of `stat(2)`.
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist.
+    /// * Current user has insufficient permissions.
+    pub fn device_number<P: AsRef<Path>>(&self, path: P) -> Result<u64> {
+        self.apply(path.as_ref(), |r, p| r.device_number(p))
+    }
+
+    /// Opens a handle on the node at `path`, following symlinks, which stays
+    /// open until the returned `FakeHandle` is dropped.
+    ///
+    /// With `Flavor::Windows`, the node and the directories above it cannot
+    /// be removed or renamed while the handle is open, as with a handle that
+    /// does not share delete access. Otherwise handles have no effect.
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist.
+    pub fn open_handle<P: AsRef<Path>>(&self, path: P) -> Result<FakeHandle> {
+        let registry = Arc::downgrade(&self.registry);
+        self.apply_mut(path.as_ref(), |r, p| r.open_handle(p))
+            .map(|path| FakeHandle::new(registry, path))
+    }
+
+    /// Limits the total length of the files on the root volume, that is of all
+    /// files outside other mounts. `None`, the default, means no limit.
+    /// Writes that would exceed it fail with `ErrorKind::StorageFull`.
+    pub fn set_capacity(&self, capacity: Option<u64>) {
+        self.registry.lock().unwrap().set_capacity(capacity)
+    }
+
+    /// Mounts `volume` on the directory at `path`, so that the nodes below it
+    /// are on a separate file system: renaming across the mount point fails
+    /// with `ErrorKind::CrossesDevices`, and removing or renaming the mount
+    /// point fails with `ErrorKind::ResourceBusy`.
+    ///
+    /// Nodes already below `path` become the contents of the volume, which
+    /// makes it possible to populate a read-only volume before mounting it.
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist.
+    /// * `path` is not a directory.
+    /// * A volume is already mounted at `path`.
+    pub fn mount<P: AsRef<Path>>(&self, path: P, volume: FakeVolume) -> Result<()> {
+        self.apply_mut(path.as_ref(), |r, p| r.mount(p, volume.clone()))
+    }
+
+    /// Limits the number of files, directories and symlinks, including the
+    /// root. `None`, the default, means no limit.
+    /// Creating a node beyond it fails with `ErrorKind::StorageFull`.
+    pub fn set_max_inodes(&self, max_inodes: Option<u64>) {
+        self.registry.lock().unwrap().set_max_inodes(max_inodes)
+    }
+
+    /// Limits the total length of the files below the directory at `path`,
+    /// or removes the limit if `quota` is `None`.
+    /// Writes and renames that would exceed it fail with
+    /// `ErrorKind::QuotaExceeded`.
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist.
+    /// * `path` is not a directory.
+    pub fn set_quota<P: AsRef<Path>>(&self, path: P, quota: Option<u64>) -> Result<()> {
+        self.apply_mut(path.as_ref(), |r, p| r.set_quota(p, quota))
+    }
+
+    /// Sets what `crash` does with unsynced changes.
+    /// Defaults to `CrashPolicy::DiscardUnsynced`.
+    pub fn set_crash_policy(&self, policy: CrashPolicy) {
+        self.registry.lock().unwrap().set_crash_policy(policy)
+    }
+
+    /// Makes every change so far durable, as if each file and directory had
+    /// been synced. Useful to set up a test before simulating crashes.
+    pub fn sync_all(&self) {
+        self.registry.lock().unwrap().sync_all()
+    }
+
+    /// Simulates a power loss.
+    ///
+    /// Changes only reach durable storage once synced: file contents and
+    /// metadata through `sync`, and the creation, removal and renaming of
+    /// entries through `sync_dir` on their parent. Everything else is
+    /// discarded or partially kept, according to the crash policy.
+    pub fn crash(&self) {
+        self.registry.lock().unwrap().crash()
+    }
+
     fn apply<F, T>(&self, path: &Path, f: F) -> T
     where
         F: FnOnce(&MutexGuard<Registry>, &Path) -> T,
     {
         let registry = self.registry.lock().unwrap();
-        let storage;
-        let path = if path.is_relative() {
-            storage = registry
-                .current_dir()
-                .unwrap_or_else(|_| PathBuf::from("/"))
-                .join(path);
-            &storage
-        } else {
-            path
-        };
+        let path = absolute(&registry, path);
 
-        f(&registry, path)
+        f(&registry, &path)
     }
 
     fn apply_mut<F, T>(&self, path: &Path, mut f: F) -> T
@@ -81,18 +287,9 @@ impl FakeFileSystem {
         F: FnMut(&mut MutexGuard<Registry>, &Path) -> T,
     {
         let mut registry = self.registry.lock().unwrap();
-        let storage;
-        let path = if path.is_relative() {
-            storage = registry
-                .current_dir()
-                .unwrap_or_else(|_| PathBuf::from("/"))
-                .join(path);
-            &storage
-        } else {
-            path
-        };
+        let path = absolute(&registry, path);
 
-        f(&mut registry, path)
+        f(&mut registry, &path)
     }
 
     fn apply_mut_from_to<F, T>(&self, from: &Path, to: &Path, mut f: F) -> T
@@ -100,31 +297,29 @@ impl FakeFileSystem {
         F: FnMut(&mut MutexGuard<Registry>, &Path, &Path) -> T,
     {
         let mut registry = self.registry.lock().unwrap();
-        let from_storage;
-        let from = if from.is_relative() {
-            from_storage = registry
-                .current_dir()
-                .unwrap_or_else(|_| PathBuf::from("/"))
-                .join(from);
-            &from_storage
-        } else {
-            from
-        };
-        let to_storage;
-        let to = if to.is_relative() {
-            to_storage = registry
-                .current_dir()
-                .unwrap_or_else(|_| PathBuf::from("/"))
-                .join(to);
-            &to_storage
-        } else {
-            to
-        };
-
-        f(&mut registry, from, to)
+        let from = absolute(&registry, from);
+        let to = absolute(&registry, to);
+
+        f(&mut registry, &from, &to)
     }
 }
 
+/// Returns `path` as stored in `registry`, relative to its current directory.
+fn absolute<'a>(registry: &Registry, path: &'a Path) -> Cow<'a, Path> {
+    let flavor = registry.flavor();
+    if path.is_relative() || flavor == Flavor::Windows {
+        let cwd = registry
+            .current_dir()
+            .unwrap_or_else(|_| PathBuf::from("/"));
+        return Cow::Owned(match flavor {
+            Flavor::Unix => cwd.join(path),
+            Flavor::Windows => windows::translate(path, &cwd),
+        });
+    }
+
+    Cow::Borrowed(path)
+}
+
 impl FileSystem for FakeFileSystem {
     type DirEntry = DirEntry;
     type ReadDir = ReadDir;
@@ -146,6 +341,14 @@ impl FileSystem for FakeFileSystem {
         self.apply(path.as_ref(), |r, p| r.is_file(p))
     }
 
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.apply(path.as_ref(), |r, p| r.exists(p))
+    }
+
+    fn try_exists<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        self.apply(path.as_ref(), |r, p| r.try_exists(p))
+    }
+
     fn create_dir<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         self.apply_mut(path.as_ref(), |r, p| r.create_dir(p))
     }
@@ -168,10 +371,10 @@ impl FileSystem for FakeFileSystem {
         self.apply(path, |r, p| r.read_dir(p)).map(|entries| {
             let entries = entries
                 .iter()
-                .map(|e| {
+                .map(|(e, file_type)| {
                     let file_name = e.file_name().unwrap_or_else(|| e.as_os_str());
 
-                    Ok(DirEntry::new(path, &file_name))
+                    Ok(DirEntry::new(path, &file_name, *file_type))
                 })
                 .collect();
 
@@ -203,10 +406,62 @@ impl FileSystem for FakeFileSystem {
         self.apply_mut(path.as_ref(), |r, p| r.overwrite_file(p, buf.as_ref()))
     }
 
+    fn append_file<P, B>(&self, path: P, buf: B) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        self.apply_mut(path.as_ref(), |r, p| r.append_file(p, buf.as_ref()))
+    }
+
+    fn append_existing_file<P, B>(&self, path: P, buf: B) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        self.apply_mut(path.as_ref(), |r, p| {
+            r.append_existing_file(p, buf.as_ref())
+        })
+    }
+
+    fn write_file_atomic<P, B>(&self, path: P, buf: B) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        self.apply_mut(path.as_ref(), |r, p| r.write_file_atomic(p, buf.as_ref()))
+    }
+
+    fn sync<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.apply_mut(path.as_ref(), |r, p| r.sync(p))
+    }
+
+    fn sync_dir<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.apply_mut(path.as_ref(), |r, p| r.sync_dir(p))
+    }
+
     fn read_file<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
         self.apply(path.as_ref(), |r, p| r.read_file(p))
     }
 
+    fn read_file_range<P: AsRef<Path>>(&self, path: P, offset: u64, len: u64) -> Result<Vec<u8>> {
+        self.apply(path.as_ref(), |r, p| r.read_file_range(p, offset, len))
+    }
+
+    fn write_file_at<P, B>(&self, path: P, offset: u64, buf: B) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        self.apply_mut(path.as_ref(), |r, p| {
+            r.write_file_at(p, offset, buf.as_ref())
+        })
+    }
+
+    fn set_len<P: AsRef<Path>>(&self, path: P, size: u64) -> Result<()> {
+        self.apply_mut(path.as_ref(), |r, p| r.set_len(p, size))
+    }
+
     fn read_file_to_string<P: AsRef<Path>>(&self, path: P) -> Result<String> {
         self.apply(path.as_ref(), |r, p| r.read_file_to_string(p))
     }
@@ -233,6 +488,16 @@ impl FileSystem for FakeFileSystem {
         })
     }
 
+    fn copy_dir_all<P, Q>(&self, from: P, to: Q, options: &CopyOptions) -> Result<()>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        self.apply_mut_from_to(from.as_ref(), to.as_ref(), |r, from, to| {
+            r.copy_dir_all(from, to, options)
+        })
+    }
+
     fn rename<P, Q>(&self, from: P, to: Q) -> Result<()>
     where
         P: AsRef<Path>,
@@ -252,16 +517,29 @@ impl FileSystem for FakeFileSystem {
     fn len<P: AsRef<Path>>(&self, path: P) -> u64 {
         self.apply(path.as_ref(), |r, p| r.len(p))
     }
+
+    fn modified<P: AsRef<Path>>(&self, path: P) -> Result<SystemTime> {
+        self.apply(path.as_ref(), |r, p| r.modified(p))
+    }
+
+    fn set_modified<P: AsRef<Path>>(&self, path: P, time: SystemTime) -> Result<()> {
+        self.apply_mut(path.as_ref(), |r, p| r.set_modified(p, time))
+    }
+
+    fn space<P: AsRef<Path>>(&self, path: P) -> Result<Space> {
+        self.apply(path.as_ref(), |r, p| r.space(p))
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct DirEntry {
     parent: PathBuf,
     file_name: OsString,
+    file_type: FileType,
 }
 
 impl DirEntry {
-    fn new<P, S>(parent: P, file_name: S) -> Self
+    fn new<P, S>(parent: P, file_name: S, file_type: FileType) -> Self
     where
         P: AsRef<Path>,
         S: AsRef<OsStr>,
@@ -269,6 +547,7 @@ impl DirEntry {
         DirEntry {
             parent: parent.as_ref().to_path_buf(),
             file_name: file_name.as_ref().to_os_string(),
+            file_type,
         }
     }
 }
@@ -281,6 +560,10 @@ impl crate::DirEntry for DirEntry {
     fn path(&self) -> PathBuf {
         self.parent.join(&self.file_name)
     }
+
+    fn file_type(&self) -> Result<FileType> {
+        Ok(self.file_type)
+    }
 }
 
 #[derive(Debug)]
@@ -313,21 +596,147 @@ impl UnixFileSystem for FakeFileSystem {
     }
 
     fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(&self, src: P, dst: Q) -> Result<()> {
-        self.apply_mut_from_to(src.as_ref(), dst.as_ref(), |r, src, dst| {
-            r.symlink(src, dst)
-        })
+        self.apply_mut(dst.as_ref(), |r, dst| r.symlink(src.as_ref(), dst))
     }
 
     fn get_symlink_src<P: AsRef<Path>>(&self, dst: P) -> Result<PathBuf> {
         return self.apply(dst.as_ref(), |r, p| r.read_link(p));
     }
+
+    fn is_symlink<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.apply(path.as_ref(), |r, p| r.is_symlink(p))
+    }
+
+    fn symlink_exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.apply(path.as_ref(), |r, p| r.symlink_exists(p))
+    }
+
+    fn device<P: AsRef<Path>>(&self, path: P) -> Result<u64> {
+        self.apply(path.as_ref(), |r, p| r.device(p))
+    }
+
+    fn access<P: AsRef<Path>>(&self, path: P, mode: u32) -> Result<()> {
+        self.apply(path.as_ref(), |r, p| r.access(p, mode))
+    }
+
+    fn owner<P: AsRef<Path>>(&self, path: P) -> Result<(u32, u32)> {
+        self.apply(path.as_ref(), |r, p| r.owner(p, true))
+    }
+
+    fn chown<P: AsRef<Path>>(&self, path: P, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+        self.apply_mut(path.as_ref(), |r, p| r.chown(p, uid, gid, true))
+    }
+
+    fn lchown<P: AsRef<Path>>(&self, path: P, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+        self.apply_mut(path.as_ref(), |r, p| r.chown(p, uid, gid, false))
+    }
+
+    fn umask(&self) -> u32 {
+        self.registry.lock().unwrap().umask()
+    }
+
+    fn set_umask(&self, umask: u32) {
+        self.registry.lock().unwrap().set_umask(umask)
+    }
+
+    fn create_dir_with_mode<P: AsRef<Path>>(&self, path: P, mode: u32) -> Result<()> {
+        self.apply_mut(path.as_ref(), |r, p| r.create_dir_with_mode(p, mode))
+    }
+
+    fn create_file_with_mode<P, B>(&self, path: P, buf: B, mode: u32) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        self.apply_mut(path.as_ref(), |r, p| {
+            r.create_file_with_mode(p, buf.as_ref(), mode)
+        })
+    }
+
+    fn get_xattr<P, N>(&self, path: P, name: N) -> Result<Vec<u8>>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>,
+    {
+        self.apply(path.as_ref(), |r, p| r.get_xattr(p, name.as_ref(), true))
+    }
+
+    fn lget_xattr<P, N>(&self, path: P, name: N) -> Result<Vec<u8>>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>,
+    {
+        self.apply(path.as_ref(), |r, p| r.get_xattr(p, name.as_ref(), false))
+    }
+
+    fn set_xattr<P, N, V>(&self, path: P, name: N, value: V) -> Result<()>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>,
+        V: AsRef<[u8]>,
+    {
+        self.apply_mut(path.as_ref(), |r, p| {
+            r.set_xattr(p, name.as_ref(), value.as_ref(), true)
+        })
+    }
+
+    fn lset_xattr<P, N, V>(&self, path: P, name: N, value: V) -> Result<()>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>,
+        V: AsRef<[u8]>,
+    {
+        self.apply_mut(path.as_ref(), |r, p| {
+            r.set_xattr(p, name.as_ref(), value.as_ref(), false)
+        })
+    }
+
+    fn list_xattrs<P: AsRef<Path>>(&self, path: P) -> Result<Vec<OsString>> {
+        self.apply(path.as_ref(), |r, p| r.list_xattrs(p, true))
+    }
+
+    fn llist_xattrs<P: AsRef<Path>>(&self, path: P) -> Result<Vec<OsString>> {
+        self.apply(path.as_ref(), |r, p| r.list_xattrs(p, false))
+    }
+
+    fn remove_xattr<P, N>(&self, path: P, name: N) -> Result<()>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>,
+    {
+        self.apply_mut(path.as_ref(), |r, p| r.remove_xattr(p, name.as_ref(), true))
+    }
+
+    fn lremove_xattr<P, N>(&self, path: P, name: N) -> Result<()>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>,
+    {
+        self.apply_mut(path.as_ref(), |r, p| {
+            r.remove_xattr(p, name.as_ref(), false)
+        })
+    }
+
+    fn mkfifo<P: AsRef<Path>>(&self, path: P, mode: u32) -> Result<()> {
+        self.apply_mut(path.as_ref(), |r, p| r.mknod(p, FileType::Fifo, mode, 0))
+    }
+
+    fn mknod<P: AsRef<Path>>(
+        &self,
+        path: P,
+        file_type: FileType,
+        mode: u32,
+        dev: u64,
+    ) -> Result<()> {
+        self.apply_mut(path.as_ref(), |r, p| r.mknod(p, file_type, mode, dev))
+    }
 }
 
 #[cfg(feature = "temp")]
 impl TempFileSystem for FakeFileSystem {
     type TempDir = FakeTempDir;
 
-    fn temp_dir<S: AsRef<str>>(&self, prefix: S) -> Result<Self::TempDir> {
+    fn temp_dir_os<S: AsRef<OsStr>>(&self, prefix: S) -> Result<Self::TempDir> {
         let base = env::temp_dir();
         let dir = FakeTempDir::new(Arc::downgrade(&self.registry), &base, prefix.as_ref());
 
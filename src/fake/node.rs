@@ -19,46 +19,193 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use crate::fake::contents::{Contents, DynamicFile};
 use crate::fake::registry::Registry;
+use crate::FileType;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::io::Result;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+static NEXT_INO: AtomicU64 = AtomicU64::new(1);
+
+/// Extended attributes by name. Every kind of node can have them.
+pub type Xattrs = BTreeMap<OsString, Vec<u8>>;
 
 #[derive(Debug, Clone)]
 pub struct File {
-    pub contents: Vec<u8>,
+    /// Identifies the file across renames.
+    pub ino: u64,
+    pub contents: Contents,
+    /// Computes the contents instead of `contents`, if set.
+    pub dynamic: Option<DynamicFile>,
+    /// Permission bits, which the registry masks with the umask of the
+    /// creating process. The same goes for directories, but not symlinks.
     pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub modified: SystemTime,
+    pub xattrs: Xattrs,
 }
 
 impl File {
-    pub fn new(contents: Vec<u8>) -> Self {
+    pub fn new(contents: Contents) -> Self {
         File {
+            ino: NEXT_INO.fetch_add(1, Ordering::Relaxed),
             contents,
-            mode: 0o644,
+            dynamic: None,
+            mode: 0o666,
+            uid: 0,
+            gid: 0,
+            modified: SystemTime::now(),
+            xattrs: Xattrs::new(),
+        }
+    }
+
+    pub fn new_dynamic(dynamic: DynamicFile) -> Self {
+        File {
+            mode: if dynamic.is_writable() { 0o666 } else { 0o444 },
+            dynamic: Some(dynamic),
+            ..File::new(Contents::default())
+        }
+    }
+
+    /// Returns the current contents.
+    pub fn snapshot(&self) -> Contents {
+        match self.dynamic {
+            Some(ref dynamic) => dynamic.read(),
+            None => self.contents.clone(),
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        match self.dynamic {
+            Some(ref dynamic) => dynamic.read().len(),
+            None => self.contents.len(),
+        }
+    }
+
+    pub fn read(&self, offset: u64, len: u64) -> Vec<u8> {
+        match self.dynamic {
+            Some(ref dynamic) => dynamic.read().read(offset, len),
+            None => self.contents.read(offset, len),
+        }
+    }
+
+    /// Replaces the contents. Dynamic files hand them to their write handler.
+    pub fn set_contents(&mut self, contents: Contents) -> Result<()> {
+        match self.dynamic {
+            Some(ref dynamic) => dynamic.write(&contents.to_vec())?,
+            None => self.contents = contents,
+        }
+        self.modified = SystemTime::now();
+        Ok(())
+    }
+
+    /// Writes `buf` at `offset`. Dynamic files hand it to their write handler.
+    pub fn write(&mut self, offset: u64, buf: &[u8]) -> Result<()> {
+        match self.dynamic {
+            Some(ref dynamic) => dynamic.write(buf)?,
+            None => self.contents.write(offset, buf)?,
+        }
+        self.modified = SystemTime::now();
+        Ok(())
+    }
+
+    pub fn append(&mut self, buf: &[u8]) -> Result<()> {
+        let len = if self.dynamic.is_some() {
+            0
+        } else {
+            self.contents.len()
+        };
+        self.write(len, buf)
+    }
+
+    /// Truncates or extends the file. Dynamic files ignore this, like the
+    /// `O_TRUNC` that comes with writing to a file in `/sys`.
+    pub fn set_len(&mut self, len: u64) {
+        if self.dynamic.is_none() {
+            self.contents.set_len(len);
         }
+        self.modified = SystemTime::now();
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct Dir {
     pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub modified: SystemTime,
+    pub xattrs: Xattrs,
 }
 
 impl Dir {
     pub fn new() -> Self {
-        Dir { mode: 0o644 }
+        Dir {
+            mode: 0o777,
+            uid: 0,
+            gid: 0,
+            modified: SystemTime::now(),
+            xattrs: Xattrs::new(),
+        }
+    }
+}
+
+impl Default for Dir {
+    fn default() -> Self {
+        Dir::new()
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct Symlink {
     pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
     pub source: PathBuf,
+    pub modified: SystemTime,
+    pub xattrs: Xattrs,
 }
 
 impl Symlink {
     pub fn new(source: PathBuf) -> Self {
         Symlink {
-            mode: 0o644,
+            mode: 0o777,
+            uid: 0,
+            gid: 0,
             source,
+            modified: SystemTime::now(),
+            xattrs: Xattrs::new(),
+        }
+    }
+}
+
+/// A node without contents: a named pipe, a socket or a device.
+#[derive(Debug, Clone)]
+pub struct Special {
+    pub file_type: FileType,
+    /// The device number of a character or block device, 0 otherwise.
+    pub dev: u64,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub modified: SystemTime,
+    pub xattrs: Xattrs,
+}
+
+impl Special {
+    pub fn new(file_type: FileType) -> Self {
+        Special {
+            file_type,
+            dev: 0,
+            mode: 0o666,
+            uid: 0,
+            gid: 0,
+            modified: SystemTime::now(),
+            xattrs: Xattrs::new(),
         }
     }
 }
@@ -68,6 +215,7 @@ pub enum Node {
     File(File),
     Dir(Dir),
     Symlink(Symlink),
+    Special(Special),
 }
 
 impl Node {
@@ -86,4 +234,86 @@ impl Node {
             _ => false,
         }
     }
+
+    pub fn mode(&self) -> u32 {
+        match self {
+            Self::File(file) => file.mode,
+            Self::Dir(dir) => dir.mode,
+            Self::Symlink(symlink) => symlink.mode,
+            Self::Special(special) => special.mode,
+        }
+    }
+
+    pub fn set_mode(&mut self, mode: u32) {
+        match self {
+            Self::File(file) => file.mode = mode,
+            Self::Dir(dir) => dir.mode = mode,
+            Self::Symlink(symlink) => symlink.mode = mode,
+            Self::Special(special) => special.mode = mode,
+        }
+    }
+
+    /// Returns the uid and gid of the owner.
+    pub fn owner(&self) -> (u32, u32) {
+        match self {
+            Self::File(file) => (file.uid, file.gid),
+            Self::Dir(dir) => (dir.uid, dir.gid),
+            Self::Symlink(symlink) => (symlink.uid, symlink.gid),
+            Self::Special(special) => (special.uid, special.gid),
+        }
+    }
+
+    pub fn file_type(&self) -> FileType {
+        match self {
+            Self::File(_) => FileType::File,
+            Self::Dir(_) => FileType::Dir,
+            Self::Symlink(_) => FileType::Symlink,
+            Self::Special(special) => special.file_type,
+        }
+    }
+
+    pub fn set_owner(&mut self, uid: u32, gid: u32) {
+        match self {
+            Self::File(file) => (file.uid, file.gid) = (uid, gid),
+            Self::Dir(dir) => (dir.uid, dir.gid) = (uid, gid),
+            Self::Symlink(symlink) => (symlink.uid, symlink.gid) = (uid, gid),
+            Self::Special(special) => (special.uid, special.gid) = (uid, gid),
+        }
+    }
+
+    pub fn modified(&self) -> SystemTime {
+        match self {
+            Self::File(file) => file.modified,
+            Self::Dir(dir) => dir.modified,
+            Self::Symlink(symlink) => symlink.modified,
+            Self::Special(special) => special.modified,
+        }
+    }
+
+    pub fn xattrs(&self) -> &Xattrs {
+        match self {
+            Self::File(file) => &file.xattrs,
+            Self::Dir(dir) => &dir.xattrs,
+            Self::Symlink(symlink) => &symlink.xattrs,
+            Self::Special(special) => &special.xattrs,
+        }
+    }
+
+    pub fn xattrs_mut(&mut self) -> &mut Xattrs {
+        match self {
+            Self::File(file) => &mut file.xattrs,
+            Self::Dir(dir) => &mut dir.xattrs,
+            Self::Symlink(symlink) => &mut symlink.xattrs,
+            Self::Special(special) => &mut special.xattrs,
+        }
+    }
+
+    pub fn set_modified(&mut self, modified: SystemTime) {
+        match self {
+            Self::File(file) => file.modified = modified,
+            Self::Dir(dir) => dir.modified = modified,
+            Self::Symlink(symlink) => symlink.modified = modified,
+            Self::Special(special) => special.modified = modified,
+        }
+    }
 }
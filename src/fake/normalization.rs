@@ -0,0 +1,66 @@
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+
+use unicode_normalization::{is_nfd, UnicodeNormalization};
+
+/// How `FakeFileSystem` treats names that are equivalent under Unicode
+/// normalization, such as `é` as one code point (NFC) or as `e` followed by a
+/// combining accent (NFD). See `FakeFileSystem::set_normalization`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Normalization {
+    /// Names are stored and compared as is, as on Linux.
+    #[default]
+    Preserve,
+    /// Names are converted to NFD when stored and looked up, as on HFS+, so
+    /// `read_dir` returns them decomposed whatever form they were created in.
+    Nfd,
+    /// Names are stored as is but compared in NFD, as on APFS.
+    Insensitive,
+}
+
+impl Normalization {
+    /// Returns `name` in the form it is stored in.
+    pub(crate) fn stored<'a>(&self, name: &'a OsStr) -> Cow<'a, OsStr> {
+        match *self {
+            Normalization::Nfd => nfd(name),
+            Normalization::Preserve | Normalization::Insensitive => Cow::Borrowed(name),
+        }
+    }
+
+    /// Returns `name` in the form it is compared in.
+    pub(crate) fn compared<'a>(&self, name: &'a OsStr) -> Cow<'a, OsStr> {
+        match *self {
+            Normalization::Preserve => Cow::Borrowed(name),
+            Normalization::Nfd | Normalization::Insensitive => nfd(name),
+        }
+    }
+}
+
+/// Decomposes `name`, leaving names that are not valid Unicode alone.
+fn nfd(name: &OsStr) -> Cow<'_, OsStr> {
+    match name.to_str() {
+        Some(s) if !is_nfd(s) => Cow::Owned(OsString::from(s.nfd().collect::<String>())),
+        _ => Cow::Borrowed(name),
+    }
+}
@@ -19,16 +19,81 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
 use std::io::{Error, ErrorKind, Result};
 use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
 
-use super::node::{Dir, File, Node, Symlink};
+use super::case::CaseSensitivity;
+use super::contents::{ContentSource, Contents, DynamicFile};
+use super::disk::{CrashPolicy, Disk};
+use super::node::{Dir, File, Node, Special, Symlink};
+use super::normalization::Normalization;
+use super::volume::{FakeVolume, Mount};
+use super::windows::{self, Flavor};
+use copy::CopyCounter;
+use {CopyConflict, CopyOptions, FileType, Space};
+
+/// Maximum number of symlinks followed while resolving a single path, matching
+/// Linux's `MAXSYMLINKS`.
+pub const DEFAULT_MAX_SYMLINK_DEPTH: usize = 40;
+
+/// Maximum length of a name in bytes, matching Linux's `NAME_MAX`.
+pub const DEFAULT_MAX_NAME_LEN: usize = 255;
+
+/// Maximum length of a path in bytes, including the terminating NUL, matching
+/// Linux's `PATH_MAX`.
+pub const DEFAULT_MAX_PATH_LEN: usize = 4096;
+
+/// Device id of the root volume.
+const ROOT_DEVICE: u64 = 1;
+
+/// Id of the superuser, who bypasses permission checks.
+pub const ROOT_UID: u32 = 0;
+
+/// Ids of the user the file system is accessed as, unless set otherwise. The
+/// root directory belongs to them.
+pub const DEFAULT_UID: u32 = 1000;
+pub const DEFAULT_GID: u32 = 1000;
+
+/// Mode bits with a special meaning: set-user-id and set-group-id, which on a
+/// directory makes new nodes inherit its group, and the sticky bit, which
+/// restricts who may remove nodes from a directory.
+pub const S_ISUID: u32 = 0o4000;
+pub const S_ISGID: u32 = 0o2000;
+pub const S_ISVTX: u32 = 0o1000;
+
+/// Permission bits masked out of new nodes, unless set otherwise.
+pub const DEFAULT_UMASK: u32 = 0o022;
+
+/// Permission bits, as in a `mode` shifted to the class being checked.
+pub const R_OK: u32 = 0o4;
+pub const W_OK: u32 = 0o2;
+pub const X_OK: u32 = 0o1;
+
+/// The extended attribute namespaces Linux knows. Names must start with one.
+const XATTR_NAMESPACES: [&str; 4] = ["user.", "trusted.", "security.", "system."];
 
 #[derive(Debug, Clone)]
 pub struct Registry {
     cwd: PathBuf,
     files: HashMap<PathBuf, Node>,
+    disk: Disk,
+    max_symlink_depth: usize,
+    max_name_len: usize,
+    max_path_len: usize,
+    mounts: HashMap<PathBuf, Mount>,
+    max_inodes: Option<u64>,
+    quotas: HashMap<PathBuf, u64>,
+    case: CaseSensitivity,
+    normalization: Normalization,
+    flavor: Flavor,
+    uid: u32,
+    gid: u32,
+    umask: u32,
+    /// Number of open handles by path.
+    handles: HashMap<PathBuf, usize>,
 }
 
 impl Default for Registry {
@@ -42,9 +107,218 @@ impl Registry {
         let cwd = PathBuf::from("/");
         let mut files = HashMap::new();
 
-        files.insert(cwd.clone(), Node::Dir(Dir::new()));
+        let mut root = Node::Dir(Dir::new());
+        root.set_owner(DEFAULT_UID, DEFAULT_GID);
+        root.set_mode(0o755);
+        files.insert(cwd.clone(), root);
+
+        let mut mounts = HashMap::new();
+        let root = Mount {
+            device: ROOT_DEVICE,
+            capacity: None,
+            readonly: false,
+            xattrs: true,
+        };
+        mounts.insert(cwd.clone(), root);
+
+        Registry {
+            cwd,
+            disk: Disk::new(&files),
+            files,
+            max_symlink_depth: DEFAULT_MAX_SYMLINK_DEPTH,
+            max_name_len: DEFAULT_MAX_NAME_LEN,
+            max_path_len: DEFAULT_MAX_PATH_LEN,
+            mounts,
+            max_inodes: None,
+            quotas: HashMap::new(),
+            case: CaseSensitivity::default(),
+            normalization: Normalization::default(),
+            flavor: Flavor::default(),
+            uid: DEFAULT_UID,
+            gid: DEFAULT_GID,
+            umask: DEFAULT_UMASK,
+            handles: HashMap::new(),
+        }
+    }
+
+    pub fn max_symlink_depth(&self) -> usize {
+        self.max_symlink_depth
+    }
+
+    pub fn set_max_symlink_depth(&mut self, depth: usize) {
+        self.max_symlink_depth = depth;
+    }
+
+    pub fn set_max_name_len(&mut self, len: usize) {
+        self.max_name_len = len;
+    }
+
+    pub fn set_max_path_len(&mut self, len: usize) {
+        self.max_path_len = len;
+    }
+
+    pub fn set_case_sensitivity(&mut self, case: CaseSensitivity) {
+        self.case = case;
+    }
+
+    pub fn set_normalization(&mut self, normalization: Normalization) {
+        self.normalization = normalization;
+    }
+
+    pub fn flavor(&self) -> Flavor {
+        self.flavor
+    }
+
+    pub fn set_flavor(&mut self, flavor: Flavor) {
+        self.flavor = flavor;
+    }
+
+    pub fn set_user(&mut self, uid: u32, gid: u32) {
+        self.uid = uid;
+        self.gid = gid;
+    }
+
+    pub fn umask(&self) -> u32 {
+        self.umask
+    }
+
+    pub fn set_umask(&mut self, umask: u32) {
+        self.umask = umask & 0o777;
+    }
+
+    /// Checks `mode`, a mask of `R_OK`, `W_OK` and `X_OK`, against the
+    /// permissions of the node at `path` after following symlinks. A `mode`
+    /// of 0 only checks that it exists.
+    pub fn access(&self, path: &Path, mode: u32) -> Result<()> {
+        let path = &self.resolve_path(path, true)?;
+        self.get(path)?;
+        if mode & W_OK != 0 {
+            self.check_writable(path)?;
+        }
+
+        self.check_access(path, mode)
+    }
+
+    pub fn open_handle(&mut self, path: &Path) -> Result<PathBuf> {
+        let path = self.resolve_path(path, true)?;
+        self.get(&path)?;
+        *self.handles.entry(path.clone()).or_insert(0) += 1;
+
+        Ok(path)
+    }
+
+    pub fn close_handle(&mut self, path: &Path) {
+        if let Some(count) = self.handles.get_mut(path) {
+            *count -= 1;
+            if *count == 0 {
+                self.handles.remove(path);
+            }
+        }
+    }
+
+    pub fn set_capacity(&mut self, capacity: Option<u64>) {
+        if let Some(root) = self.mounts.get_mut(Path::new("/")) {
+            root.capacity = capacity;
+        }
+    }
+
+    pub fn mount(&mut self, path: &Path, volume: FakeVolume) -> Result<()> {
+        let path = self.resolve_path(path, true)?;
+        self.get_dir(&path)?;
+        if self.mounts.contains_key(&path) {
+            return Err(create_error(ErrorKind::ResourceBusy));
+        }
+
+        let device = volume
+            .device
+            .unwrap_or_else(|| self.mounts.values().map(|m| m.device).max().unwrap_or(0) + 1);
+        let mount = Mount {
+            device,
+            capacity: volume.capacity,
+            readonly: volume.readonly,
+            xattrs: !volume.no_xattrs,
+        };
+        self.mounts.insert(path, mount);
+
+        Ok(())
+    }
+
+    pub fn device(&self, path: &Path) -> Result<u64> {
+        let path = &self.resolve_path(path, true)?;
+        self.get(path)?;
 
-        Registry { cwd, files }
+        Ok(self.mount_of(path).device)
+    }
+
+    pub fn set_max_inodes(&mut self, max_inodes: Option<u64>) {
+        self.max_inodes = max_inodes;
+    }
+
+    pub fn set_quota(&mut self, path: &Path, quota: Option<u64>) -> Result<()> {
+        let path = self.resolve_path(path, true)?;
+        self.get_dir(&path)?;
+        match quota {
+            Some(quota) => self.quotas.insert(path, quota),
+            None => self.quotas.remove(&path),
+        };
+
+        Ok(())
+    }
+
+    /// Reports the capacity as total space, or `u64::MAX` if there is none.
+    pub fn space(&self, path: &Path) -> Result<Space> {
+        let path = &self.resolve_path(path, true)?;
+        self.get(path)?;
+
+        let mount_point = self.mount_point(path);
+        let total = self.mounts[mount_point].capacity.unwrap_or(u64::MAX);
+        let free = total.saturating_sub(self.used_bytes(mount_point));
+        let available = self
+            .quotas
+            .iter()
+            .filter(|&(dir, _)| path.starts_with(dir))
+            .map(|(dir, quota)| quota.saturating_sub(self.used_bytes(dir)))
+            .fold(free, u64::min);
+
+        Ok(Space {
+            total,
+            free,
+            available,
+        })
+    }
+
+    pub fn set_crash_policy(&mut self, policy: CrashPolicy) {
+        self.disk.set_policy(policy);
+    }
+
+    pub fn sync_all(&mut self) {
+        self.disk.sync_all(&self.files);
+    }
+
+    /// Replaces the file system with what was durable, plus whatever unsynced
+    /// changes the crash policy lets through.
+    pub fn crash(&mut self) {
+        self.files = self.disk.crash(&self.files);
+    }
+
+    pub fn sync(&mut self, path: &Path) -> Result<()> {
+        let path = &self.resolve_path(path, true)?;
+        match self.files.get(path) {
+            Some(Node::File(file)) => {
+                self.disk.sync_file(file);
+                Ok(())
+            }
+            Some(_) => self.sync_dir(path),
+            None => Err(create_error(ErrorKind::NotFound)),
+        }
+    }
+
+    pub fn sync_dir(&mut self, path: &Path) -> Result<()> {
+        let path = &self.resolve_path(path, true)?;
+        self.get_dir(path)?;
+        self.disk.sync_dir(&self.files, path);
+
+        Ok(())
     }
 
     pub fn current_dir(&self) -> Result<PathBuf> {
@@ -52,7 +326,11 @@ impl Registry {
     }
 
     pub fn set_current_dir(&mut self, cwd: PathBuf) -> Result<()> {
-        match self.get_dir(&cwd) {
+        let cwd = self.resolve_path(&cwd, true)?;
+        match self
+            .get_dir(&cwd)
+            .and_then(|_| self.check_access(&cwd, X_OK))
+        {
             Ok(_) => {
                 self.cwd = cwd;
                 Ok(())
@@ -81,8 +359,40 @@ impl Registry {
         }
     }
 
+    pub fn exists(&self, path: &Path) -> bool {
+        self.try_exists(path).unwrap_or(false)
+    }
+
+    pub fn try_exists(&self, path: &Path) -> Result<bool> {
+        match self.resolve_path(path, true) {
+            Ok(resolved_path) => Ok(self.files.contains_key(&resolved_path)),
+            Err(ref e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn is_symlink(&self, path: &Path) -> bool {
+        match self.resolve_path(path, false) {
+            Ok(resolved_path) => matches!(self.files.get(&resolved_path), Some(Node::Symlink(_))),
+            Err(_) => false,
+        }
+    }
+
+    pub fn symlink_exists(&self, path: &Path) -> bool {
+        match self.resolve_path(path, false) {
+            Ok(resolved_path) => self.files.contains_key(&resolved_path),
+            Err(_) => false,
+        }
+    }
+
     pub fn create_dir(&mut self, path: &Path) -> Result<()> {
-        self.insert(path.to_path_buf(), Node::Dir(Dir::new()))
+        self.create_dir_with_mode(path, 0o777)
+    }
+
+    pub fn create_dir_with_mode(&mut self, path: &Path, mode: u32) -> Result<()> {
+        let path = self.resolve_path(path, false)?;
+        self.reserve(&path, 1, 0)?;
+        self.create(path, Node::Dir(Dir { mode, ..Dir::new() }))
     }
 
     pub fn create_dir_all(&mut self, path: &Path) -> Result<()> {
@@ -108,73 +418,208 @@ impl Registry {
 
     pub fn remove_dir(&mut self, path: &Path) -> Result<()> {
         let path = &self.resolve_path(path, false)?;
+        self.check_not_mounted(path)?;
         match self.get(path) {
-            Ok(Node::Dir(_)) if self.descendants(path).is_empty() => {}
+            Ok(Node::Dir(_)) if self.children(path).is_empty() => {}
             Ok(Node::Dir(_)) => return Err(create_error(ErrorKind::DirectoryNotEmpty)),
             Ok(_) => return Err(create_error(ErrorKind::NotADirectory)),
             Err(e) => return Err(e),
         };
+        self.check_not_open(path)?;
+        self.check_not_readonly(path)?;
 
         self.remove(path).and(Ok(()))
     }
 
     pub fn remove_dir_all(&mut self, path: &Path) -> Result<()> {
         let path = &self.resolve_path(path, false)?;
-        self.get_dir_mut(path)?;
-
-        let descendants = self.descendants(path);
-        let all_readable = descendants.iter().all(|(_, mode)| mode & 0o444 != 0);
+        self.get_dir(path)?;
+        self.check_not_mounted(path)?;
+        self.check_not_open(path)?;
 
-        if !all_readable {
-            return Err(create_error(ErrorKind::PermissionDenied));
+        // Every directory has to be listed, and emptied unless it already is.
+        let mut descendants = self.descendants(path);
+        for dir in descendants.iter().chain(Some(path)) {
+            if let Ok(Node::Dir(_)) = self.get(dir) {
+                self.check_access(dir, R_OK)?;
+                if !self.children(dir).is_empty() {
+                    self.check_access(dir, W_OK | X_OK)?;
+                }
+            }
         }
 
-        for (child, _) in descendants {
+        descendants.sort_by_key(|p| std::cmp::Reverse(p.components().count()));
+        for child in descendants {
             self.remove(&child)?;
         }
 
         self.remove(path).and(Ok(()))
     }
 
-    pub fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>> {
+    pub fn read_dir(&self, path: &Path) -> Result<Vec<(PathBuf, FileType)>> {
         let path = &self.resolve_path(path, true)?;
         self.get_dir(path)?;
+        self.check_access(path, R_OK)?;
 
-        Ok(self.children(path))
+        Ok(self
+            .children(path)
+            .into_iter()
+            .map(|child| {
+                let file_type = self.files[&child].file_type();
+                (child, file_type)
+            })
+            .collect())
     }
 
     pub fn create_file(&mut self, path: &Path, buf: &[u8]) -> Result<()> {
+        self.create_file_with_contents(path, Contents::from(buf))
+    }
+
+    pub fn create_file_with_mode(&mut self, path: &Path, buf: &[u8], mode: u32) -> Result<()> {
+        let path = &self.resolve_path(path, true)?;
+        self.reserve(path, 1, buf.len() as u64)?;
+        let file = File {
+            mode,
+            ..File::new(Contents::from(buf))
+        };
+        self.create(path.to_path_buf(), Node::File(file))
+    }
+
+    /// Creates a node of type `file_type`. Only root may create devices.
+    pub fn mknod(&mut self, path: &Path, file_type: FileType, mode: u32, dev: u64) -> Result<()> {
+        let device = match file_type {
+            FileType::Dir | FileType::Symlink => return Err(create_error(ErrorKind::InvalidInput)),
+            FileType::CharDevice | FileType::BlockDevice => true,
+            _ => false,
+        };
+        let path = self.resolve_path(path, false)?;
+        self.check_creatable(&path)?;
+        if device && self.uid != ROOT_UID {
+            return Err(create_error(ErrorKind::PermissionDenied));
+        }
+        self.reserve(&path, 1, 0)?;
+        let mode = mode & 0o7777;
+        let node = match file_type {
+            FileType::File => Node::File(File {
+                mode,
+                ..File::new(Contents::default())
+            }),
+            _ => Node::Special(Special {
+                mode,
+                dev: if device { dev } else { 0 },
+                ..Special::new(file_type)
+            }),
+        };
+        self.create(path, node)
+    }
+
+    pub fn device_number(&self, path: &Path) -> Result<u64> {
         let path = &self.resolve_path(path, true)?;
-        let file = File::new(buf.to_vec());
-        self.insert(path.to_path_buf(), Node::File(file))
+
+        match self.get(path)? {
+            Node::Special(special) => Ok(special.dev),
+            _ => Ok(0),
+        }
+    }
+
+    pub fn create_generated_file(
+        &mut self,
+        path: &Path,
+        len: u64,
+        source: ContentSource,
+    ) -> Result<()> {
+        self.create_file_with_contents(path, Contents::generated(len, source))
+    }
+
+    pub fn create_dynamic_file(&mut self, path: &Path, dynamic: DynamicFile) -> Result<()> {
+        let path = &self.resolve_path(path, true)?;
+        let file = File::new_dynamic(dynamic);
+        self.reserve(path, 1, 0)?;
+        self.create(path.to_path_buf(), Node::File(file))
+    }
+
+    fn create_file_with_contents(&mut self, path: &Path, contents: Contents) -> Result<()> {
+        let path = &self.resolve_path(path, true)?;
+        self.reserve(path, 1, contents.len())?;
+        let file = File::new(contents);
+        self.create(path.to_path_buf(), Node::File(file))
     }
 
     pub fn write_file(&mut self, path: &Path, buf: &[u8]) -> Result<()> {
+        self.write_contents(path, Contents::from(buf))
+    }
+
+    fn write_contents(&mut self, path: &Path, contents: Contents) -> Result<()> {
         let path = &self.resolve_path(path, true)?;
-        self.get_file_mut(path)
-            .map(|ref mut f| f.contents = buf.to_vec())
-            .or_else(|e| {
-                if e.kind() == ErrorKind::NotFound {
-                    self.create_file(path, buf)
-                } else {
-                    Err(e)
-                }
-            })
+        self.reserve_len(path, contents.len())?;
+        match self.get_file_mut(path) {
+            Ok(f) => f.set_contents(contents),
+            Err(ref e) if e.kind() == ErrorKind::NotFound => {
+                self.create_file_with_contents(path, contents)
+            }
+            Err(e) => Err(e),
+        }
     }
 
     pub fn overwrite_file(&mut self, path: &Path, buf: &[u8]) -> Result<()> {
         let path = &self.resolve_path(path, true)?;
-        self.get_file_mut(path)
-            .map(|ref mut f| f.contents = buf.to_vec())
+        self.reserve_len(path, buf.len() as u64)?;
+        self.get_file_mut(path)?.set_contents(Contents::from(buf))
     }
 
-    pub fn read_file(&self, path: &Path) -> Result<Vec<u8>> {
+    pub fn append_file(&mut self, path: &Path, buf: &[u8]) -> Result<()> {
         let path = &self.resolve_path(path, true)?;
-        match self.get_file(path) {
-            Ok(f) if f.mode & 0o444 != 0 => Ok(f.contents.clone()),
-            Ok(_) => Err(create_error(ErrorKind::PermissionDenied)),
-            Err(err) => Err(err),
+        self.append_existing_file(path, buf).or_else(|e| {
+            if e.kind() == ErrorKind::NotFound {
+                self.create_file(path, buf)
+            } else {
+                Err(e)
+            }
+        })
+    }
+
+    pub fn append_existing_file(&mut self, path: &Path, buf: &[u8]) -> Result<()> {
+        let path = &self.resolve_path(path, true)?;
+        // An end past `u64::MAX` is left to the write to reject.
+        let len = self.stored_len(path).saturating_add(buf.len() as u64);
+        self.reserve_len(path, len)?;
+        self.get_file_mut(path)?.append(buf)
+    }
+
+    /// Swaps in `buf` as the contents of the file at `path` without looking at
+    /// its permissions, as renaming a new file over it would. Like the real
+    /// thing, the new contents are synced before returning.
+    pub fn write_file_atomic(&mut self, path: &Path, buf: &[u8]) -> Result<()> {
+        let path = &self.resolve_path_to_create(path)?;
+        self.check_writable(path)?;
+        if let Some(parent) = path.parent() {
+            self.check_access(parent, W_OK | X_OK)?;
         }
+        self.reserve_len(path, buf.len() as u64)?;
+        match self.files.get_mut(path) {
+            Some(Node::File(file)) => file.set_contents(Contents::from(buf))?,
+            Some(Node::Dir(_)) => return Err(create_error(ErrorKind::IsADirectory)),
+            Some(_) => return Err(not_a_regular_file()),
+            None => self.create_file(path, buf)?,
+        }
+
+        self.sync(path)?;
+        match path.parent() {
+            Some(parent) => self.sync_dir(parent),
+            None => Ok(()),
+        }
+    }
+
+    pub fn read_file(&self, path: &Path) -> Result<Vec<u8>> {
+        self.read_contents(path).map(|contents| contents.to_vec())
+    }
+
+    fn read_contents(&self, path: &Path) -> Result<Contents> {
+        let path = &self.resolve_path(path, true)?;
+        let file = self.get_file(path)?;
+        self.check_access(path, R_OK)?;
+
+        Ok(file.snapshot())
     }
 
     pub fn read_file_to_string(&self, path: &Path) -> Result<String> {
@@ -187,20 +632,48 @@ impl Registry {
 
     pub fn read_file_into(&self, path: &Path, buf: &mut Vec<u8>) -> Result<usize> {
         let path = &self.resolve_path(path, true)?;
-        match self.get_file(path) {
-            Ok(f) if f.mode & 0o444 != 0 => {
-                buf.extend(&f.contents);
-                Ok(f.contents.len())
-            }
-            Ok(_) => Err(create_error(ErrorKind::PermissionDenied)),
-            Err(err) => Err(err),
-        }
+        let file = self.get_file(path)?;
+        self.check_access(path, R_OK)?;
+
+        let contents = file.snapshot().to_vec();
+        buf.extend(&contents);
+        Ok(contents.len())
+    }
+
+    pub fn read_file_range(&self, path: &Path, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let path = &self.resolve_path(path, true)?;
+        let file = self.get_file(path)?;
+        self.check_access(path, R_OK)?;
+
+        Ok(file.read(offset, len))
+    }
+
+    pub fn write_file_at(&mut self, path: &Path, offset: u64, buf: &[u8]) -> Result<()> {
+        let path = &self.resolve_path(path, true)?;
+        let end = offset
+            .checked_add(buf.len() as u64)
+            .ok_or_else(|| create_error(ErrorKind::FileTooLarge))?;
+        let len = self.stored_len(path).max(end);
+        self.reserve_len(path, len)?;
+        self.get_file_mut(path)?.write(offset, buf)
+    }
+
+    pub fn set_len(&mut self, path: &Path, size: u64) -> Result<()> {
+        let path = &self.resolve_path(path, true)?;
+        self.reserve_len(path, size)?;
+        self.get_file_mut(path)?.set_len(size);
+
+        Ok(())
     }
 
     pub fn remove_file(&mut self, path: &Path) -> Result<()> {
         let path = &self.resolve_path(path, false)?;
         match self.get(path)? {
-            Node::File(_) | Node::Symlink(_) => self.remove(path).and(Ok(())),
+            Node::File(_) | Node::Symlink(_) | Node::Special(_) => {
+                self.check_not_open(path)?;
+                self.check_not_readonly(path)?;
+                self.remove(path).and(Ok(()))
+            }
             Node::Dir(_) => Err(create_error(ErrorKind::Other)),
         }
     }
@@ -208,13 +681,14 @@ impl Registry {
     pub fn copy_file(&mut self, from: &Path, to: &Path) -> Result<()> {
         let from = &self.resolve_path(from, true)?;
         let to = &self.resolve_path(to, true)?;
-        match (self.read_file(from), self.get(to)) {
-            (Ok(ref buf), Err(e)) if e.kind() == ErrorKind::NotFound => self.write_file(to, buf),
-            (Ok(ref buf), Ok(Node::File(f))) if f.mode != 644 => self.write_file(to, buf),
-            (Ok(ref buf), Ok(Node::Symlink(l))) if l.mode != 644 => self.write_file(to, buf),
+        match (self.read_contents(from), self.get(to)) {
+            (Ok(buf), Err(e)) if e.kind() == ErrorKind::NotFound => self.write_contents(to, buf),
+            (Ok(buf), Ok(Node::File(f))) if f.mode != 644 => self.write_contents(to, buf),
+            (Ok(buf), Ok(Node::Symlink(l))) if l.mode != 644 => self.write_contents(to, buf),
             (Ok(_), Ok(Node::Symlink(_)) | Ok(Node::File(_))) => {
                 Err(create_error(ErrorKind::PermissionDenied))
             }
+            (Ok(_), Ok(Node::Special(_))) => Err(not_a_regular_file()),
             (Ok(_), _) => Err(create_error(ErrorKind::IsADirectory)),
             (Err(e), _) if e.kind() == ErrorKind::IsADirectory => {
                 Err(create_error(ErrorKind::InvalidInput))
@@ -232,87 +706,193 @@ impl Registry {
         }
     }
 
-    fn resolve_path(&'_ self, path: &Path, follow_last_component: bool) -> Result<PathBuf> {
+    /// Resolves every symlink in `path`, optionally leaving the last component
+    /// unresolved. The last component does not need to exist, unless it is
+    /// reached through a symlink. Relative symlink sources are resolved against
+    /// the directory containing the link.
+    ///
+    /// Fails with `FilesystemLoop` once more than `max_symlink_depth` symlinks
+    /// have been followed, and with `InvalidFilename` if the Windows flavour
+    /// rejected `path` or it is too long. See `check_path`.
+    fn resolve_path(&self, path: &Path, follow_last_component: bool) -> Result<PathBuf> {
+        if self.flavor == Flavor::Windows && windows::is_rejected(path) {
+            return Err(create_error(ErrorKind::InvalidFilename));
+        }
+        self.check_path(path)?;
         match self.files.get(path) {
-            Some(Node::File(_)) | Some(Node::Dir(_)) => return Ok(path.to_path_buf()),
-            Some(Node::Symlink(_)) if follow_last_component => {
-                return Ok(self.recurse_symlink(path).map(|(_, p)| p)?)
+            Some(Node::File(_)) | Some(Node::Dir(_)) => {
+                return self.check_ancestors(path).map(|_| path.to_path_buf())
+            }
+            Some(Node::Symlink(_)) if !follow_last_component => {
+                return self.check_ancestors(path).map(|_| path.to_path_buf())
             }
-            Some(Node::Symlink(_)) => return Ok(path.to_path_buf()),
-            None => (),
+            _ => (),
         }
-        let mut pathbuf = PathBuf::new();
-        let count = path.components().count();
-        for (i, component) in path.components().enumerate() {
-            match component {
-                Component::Prefix(prefix) => {
-                    pathbuf.push(Component::Prefix(prefix));
+        let mut resolved = PathBuf::new();
+        let mut remaining = path.to_path_buf();
+        let mut hops = 0;
+        let mut through_last_symlink = false;
+
+        loop {
+            let mut components = remaining.components();
+            let component = match components.next() {
+                Some(component) => component,
+                None => return Ok(resolved),
+            };
+            let rest = components.as_path().to_path_buf();
+            let is_last = rest.as_os_str().is_empty();
+
+            let name = match component {
+                Component::Prefix(_) | Component::RootDir => {
+                    resolved.push(component);
+                    remaining = rest;
                     continue;
                 }
-                Component::RootDir => {
-                    pathbuf.push(Component::RootDir);
+                Component::CurDir => {
+                    remaining = rest;
                     continue;
                 }
-                Component::Normal(comp) => pathbuf.push(Component::Normal(comp)),
-                Component::CurDir | Component::ParentDir => {
-                    return Err(create_error(ErrorKind::InvalidInput))
+                Component::ParentDir => {
+                    // `resolved` never contains symlinks, so this matches the
+                    // physical `..` lookup POSIX requires.
+                    resolved.pop();
+                    remaining = rest;
+                    continue;
                 }
-            }
+                Component::Normal(name) => name,
+            };
+            self.check_search(&resolved)?;
+            let candidate = self.child_path(&resolved, name);
 
-            match self.files.get(&pathbuf) {
-                Some(Node::File(_)) | Some(Node::Dir(_)) => continue,
-                Some(Node::Symlink(_)) => {
-                    if !follow_last_component && i == count - 1 {
-                        return Ok(pathbuf);
-                    } else {
-                        pathbuf = self.recurse_symlink(&pathbuf).map(|(_, p)| p)?;
+            match self.files.get(&candidate) {
+                Some(Node::Symlink(link)) if follow_last_component || !is_last => {
+                    hops += 1;
+                    if hops > self.max_symlink_depth {
+                        return Err(create_error(ErrorKind::FilesystemLoop));
                     }
-                }
-                None => {
-                    if i == count - 1 {
-                        return Ok(pathbuf);
+                    through_last_symlink |= is_last;
+                    remaining = if is_last {
+                        link.source.clone()
                     } else {
-                        return Err(create_error(ErrorKind::NotFound));
-                    }
+                        link.source.join(rest)
+                    };
+                }
+                Some(_) => {
+                    resolved = candidate;
+                    remaining = rest;
                 }
+                None if is_last && !through_last_symlink => return Ok(candidate),
+                None => return Err(create_error(ErrorKind::NotFound)),
             }
         }
-        Ok(pathbuf)
     }
-    fn recurse_symlink<'a>(&'a self, path: &Path) -> Result<(&'a Node, PathBuf)> {
-        let mut traversed_items = HashSet::new();
-        let mut path = path;
-        let mut current = self.files.get(path);
-        while let Some(&Node::Symlink(_)) = current {
-            if traversed_items.contains(path) {
-                return Err(create_error(ErrorKind::Other));
-            }
-            traversed_items.insert(path.to_path_buf());
-            path = if let Node::Symlink(ref link) = current.unwrap() {
-                &link.source
-            } else {
-                path
-            };
-            current = self.files.get(path);
+
+    /// Fails unless the current user can search every directory above `path`,
+    /// which must not go through symlinks.
+    fn check_ancestors(&self, path: &Path) -> Result<()> {
+        path.ancestors()
+            .skip(1)
+            .try_for_each(|dir| self.check_search(dir))
+    }
+
+    /// Fails as Linux and std do for paths that cannot be looked up: with
+    /// `InvalidInput` if `path` contains a NUL byte, and with `InvalidFilename`
+    /// if `path` or one of its names is too long.
+    fn check_path(&self, path: &Path) -> Result<()> {
+        let bytes = path.as_os_str().as_encoded_bytes();
+        if bytes.contains(&0) {
+            return Err(create_error(ErrorKind::InvalidInput));
         }
-        match current {
-            None => Err(create_error(ErrorKind::NotFound)),
-            Some(node) => Ok((&node, path.to_path_buf())),
+        let name_too_long = path.components().any(|component| match component {
+            Component::Normal(name) => name.len() > self.max_name_len,
+            _ => false,
+        });
+        if name_too_long || bytes.len() >= self.max_path_len {
+            return Err(create_error(ErrorKind::InvalidFilename));
+        }
+
+        Ok(())
+    }
+
+    /// Like `resolve_path`, but resolves a broken symlink to the path it points
+    /// to, which is where a file created through the link ends up.
+    fn resolve_path_to_create(&self, path: &Path) -> Result<PathBuf> {
+        match self.resolve_path(path, true) {
+            Err(ref err) if err.kind() == ErrorKind::NotFound => (),
+            result => return result,
         }
+        let link = self.resolve_path(path, false)?;
+        match self.files.get(&link) {
+            Some(Node::Symlink(symlink)) => {
+                let target = match link.parent() {
+                    Some(parent) => parent.join(&symlink.source),
+                    None => symlink.source.clone(),
+                };
+                self.resolve_path_to_create(&target)
+            }
+            _ => Err(create_error(ErrorKind::NotFound)),
+        }
+    }
+
+    fn recurse_symlink(&self, path: &Path) -> Result<(&Node, PathBuf)> {
+        let path = self.resolve_path(path, true)?;
+        self.get(&path).map(|node| (node, path))
     }
 
     pub fn rename(&mut self, from: &Path, to: &Path) -> Result<()> {
-        let mut from = from.to_path_buf();
-        match self.resolve_path(&from, false) {
-            Ok(path) => from = path,
-            Err(_) => return Err(create_error(ErrorKind::NotFound)),
+        let name = to.file_name().map(|name| name.to_os_string());
+        let from = self.resolve_path(from, false)?;
+        let to = self.resolve_path(to, false)?;
+        self.check_not_mounted(&from)?;
+        if self.mounts.contains_key(&to) {
+            return Err(create_error(ErrorKind::ResourceBusy));
         }
-        let mut to = to.to_path_buf();
-        match self.resolve_path(&to, false) {
-            Ok(path) => to = path,
-            Err(_) => return Err(create_error(ErrorKind::NotFound)),
+        if self.mount_point(&from) != self.mount_point(&to) {
+            return Err(create_error(ErrorKind::CrossesDevices));
         }
+        for parent in [from.parent(), to.parent()].iter().flatten() {
+            self.check_access(parent, W_OK | X_OK)?;
+        }
+        self.reserve_move(&from, &to)?;
+        self.check_not_open(&from)?;
+        if from != to && self.files.contains_key(&to) {
+            self.check_not_open(&to)?;
+            self.check_not_readonly(&to)?;
+        }
+        if from != to {
+            self.rename_node(&from, &to)?;
+        }
+
+        // Unless names are case-sensitive, `to` may be spelled as the node it
+        // replaced, or even be `from` itself, instead of as requested.
+        match name {
+            Some(ref name) if to.file_name() != Some(&self.normalization.stored(name)) => {
+                let renamed = to.with_file_name(name);
+                match self.get(&to)? {
+                    Node::Dir(_) => self.move_dir(&to, &renamed),
+                    _ => self.rename_path(&to, renamed),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn rename_node(&mut self, from: &Path, to: &Path) -> Result<()> {
+        let (from, to) = (from.to_path_buf(), to.to_path_buf());
         match (self.get(&from), self.get(&to)) {
+            (Ok(&Node::Special(_)), Err(ref err)) if err.kind() == ErrorKind::NotFound => {
+                self.rename_path(&from, to)
+            }
+            (Ok(&Node::Special(_)), Ok(&Node::Dir(_))) => {
+                Err(create_error(ErrorKind::IsADirectory))
+            }
+            (Ok(&Node::Dir(_)), Ok(&Node::Special(_))) => {
+                Err(create_error(ErrorKind::NotADirectory))
+            }
+            (Ok(&Node::Special(_)), Ok(_)) | (Ok(_), Ok(&Node::Special(_))) => {
+                self.remove(&to)?;
+                self.rename_path(&from, to)
+            }
             (Ok(&Node::File(_)), Ok(&Node::File(_))) => {
                 self.remove_file(&to)?;
                 self.rename_path(&from, to)
@@ -323,7 +903,7 @@ impl Registry {
             (Ok(&Node::Dir(_)), Err(ref err)) if err.kind() == ErrorKind::NotFound => {
                 self.move_dir(&from, &to)
             }
-            (Ok(&Node::Dir(_)), Ok(&Node::Dir(_))) if self.descendants(&to).is_empty() => {
+            (Ok(&Node::Dir(_)), Ok(&Node::Dir(_))) if self.children(&to).is_empty() => {
                 self.remove(&to)?;
                 self.move_dir(&from, &to)
             }
@@ -334,7 +914,7 @@ impl Registry {
                 self.rename_path(&from, to)
             }
             (Ok(&Node::Dir(_)), Ok(&Node::Symlink(_))) => match self.recurse_symlink(&to)? {
-                (Node::Dir(_), path) if self.descendants(&path).is_empty() => {
+                (Node::Dir(_), path) if self.children(&path).is_empty() => {
                     self.remove(&to)?;
                     self.move_dir(&from, &to)
                 }
@@ -350,7 +930,7 @@ impl Registry {
                 self.rename_path(&from, to)
             }
             (Ok(&Node::Symlink(_)), Ok(&Node::Dir(_))) => match self.recurse_symlink(&from)? {
-                (Node::Dir(_), _) if self.descendants(&to).is_empty() => {
+                (Node::Dir(_), _) if self.children(&to).is_empty() => {
                     self.remove(&to)?;
                     self.move_dir(&from, &to)
                 }
@@ -371,7 +951,7 @@ impl Registry {
                         self.rename_path(&from, to)
                     }
                     (Ok((Node::Dir(_), _)), Ok((Node::Dir(_), path))) => {
-                        if self.descendants(&path).is_empty() {
+                        if self.children(&path).is_empty() {
                             self.remove(&to)?;
                             self.rename_path(&from, to)
                         } else {
@@ -381,7 +961,8 @@ impl Registry {
                     (_, Err(_))
                     | (Ok((Node::File(_), _)), _)
                     | (Ok((Node::Dir(_), _)), _)
-                    | (Ok((Node::Symlink(_), _)), _) => Err(create_error(ErrorKind::Other)),
+                    | (Ok((Node::Symlink(_), _)), _)
+                    | (Ok((Node::Special(_), _)), _) => Err(create_error(ErrorKind::Other)),
                 }
             }
             (Ok(&Node::File(_)), Ok(&Node::Dir(_)))
@@ -402,79 +983,314 @@ impl Registry {
     }
 
     pub fn readonly(&self, path: &Path) -> Result<bool> {
-        self.get(path).map(|node| match node {
-            Node::File(ref file) => file.mode & 0o222 == 0,
-            Node::Dir(ref dir) => dir.mode & 0o222 == 0,
-            Node::Symlink(ref symlink) => symlink.mode & 0o222 == 0,
-        })
+        let path = &self.resolve_path(path, false)?;
+        self.get(path).map(|node| node.mode() & 0o222 == 0)
     }
 
     pub fn set_readonly(&mut self, path: &Path, readonly: bool) -> Result<()> {
-        fn set_readonly_mode(mode: &mut u32, readonly: bool) {
+        self.check_owner(path)?;
+        self.get_mut(path).map(|node| {
             if readonly {
-                *mode &= !0o222
+                node.set_mode(node.mode() & !0o222)
             } else {
-                *mode |= 0o222
-            }
-        }
-        self.get_mut(path).map(|node| match node {
-            Node::File(ref mut file) => {
-                set_readonly_mode(&mut file.mode, readonly);
-            }
-            Node::Dir(ref mut dir) => {
-                set_readonly_mode(&mut dir.mode, readonly);
-            }
-            Node::Symlink(ref mut link) => {
-                set_readonly_mode(&mut link.mode, readonly);
+                node.set_mode(node.mode() | 0o222)
             }
         })
     }
 
+    /// Returns the mode of the node at `path` after following symlinks,
+    /// including its file type bits as in `st_mode`.
     pub fn mode(&self, path: &Path) -> Result<u32> {
-        self.get(path).map(|node| match node {
-            Node::File(ref file) => file.mode,
-            Node::Dir(ref dir) => dir.mode,
-            Node::Symlink(ref link) => link.mode,
-        })
+        let path = &self.resolve_path(path, true)?;
+        self.get(path)
+            .map(|node| node.file_type().mode_bits() | node.mode())
     }
 
+    /// Sets the permission and special bits of the node at `path` after
+    /// following symlinks. Any other bits of `mode` are ignored, as by `chmod`.
     pub fn set_mode(&mut self, path: &Path, mode: u32) -> Result<()> {
-        self.get_mut(path).map(|node| match node {
-            Node::File(ref mut file) => file.mode = mode,
-            Node::Dir(ref mut dir) => dir.mode = mode,
-            Node::Symlink(ref mut link) => link.mode = mode,
-        })
+        let path = &self.resolve_path(path, true)?;
+        self.check_owner(path)?;
+        self.get_mut(path).map(|node| node.set_mode(mode & 0o7777))
+    }
+
+    pub fn owner(&self, path: &Path, follow: bool) -> Result<(u32, u32)> {
+        let path = &self.resolve_path(path, follow)?;
+        self.get(path).map(|node| node.owner())
+    }
+
+    /// Changes the owner and group of the node at `path`, leaving either
+    /// unchanged if `None`. Only root may give a node away; its owner may
+    /// only change its group to their own.
+    pub fn chown(
+        &mut self,
+        path: &Path,
+        uid: Option<u32>,
+        gid: Option<u32>,
+        follow: bool,
+    ) -> Result<()> {
+        let path = &self.resolve_path(path, follow)?;
+        let (owner, group) = self.get(path)?.owner();
+        self.check_writable(path)?;
+        let (uid, gid) = (uid.unwrap_or(owner), gid.unwrap_or(group));
+        if self.uid != ROOT_UID
+            && (self.uid != owner || uid != owner || (gid != group && gid != self.gid))
+        {
+            return Err(create_error(ErrorKind::PermissionDenied));
+        }
+        self.get_mut(path)?.set_owner(uid, gid);
+
+        Ok(())
+    }
+
+    /// Returns the value of the extended attribute `name`. Attributes in the
+    /// `user` namespace need read permission; `trusted` ones are hidden from
+    /// everyone but root.
+    pub fn get_xattr(&self, path: &Path, name: &OsStr, follow: bool) -> Result<Vec<u8>> {
+        let path = &self.resolve_path(path, follow)?;
+        self.check_xattrs(path, Some(name))?;
+        if xattr_namespace(name) == Some("user.") {
+            self.check_access(path, R_OK)?;
+        }
+        if xattr_namespace(name) == Some("trusted.") && self.uid != ROOT_UID {
+            return Err(no_such_xattr());
+        }
+
+        self.get(path)?
+            .xattrs()
+            .get(name)
+            .cloned()
+            .ok_or_else(no_such_xattr)
+    }
+
+    pub fn set_xattr(
+        &mut self,
+        path: &Path,
+        name: &OsStr,
+        value: &[u8],
+        follow: bool,
+    ) -> Result<()> {
+        let path = &self.resolve_path(path, follow)?;
+        self.check_xattrs(path, Some(name))?;
+        self.check_xattr_writable(path, name)?;
+        self.get_mut(path)?
+            .xattrs_mut()
+            .insert(name.to_os_string(), value.to_vec());
+
+        Ok(())
+    }
+
+    /// Lists the names of the extended attributes, without the `trusted` ones
+    /// unless the current user is root.
+    pub fn list_xattrs(&self, path: &Path, follow: bool) -> Result<Vec<OsString>> {
+        let path = &self.resolve_path(path, follow)?;
+        self.check_xattrs(path, None)?;
+
+        Ok(self
+            .get(path)?
+            .xattrs()
+            .keys()
+            .filter(|name| xattr_namespace(name) != Some("trusted.") || self.uid == ROOT_UID)
+            .cloned()
+            .collect())
+    }
+
+    pub fn remove_xattr(&mut self, path: &Path, name: &OsStr, follow: bool) -> Result<()> {
+        let path = &self.resolve_path(path, follow)?;
+        self.check_xattrs(path, Some(name))?;
+        self.check_xattr_writable(path, name)?;
+        self.get_mut(path)?
+            .xattrs_mut()
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(no_such_xattr)
     }
 
     pub fn len(&self, path: &Path) -> u64 {
-        self.get(path)
+        self.resolve_path(path, false)
+            .and_then(|path| self.get(&path))
             .map(|node| match node {
-                Node::File(ref file) => file.contents.len() as u64,
+                Node::File(ref file) => file.len(),
                 Node::Dir(_) => 4096,
                 Node::Symlink(_) => 34, // This is what it actually is on macOS
+                Node::Special(_) => 0,
             })
             .unwrap_or(0)
     }
 
+    pub fn modified(&self, path: &Path) -> Result<SystemTime> {
+        let path = &self.resolve_path(path, true)?;
+        self.get(path).map(|node| node.modified())
+    }
+
+    pub fn set_modified(&mut self, path: &Path, modified: SystemTime) -> Result<()> {
+        let path = &self.resolve_path(path, true)?;
+        self.get_mut(path).map(|node| node.set_modified(modified))
+    }
+
+    pub fn copy_dir_all(&mut self, from: &Path, to: &Path, options: &CopyOptions) -> Result<()> {
+        let from = &self.resolve_path(from, true)?;
+        self.get_dir(from)?;
+        let mut to = self.resolve_path(to, false)?;
+        if self.is_dir(&to) {
+            to = self.resolve_path(&to, true)?;
+        }
+        if to.starts_with(from) {
+            return Err(create_error(ErrorKind::InvalidInput));
+        }
+
+        self.copy_dir(from, &to, options, &mut CopyCounter::default())
+    }
+
+    fn copy_dir(
+        &mut self,
+        from: &Path,
+        to: &Path,
+        options: &CopyOptions,
+        counter: &mut CopyCounter,
+    ) -> Result<()> {
+        if !self.is_dir(to) {
+            if !self.replace_existing(to, options)? {
+                return Ok(());
+            }
+            self.create_dir(to)?;
+        }
+        let source = &self.resolve_path(from, true)?;
+        let target = &self.resolve_path(to, true)?;
+
+        let mut names: Vec<_> = self
+            .children(source)
+            .iter()
+            .filter_map(|child| child.file_name().map(|name| name.to_os_string()))
+            .collect();
+        names.sort();
+
+        for name in names {
+            let from = &from.join(&name);
+            let to = &target.join(&name);
+
+            match self.get(&source.join(&name))?.clone() {
+                Node::Symlink(link) if !options.follow_symlinks => {
+                    if self.replace_existing(to, options)? {
+                        self.reserve(to, 1, 0)?;
+                        self.create(to.to_path_buf(), Node::Symlink(Symlink::new(link.source)))?;
+                        counter.record(options, from, to, 0);
+                    }
+                }
+                _ if self.is_dir(from) => self.copy_dir(from, to, options, counter)?,
+                _ => {
+                    let contents = self.read_contents(from)?;
+                    if self.replace_existing(to, options)? {
+                        let bytes = contents.len();
+                        let mut file = File::new(contents);
+                        if options.preserve_timestamps {
+                            file.modified = self.modified(from)?;
+                        }
+                        self.reserve(to, 1, bytes)?;
+                        self.create(to.to_path_buf(), Node::File(file))?;
+                        // Like `fchmod`, preserving the mode ignores the umask.
+                        if options.preserve_mode {
+                            let mode = self.get(&self.resolve_path(from, true)?)?.mode();
+                            self.get_mut(to)?.set_mode(mode);
+                        }
+                        counter.record(options, from, to, bytes);
+                    }
+                }
+            }
+        }
+
+        if options.preserve_timestamps {
+            let modified = self.get(source)?.modified();
+            self.get_mut(target)?.set_modified(modified);
+        }
+        if options.preserve_mode {
+            let mode = self.mode(source)?;
+            self.set_mode(target, mode)?;
+        }
+        counter.record(options, from, to, 0);
+
+        Ok(())
+    }
+
+    /// Clears the way for a non-directory node at `path`. Returns `false` if the
+    /// node should be skipped instead.
+    fn replace_existing(&mut self, path: &Path, options: &CopyOptions) -> Result<bool> {
+        let path = &self.resolve_path(path, false)?;
+        match self.files.get(path) {
+            None => Ok(true),
+            Some(Node::Dir(_)) => Err(create_error(ErrorKind::IsADirectory)),
+            Some(_) => match options.on_conflict {
+                CopyConflict::Skip => Ok(false),
+                CopyConflict::Error => Err(create_error(ErrorKind::AlreadyExists)),
+                CopyConflict::Overwrite => self.remove(path).and(Ok(true)),
+            },
+        }
+    }
+
     fn get(&self, path: &Path) -> Result<&Node> {
         self.files
             .get(path)
+            .or_else(|| self.files.get(&self.spelled(path)))
             .ok_or_else(|| create_error(ErrorKind::NotFound))
     }
 
     fn get_mut(&mut self, path: &Path) -> Result<&mut Node> {
+        let path = &self.spelled(path);
+        self.check_writable(path)?;
         self.files
             .get_mut(path)
             .ok_or_else(|| create_error(ErrorKind::NotFound))
     }
 
+    /// Returns the path of the child of `dir` called `name`. Unless names are
+    /// compared as stored, that is the existing child whose name matches `name`.
+    fn child_path(&self, dir: &Path, name: &OsStr) -> PathBuf {
+        let name = self.normalization.stored(name);
+        let path = dir.join(&name);
+        let exact = self.case.is_sensitive() && self.normalization != Normalization::Insensitive;
+        if exact || self.files.contains_key(&path) {
+            return path;
+        }
+
+        let name = self.normalization.compared(&name);
+        self.files
+            .keys()
+            .find(|p| {
+                p.parent() == Some(dir)
+                    && p.file_name().is_some_and(|child| {
+                        self.case
+                            .matches(&self.normalization.compared(child), &name)
+                    })
+            })
+            .cloned()
+            .unwrap_or(path)
+    }
+
+    /// Returns `path` spelled as in the registry, without following symlinks.
+    fn spelled(&self, path: &Path) -> PathBuf {
+        let plain = self.case.is_sensitive() && self.normalization == Normalization::Preserve;
+        if plain || self.files.contains_key(path) {
+            return path.to_path_buf();
+        }
+
+        let mut spelled = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::Normal(name) => spelled = self.child_path(&spelled, name),
+                component => spelled.push(component),
+            }
+        }
+
+        spelled
+    }
+
     fn get_dir(&self, path: &Path) -> Result<&Dir> {
         self.get(path).and_then(|node| match node {
             Node::Dir(ref dir) => Ok(dir),
-            Node::File(_) => Err(create_error(ErrorKind::NotADirectory)),
+            Node::File(_) | Node::Special(_) => Err(create_error(ErrorKind::NotADirectory)),
             Node::Symlink(_) => match self.recurse_symlink(path) {
                 Ok((Node::Dir(dir), _)) => Ok(&dir),
-                Ok((Node::File(_), _)) | Ok((Node::Symlink(_), _)) => {
+                Ok((Node::File(_), _)) | Ok((Node::Symlink(_), _)) | Ok((Node::Special(_), _)) => {
                     Err(create_error(ErrorKind::NotADirectory))
                 }
                 Err(e) => Err(e),
@@ -482,33 +1298,14 @@ impl Registry {
         })
     }
 
-    fn get_dir_mut(&mut self, path: &Path) -> Result<&mut Dir> {
-        let mut path = path.to_path_buf();
-        match self.get(&path)? {
-            Node::Dir(dir) if dir.mode & 0o222 != 0 => (), // still get the original path
-            Node::Dir(_) => return Err(create_error(ErrorKind::PermissionDenied)),
-            Node::File(_) => return Err(create_error(ErrorKind::NotADirectory)),
-            Node::Symlink(_) => match self.recurse_symlink(&path) {
-                Ok((Node::Dir(_), new_path)) => path = new_path,
-                Ok((Node::File(_), _)) | Ok((Node::Symlink(_), _)) => {
-                    return Err(create_error(ErrorKind::NotADirectory))
-                }
-                Err(e) => return Err(e),
-            },
-        };
-        if let Ok(Node::Dir(dir)) = self.get_mut(&path) {
-            Ok(dir)
-        } else {
-            Err(create_error(ErrorKind::Other))
-        }
-    }
-
     fn get_file(&self, path: &Path) -> Result<&File> {
         self.get(path).and_then(|node| match node {
             Node::File(ref file) => Ok(file),
             Node::Dir(_) => Err(create_error(ErrorKind::IsADirectory)),
+            Node::Special(_) => Err(not_a_regular_file()),
             Node::Symlink(_) => match self.recurse_symlink(path) {
                 Ok((Node::File(file), _)) => Ok(&file),
+                Ok((Node::Special(_), _)) => Err(not_a_regular_file()),
                 Ok((Node::Dir(_), _)) | Ok((Node::Symlink(_), _)) => {
                     Err(create_error(ErrorKind::IsADirectory))
                 }
@@ -520,95 +1317,356 @@ impl Registry {
     fn get_file_mut(&mut self, path: &Path) -> Result<&mut File> {
         let mut path = path.to_path_buf();
         match self.get(&path)? {
-            Node::File(file) if file.mode & 0o222 != 0 => (), // still get the original path
-            Node::File(_) => return Err(create_error(ErrorKind::PermissionDenied)),
+            Node::File(_) => (), // still get the original path
             Node::Dir(_) => return Err(create_error(ErrorKind::IsADirectory)),
+            Node::Special(_) => return Err(not_a_regular_file()),
             Node::Symlink(_) => match self.recurse_symlink(&path) {
                 Ok((Node::File(_), new_path)) => path = new_path,
+                Ok((Node::Special(_), _)) => return Err(not_a_regular_file()),
                 Ok((Node::Dir(_), _)) | Ok((Node::Symlink(_), _)) => {
                     return Err(create_error(ErrorKind::IsADirectory))
                 }
                 Err(e) => return Err(e),
             },
         };
+        self.check_access(&path, W_OK)?;
+        let uid = self.uid;
         match self.get_mut(&path) {
-            Ok(Node::File(file)) => Ok(file),
+            Ok(Node::File(file)) => {
+                // Writing to a set-id file someone else owns disarms it.
+                if uid != ROOT_UID && uid != file.uid {
+                    file.mode &= !(S_ISUID | S_ISGID);
+                }
+                Ok(file)
+            }
             Ok(Node::Dir(_)) => Err(create_error(ErrorKind::IsADirectory)),
             Ok(Node::Symlink(_)) => Err(create_error(ErrorKind::Other)),
+            Ok(Node::Special(_)) => Err(not_a_regular_file()),
             Err(e) => Err(e),
         }
     }
 
     fn insert(&mut self, path: PathBuf, file: Node) -> Result<()> {
         let path = self.resolve_path(&path, false)?;
-        if self.files.get(&path).is_some() {
+        self.check_creatable(&path)?;
+        if let Some(Node::Dir(dir)) = path.parent().and_then(|p| self.files.get_mut(p)) {
+            dir.modified = SystemTime::now();
+        }
+        self.files.insert(path, file);
+
+        Ok(())
+    }
+
+    /// Checks that a new node can be inserted at the resolved `path`.
+    fn check_creatable(&self, path: &Path) -> Result<()> {
+        self.check_writable(path)?;
+        if self.files.get(path).is_some() {
             return Err(create_error(ErrorKind::AlreadyExists));
         }
-        let parent: &Path = &path
+        let parent = path
             .parent()
             .ok_or_else(|| create_error(ErrorKind::NotADirectory))?;
         match self.files.get(parent) {
-            Some(Node::Dir(_)) => self.get_dir_mut(parent)?,
-            None | Some(_) => return Err(create_error(ErrorKind::NotADirectory)),
+            Some(Node::Dir(_)) => self.check_access(parent, W_OK | X_OK),
+            None | Some(_) => Err(create_error(ErrorKind::NotADirectory)),
+        }
+    }
+
+    /// Inserts a new node at `path`, owned by the current user.
+    fn create(&mut self, path: PathBuf, mut node: Node) -> Result<()> {
+        self.adopt(&path, &mut node);
+        self.insert(path, node)
+    }
+
+    /// Gives a new node at `path` to the current user and masks its mode with
+    /// the umask. In a set-group-id directory, the node gets the group of the
+    /// directory instead, and new directories are set-group-id too.
+    fn adopt(&self, path: &Path, node: &mut Node) {
+        let mut gid = self.gid;
+        let mut mode = match node {
+            // Symlinks are always created with all permission bits set.
+            Node::Symlink(_) => node.mode(),
+            _ => node.mode() & !self.umask,
         };
-        self.files.insert(path, file);
+        if let Some(Node::Dir(parent)) = path.parent().and_then(|p| self.files.get(p)) {
+            if parent.mode & S_ISGID != 0 {
+                gid = parent.gid;
+                if let Node::Dir(_) = node {
+                    mode |= S_ISGID;
+                }
+            }
+        }
+        node.set_owner(self.uid, gid);
+        node.set_mode(mode);
+    }
+
+    fn remove(&mut self, path: &Path) -> Result<Node> {
+        self.check_writable(path)?;
+        if let Some(parent) = path.parent().filter(|p| self.files.contains_key(*p)) {
+            self.check_access(parent, W_OK | X_OK)?;
+            self.check_sticky(parent, path)?;
+        }
+        let node = self
+            .files
+            .remove(path)
+            .ok_or_else(|| create_error(ErrorKind::NotFound))?;
+        if let Some(Node::Dir(parent)) = path.parent().and_then(|p| self.files.get_mut(p)) {
+            parent.modified = SystemTime::now();
+        }
+
+        Ok(node)
+    }
+
+    /// Lists every node below `path`. Symlinks are listed but not followed, so
+    /// that links to `.`, to an ancestor or out of the tree are just leaves.
+    fn descendants(&self, path: &Path) -> Vec<PathBuf> {
+        self.files
+            .keys()
+            .filter(|p| p.starts_with(path) && *p != path)
+            .cloned()
+            .collect()
+    }
+
+    fn children(&self, path: &Path) -> Vec<PathBuf> {
+        self.files
+            .keys()
+            .filter(|p| p.parent().map(|parent| parent == path).unwrap_or(false))
+            .map(|p| p.to_path_buf())
+            .collect()
+    }
+
+    /// Fails if adding `inodes` nodes and `bytes` bytes at `path` would exceed
+    /// the capacity, the maximum number of inodes or a directory quota.
+    fn reserve(&self, path: &Path, inodes: u64, bytes: u64) -> Result<()> {
+        if let Some(max_inodes) = self.max_inodes {
+            if self.files.len() as u64 + inodes > max_inodes {
+                return Err(create_error(ErrorKind::StorageFull));
+            }
+        }
+        if bytes == 0 {
+            return Ok(());
+        }
+        let mount_point = self.mount_point(path);
+        if let Some(capacity) = self.mounts[mount_point].capacity {
+            if self.used_bytes(mount_point) + bytes > capacity {
+                return Err(create_error(ErrorKind::StorageFull));
+            }
+        }
+        for (dir, quota) in &self.quotas {
+            if path.starts_with(dir) && self.used_bytes(dir) + bytes > *quota {
+                return Err(create_error(ErrorKind::QuotaExceeded));
+            }
+        }
 
         Ok(())
     }
 
-    fn remove(&mut self, path: &Path) -> Result<Node> {
-        match self.files.remove(path) {
-            Some(f) => Ok(f),
-            None => Err(create_error(ErrorKind::NotFound)),
+    /// Like `reserve`, for moving the nodes at `from` to `to`, which charges
+    /// them to the quotas above `to` that do not already count them.
+    fn reserve_move(&self, from: &Path, to: &Path) -> Result<()> {
+        let bytes = self.used_bytes(from);
+        let replaced = if self.files.contains_key(to) && !from.starts_with(to) {
+            self.used_bytes(to)
+        } else {
+            0
+        };
+        for (dir, quota) in &self.quotas {
+            if to.starts_with(dir)
+                && !from.starts_with(dir)
+                && self.used_bytes(dir).saturating_sub(replaced) + bytes > *quota
+            {
+                return Err(create_error(ErrorKind::QuotaExceeded));
+            }
         }
+
+        Ok(())
     }
 
-    fn descendants(&self, path: &Path) -> Vec<(PathBuf, u32)> {
-        let mut pathbuf = path.to_path_buf();
-        if let Ok(Node::Symlink(_)) = self.get(&path) {
-            if let Ok((_, new_path)) = self.recurse_symlink(&path) {
-                pathbuf = new_path;
+    /// Like `reserve`, for growing the existing file at `path` to `len` bytes.
+    fn reserve_len(&self, path: &Path, len: u64) -> Result<()> {
+        match self.files.get(path) {
+            Some(Node::File(file)) if file.dynamic.is_none() && len > file.contents.len() => {
+                self.reserve(path, 0, len - file.contents.len())
             }
+            _ => Ok(()),
         }
-        let path = &pathbuf;
-        let mut descendants: Vec<(PathBuf, u32)> = self
-            .files
+    }
+
+    fn stored_len(&self, path: &Path) -> u64 {
+        match self.files.get(path) {
+            Some(Node::File(file)) => file.contents.len(),
+            _ => 0,
+        }
+    }
+
+    /// Returns the total length of the files below `dir` on the same volume.
+    fn used_bytes(&self, dir: &Path) -> u64 {
+        let mount_point = self.mount_point(dir);
+        self.files
             .iter()
-            .filter(|(p, _)| p.starts_with(path) && *p != path)
-            .map(|(p, n)| {
-                (
-                    p.to_path_buf(),
-                    match n {
-                        Node::File(ref file) => file.mode,
-                        Node::Dir(ref dir) => dir.mode,
-                        Node::Symlink(ref link) => link.mode,
-                    },
-                )
+            .filter(|&(path, _)| path.starts_with(dir) && self.mount_point(path) == mount_point)
+            .map(|(_, node)| match node {
+                Node::File(file) => file.contents.len(),
+                _ => 0,
             })
-            .collect();
-        let mut found_symlink = true;
-        let mut list = descendants.clone();
-        while found_symlink {
-            found_symlink = false;
-            let mut new_list = Vec::new();
-            for (p, _) in list {
-                if let Some(Node::Symlink(_)) = self.files.get(&p) {
-                    found_symlink = true;
-                    new_list.extend(self.descendants(&p));
+            .sum()
+    }
+
+    /// Returns the mount point of the volume `path` is on.
+    fn mount_point(&self, path: &Path) -> &Path {
+        self.mounts
+            .keys()
+            .filter(|mount_point| path.starts_with(mount_point))
+            .max_by_key(|mount_point| mount_point.components().count())
+            .map(|mount_point| mount_point.as_path())
+            .unwrap_or_else(|| Path::new("/"))
+    }
+
+    fn mount_of(&self, path: &Path) -> &Mount {
+        &self.mounts[self.mount_point(path)]
+    }
+
+    /// Fails with `PermissionDenied` unless the current user has every
+    /// permission in `mode` on the node at `path`, which is not followed if it
+    /// is a symlink. The owner, group and other bits are checked, in that order.
+    fn check_access(&self, path: &Path, mode: u32) -> Result<()> {
+        let node = self.get(path)?;
+        // Root may do anything but execute files without any execute bit.
+        if self.uid == ROOT_UID {
+            if let Node::File(file) = node {
+                if mode & X_OK != 0 && file.mode & 0o111 == 0 {
+                    return Err(create_error(ErrorKind::PermissionDenied));
                 }
             }
-            descendants.extend(new_list.iter().cloned());
-            list = new_list;
+            return Ok(());
+        }
+        let (uid, gid) = node.owner();
+        let granted = if uid == self.uid {
+            node.mode() >> 6
+        } else if gid == self.gid {
+            node.mode() >> 3
+        } else {
+            node.mode()
+        };
+        if granted & mode != mode {
+            return Err(create_error(ErrorKind::PermissionDenied));
         }
-        descendants
+
+        Ok(())
     }
 
-    fn children(&self, path: &Path) -> Vec<PathBuf> {
-        self.files
+    /// Fails unless the current user owns the node at `path` or is root.
+    fn check_owner(&self, path: &Path) -> Result<()> {
+        let (owner, _) = self.get(path)?.owner();
+        if self.uid != ROOT_UID && self.uid != owner {
+            return Err(create_error(ErrorKind::PermissionDenied));
+        }
+
+        Ok(())
+    }
+
+    /// Fails with `Unsupported` if the volume of the node at `path` has no
+    /// extended attributes, or if `name` is in no namespace.
+    fn check_xattrs(&self, path: &Path, name: Option<&OsStr>) -> Result<()> {
+        self.get(path)?;
+        if !self.mount_of(path).xattrs {
+            return Err(create_error(ErrorKind::Unsupported));
+        }
+        if let Some(name) = name {
+            if xattr_namespace(name).is_none() {
+                return Err(create_error(ErrorKind::Unsupported));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fails unless the current user may change the extended attribute
+    /// `name` of the node at `path`. Like on Linux, `user` attributes are only
+    /// allowed on files and directories and need write permission. The other
+    /// namespaces are left to root.
+    fn check_xattr_writable(&self, path: &Path, name: &OsStr) -> Result<()> {
+        self.check_writable(path)?;
+        if xattr_namespace(name) != Some("user.") {
+            if self.uid != ROOT_UID {
+                return Err(create_error(ErrorKind::PermissionDenied));
+            }
+            return Ok(());
+        }
+        match self.get(path)? {
+            Node::File(_) | Node::Dir(_) => self.check_access(path, W_OK),
+            _ => Err(create_error(ErrorKind::PermissionDenied)),
+        }
+    }
+
+    /// Fails if `dir` is sticky and the current user owns neither it nor the
+    /// node at `path` in it, unless they are root.
+    fn check_sticky(&self, dir: &Path, path: &Path) -> Result<()> {
+        let sticky = self.get(dir)?.mode() & S_ISVTX != 0;
+        if sticky && self.uid != ROOT_UID {
+            let (dir_owner, _) = self.get(dir)?.owner();
+            let (owner, _) = self.get(path)?.owner();
+            if self.uid != dir_owner && self.uid != owner {
+                return Err(create_error(ErrorKind::PermissionDenied));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fails unless the current user can look up names in `dir`. Paths that
+    /// are not directories are left to the lookup to reject.
+    fn check_search(&self, dir: &Path) -> Result<()> {
+        match self.files.get(dir) {
+            Some(Node::Dir(_)) => self.check_access(dir, X_OK),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_writable(&self, path: &Path) -> Result<()> {
+        if self.mount_of(path).readonly {
+            return Err(create_error(ErrorKind::ReadOnlyFilesystem));
+        }
+
+        Ok(())
+    }
+
+    /// Fails if a volume is mounted at or below `path`.
+    fn check_not_mounted(&self, path: &Path) -> Result<()> {
+        if self
+            .mounts
             .keys()
-            .filter(|p| p.parent().map(|parent| parent == path).unwrap_or(false))
-            .map(|p| p.to_path_buf())
-            .collect()
+            .any(|mount_point| mount_point.starts_with(path))
+        {
+            return Err(create_error(ErrorKind::ResourceBusy));
+        }
+
+        Ok(())
+    }
+
+    /// With the Windows flavour, fails if a handle is open on `path` or on a
+    /// node below it.
+    fn check_not_open(&self, path: &Path) -> Result<()> {
+        if self.flavor == Flavor::Windows && self.handles.keys().any(|h| h.starts_with(path)) {
+            return Err(sharing_violation());
+        }
+
+        Ok(())
+    }
+
+    /// With the Windows flavour, fails if the file or directory at `path` has
+    /// the readonly attribute.
+    fn check_not_readonly(&self, path: &Path) -> Result<()> {
+        let readonly = match self.files.get(path) {
+            Some(Node::File(file)) => file.mode & 0o222 == 0,
+            Some(Node::Dir(dir)) => dir.mode & 0o222 == 0,
+            _ => false,
+        };
+        if self.flavor == Flavor::Windows && readonly {
+            return Err(create_error(ErrorKind::PermissionDenied));
+        }
+
+        Ok(())
     }
 
     fn rename_path(&mut self, from: &Path, to: PathBuf) -> Result<()> {
@@ -619,17 +1677,30 @@ impl Registry {
     fn move_dir(&mut self, from: &Path, to: &Path) -> Result<()> {
         self.rename_path(from, to.to_path_buf())?;
 
-        for child in self.children(from) {
+        // Only the directory itself is unlinked and linked again, so the nodes
+        // below it move without further permission checks.
+        let descendants: Vec<_> = self
+            .files
+            .keys()
+            .filter(|p| p.starts_with(from))
+            .cloned()
+            .collect();
+        for child in descendants {
             let stem = child.strip_prefix(from).unwrap_or(&child);
             let new_path = to.join(stem);
 
-            self.rename(&child, &new_path)?;
+            if let Some(node) = self.files.remove(&child) {
+                self.files.insert(new_path, node);
+            }
         }
 
         Ok(())
     }
 
+    /// Creates a symlink at `dst` pointing to `src`. `src` is stored verbatim,
+    /// so relative sources are resolved against the parent of `dst` on lookup.
     pub fn symlink(&mut self, src: &Path, dst: &Path) -> Result<()> {
+        let dst = &self.resolve_path(dst, false)?;
         if self.get(dst).is_ok() {
             return Err(create_error(ErrorKind::AlreadyExists));
         }
@@ -638,16 +1709,17 @@ impl Registry {
         } else {
             return Err(create_error(ErrorKind::NotFound));
         };
-        match self.readonly(parent) {
-            Ok(true) => Err(create_error(ErrorKind::PermissionDenied)),
-            Ok(false) => {
-                self.files.insert(
-                    PathBuf::from(dst),
-                    Node::Symlink(Symlink::new(PathBuf::from(src))),
-                );
+        match self.files.get(parent) {
+            Some(Node::Dir(_)) => {
+                self.check_access(parent, W_OK | X_OK)?;
+                self.check_writable(dst)?;
+                self.reserve(dst, 1, 0)?;
+                let mut link = Node::Symlink(Symlink::new(src.to_path_buf()));
+                self.adopt(dst, &mut link);
+                self.files.insert(dst.to_path_buf(), link);
                 Ok(())
             }
-            Err(_) => Err(create_error(ErrorKind::NotFound)),
+            _ => Err(create_error(ErrorKind::NotFound)),
         }
     }
 }
@@ -673,8 +1745,59 @@ fn create_error(kind: ErrorKind) -> Error {
         ErrorKind::Interrupted => "operation interrupted",
         ErrorKind::Other => "other os error",
         ErrorKind::UnexpectedEof => "unexpected end of file",
+        ErrorKind::StorageFull => "no storage space",
+        ErrorKind::QuotaExceeded => "filesystem quota exceeded",
+        ErrorKind::ReadOnlyFilesystem => "read-only filesystem or storage medium",
+        ErrorKind::ResourceBusy => "resource busy",
+        ErrorKind::CrossesDevices => "cross-device link or rename",
+        ErrorKind::FilesystemLoop => "filesystem loop or indirection limit (e.g. symlink loop)",
+        ErrorKind::InvalidFilename => "invalid filename",
+        ErrorKind::FileTooLarge => "file too large",
+        ErrorKind::Unsupported => "unsupported",
         _ => "other",
     };
 
     Error::new(kind, description)
 }
+
+/// Returns the namespace prefix of the extended attribute `name`, if it has
+/// one that Linux knows.
+fn xattr_namespace(name: &OsStr) -> Option<&'static str> {
+    let name = name.to_string_lossy();
+    XATTR_NAMESPACES
+        .iter()
+        .find(|namespace| name.starts_with(*namespace) && name.len() > namespace.len())
+        .cloned()
+}
+
+/// Returns the error for a missing extended attribute, `ENODATA` (`ENOATTR` on
+/// macOS), which has no `ErrorKind` of its own.
+#[cfg(unix)]
+fn no_such_xattr() -> Error {
+    #[cfg(target_os = "macos")]
+    let errno = ::libc::ENOATTR;
+    #[cfg(not(target_os = "macos"))]
+    let errno = ::libc::ENODATA;
+
+    Error::from_raw_os_error(errno)
+}
+
+#[cfg(not(unix))]
+fn no_such_xattr() -> Error {
+    Error::new(ErrorKind::Other, "no data available")
+}
+
+/// Returns the error for reading or writing the contents of a named pipe, a
+/// socket or a device, which the fake does not simulate.
+fn not_a_regular_file() -> Error {
+    Error::new(ErrorKind::InvalidInput, "not a regular file")
+}
+
+/// Returns the error for `ERROR_SHARING_VIOLATION`, which std reports without
+/// an `ErrorKind` callers may match on.
+fn sharing_violation() -> Error {
+    Error::new(
+        ErrorKind::ResourceBusy,
+        "the process cannot access the file because it is being used by another process",
+    )
+}
@@ -19,6 +19,7 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
 use std::sync::{Mutex, Weak};
 
@@ -39,12 +40,14 @@ pub struct FakeTempDir {
 }
 
 impl FakeTempDir {
-    pub fn new(registry: Weak<Mutex<Registry>>, base: &Path, prefix: &str) -> Self {
+    pub fn new(registry: Weak<Mutex<Registry>>, base: &Path, prefix: &OsStr) -> Self {
         let mut rng = rand::rng();
         let suffix: String = (1..SUFFIX_LENGTH)
             .map(|_| rng.sample(Alphanumeric) as char)
             .collect();
-        let name = format!("{}_{}", prefix, suffix);
+        let mut name = prefix.to_os_string();
+        name.push("_");
+        name.push(suffix);
         let path = base.join(prefix).join(name);
 
         FakeTempDir { registry, path }
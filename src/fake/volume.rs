@@ -0,0 +1,78 @@
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+/// A separate file system to mount inside a `FakeFileSystem` with
+/// [`FakeFileSystem::mount`].
+///
+/// By default, a volume is writable, has no capacity limit, supports extended
+/// attributes and gets a device id that no other volume uses.
+///
+/// [`FakeFileSystem::mount`]: struct.FakeFileSystem.html#method.mount
+#[derive(Clone, Debug, Default)]
+pub struct FakeVolume {
+    pub(crate) capacity: Option<u64>,
+    pub(crate) readonly: bool,
+    pub(crate) device: Option<u64>,
+    pub(crate) no_xattrs: bool,
+}
+
+impl FakeVolume {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the total length of the files on the volume.
+    /// Writes that would exceed it fail with `ErrorKind::StorageFull`.
+    pub fn capacity(mut self, capacity: u64) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Sets whether changes to the volume fail with
+    /// `ErrorKind::ReadOnlyFilesystem`.
+    pub fn readonly(mut self, readonly: bool) -> Self {
+        self.readonly = readonly;
+        self
+    }
+
+    /// Sets the device id reported for nodes on the volume.
+    pub fn device(mut self, device: u64) -> Self {
+        self.device = Some(device);
+        self
+    }
+
+    /// Sets whether nodes on the volume can have extended attributes.
+    /// Without them, the xattr methods of `UnixFileSystem` fail with
+    /// `ErrorKind::Unsupported`, as for `ENOTSUP`.
+    pub fn xattrs(mut self, supported: bool) -> Self {
+        self.no_xattrs = !supported;
+        self
+    }
+}
+
+/// A volume as mounted in the registry.
+#[derive(Clone, Debug)]
+pub struct Mount {
+    pub device: u64,
+    pub capacity: Option<u64>,
+    pub readonly: bool,
+    pub xattrs: bool,
+}
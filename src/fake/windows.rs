@@ -0,0 +1,167 @@
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::ffi::OsStr;
+use std::path::{Component, Path, PathBuf};
+
+/// Whose rules `FakeFileSystem` enforces on paths and on removing nodes.
+/// See `FakeFileSystem::set_flavor`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Flavor {
+    /// POSIX rules: any name without `/` or NUL is valid, and nodes can be
+    /// removed however they are opened.
+    #[default]
+    Unix,
+    /// Win32 rules, as applied by std on Windows:
+    ///
+    /// * `\` separates components as well as `/`, and `C:\` is the directory
+    ///   `/C:` in the registry.
+    /// * Names containing `<>:"|?*` or control characters, and reserved device
+    ///   names such as `CON`, `NUL`, `COM1` or `lpt1.txt`, fail with
+    ///   `ErrorKind::InvalidFilename`.
+    /// * Trailing dots and spaces are stripped from names.
+    /// * Paths longer than `MAX_PATH` fail with `ErrorKind::InvalidFilename`.
+    /// * Files and directories with the readonly attribute cannot be removed
+    ///   or replaced, which fails with `ErrorKind::PermissionDenied`.
+    /// * Nodes with an open handle cannot be removed or renamed, nor can the
+    ///   directories above them, which fails with `ErrorKind::ResourceBusy`
+    ///   where Windows reports `ERROR_SHARING_VIOLATION`.
+    ///
+    /// Paths starting with `\\?\` skip the stripping, the reserved names and
+    /// the length limit, as they do on Windows.
+    Windows,
+}
+
+/// Maximum length of a path, including its terminating NUL.
+const MAX_PATH: usize = 260;
+
+/// What `translate` returns for paths Windows rejects. No valid name can
+/// collide with it, since it contains a forbidden character.
+const REJECTED: &str = "/<rejected>";
+
+const FORBIDDEN: &[char] = &['<', '>', ':', '"', '|', '?', '*'];
+
+const RESERVED: &[&str] = &["CON", "PRN", "AUX", "NUL"];
+
+/// Translates a Windows path to the absolute path the registry stores it at,
+/// with `/` separators and drives as top-level directories, after applying
+/// the Win32 rules to it. Relative paths are resolved against `cwd`.
+pub(crate) fn translate(path: &Path, cwd: &Path) -> PathBuf {
+    let path = match path.to_str() {
+        Some(path) => path.replace('\\', "/"),
+        None => return cwd.join(path),
+    };
+    let (verbatim, path) = match path.strip_prefix("//?/") {
+        Some(rest) => (true, format!("/{}", rest)),
+        None => (false, path),
+    };
+    let path = match path.strip_prefix('/') {
+        Some(rest) if is_drive_path(rest) => path,
+        _ if is_drive_path(&path) => format!("/{}", path),
+        _ => path,
+    };
+
+    let mut translated = PathBuf::new();
+    for component in cwd.join(path).components() {
+        match component {
+            Component::Normal(name) => match stored_name(&translated, name, verbatim) {
+                Some(name) => translated.push(name),
+                None => return PathBuf::from(REJECTED),
+            },
+            component => translated.push(component),
+        }
+    }
+    if !verbatim && exceeds_max_path(&translated) {
+        return PathBuf::from(REJECTED);
+    }
+
+    translated
+}
+
+/// Returns whether `path` is what `translate` returns for rejected paths.
+pub(crate) fn is_rejected(path: &Path) -> bool {
+    path == Path::new(REJECTED)
+}
+
+/// Returns whether the absolute `path` is too long for the Win32 API.
+fn exceeds_max_path(path: &Path) -> bool {
+    let path = path.to_string_lossy();
+    let drive = path.strip_prefix('/').is_some_and(is_drive_path);
+    let len = path.encode_utf16().count() - usize::from(drive);
+
+    len >= MAX_PATH
+}
+
+/// Returns `name` as Windows stores it in the directory `dir`, or `None` if
+/// Windows rejects it.
+fn stored_name<'a>(dir: &Path, name: &'a OsStr, verbatim: bool) -> Option<&'a OsStr> {
+    let name_str = match name.to_str() {
+        Some(name) => name,
+        None => return Some(name),
+    };
+    if dir == Path::new("/") && is_drive(name_str) {
+        return Some(name);
+    }
+    if name_str
+        .chars()
+        .any(|c| c.is_control() || FORBIDDEN.contains(&c))
+    {
+        return None;
+    }
+    if verbatim {
+        return Some(name);
+    }
+
+    let stripped = name_str.trim_end_matches(['.', ' ']);
+    if stripped.is_empty() || is_reserved(stripped) {
+        None
+    } else {
+        Some(OsStr::new(stripped))
+    }
+}
+
+/// Returns whether `name` refers to a device, whatever its extension.
+fn is_reserved(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).trim_end_matches(' ');
+    let stem = stem.to_ascii_uppercase();
+    if RESERVED.contains(&stem.as_str()) {
+        return true;
+    }
+
+    match stem.len() {
+        4 if stem.starts_with("COM") || stem.starts_with("LPT") => {
+            matches!(stem.as_bytes()[3], b'1'..=b'9')
+        }
+        _ => false,
+    }
+}
+
+fn is_drive(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    bytes.len() == 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+fn is_drive_path(path: &str) -> bool {
+    path.len() >= 2
+        && path.is_char_boundary(2)
+        && is_drive(&path[..2])
+        && (path.len() == 2 || path[2..].starts_with('/'))
+}
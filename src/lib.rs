@@ -21,30 +21,51 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
+#[cfg(unix)]
+extern crate libc;
 #[cfg(any(feature = "mock", test))]
 extern crate pseudo;
 #[cfg(feature = "temp")]
 extern crate rand;
 #[cfg(feature = "temp")]
-extern crate tempdir;
+extern crate tempfile;
+#[cfg(feature = "fake")]
+extern crate unicode_normalization;
 
+#[cfg(any(unix, feature = "temp"))]
+use std::ffi::OsStr;
 use std::ffi::OsString;
-use std::io::Result;
+use std::io::{Error, ErrorKind, Result};
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
+#[cfg(unix)]
+pub use copy::copy_dir_all_between_unix;
+pub use copy::{copy_dir_all_between, CopyConflict, CopyOptions, CopyProgress};
 #[cfg(feature = "fake")]
-pub use fake::{FakeFileSystem, FakeTempDir};
+pub use fake::{
+    CaseFolding, CaseSensitivity, ContentSource, CrashPolicy, DynamicFile, FakeFileSystem,
+    FakeHandle, FakeTempDir, FakeVolume, Flavor, Normalization,
+};
 #[cfg(any(feature = "mock", test))]
-pub use mock::{FakeError, MockFileSystem};
+pub use mock::{DirEntry as MockDirEntry, FakeError, MockFileSystem};
+pub use mount::MountFileSystem;
 pub use os::OsFileSystem;
 #[cfg(feature = "temp")]
 pub use os::OsTempDir;
+#[cfg(unix)]
+pub use sync::sync_unix;
+pub use sync::{sync, SyncAction, SyncCompare, SyncOptions, SyncReport};
 
+mod backend;
+mod copy;
 #[cfg(feature = "fake")]
 mod fake;
 #[cfg(any(feature = "mock", test))]
 mod mock;
+mod mount;
 mod os;
+mod sync;
 
 /// Provides standard file system operations.
 pub trait FileSystem {
@@ -66,6 +87,22 @@ pub trait FileSystem {
     fn is_dir<P: AsRef<Path>>(&self, path: P) -> bool;
     /// Determines whether the path exists and points to a file.
     fn is_file<P: AsRef<Path>>(&self, path: P) -> bool;
+    /// Determines whether the path exists, following symlinks.
+    /// A broken symlink is reported as not existing.
+    /// This is based on [`std::path::Path::exists`].
+    ///
+    /// [`std::path::Path::exists`]: https://doc.rust-lang.org/std/path/struct.Path.html#method.exists
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool;
+    /// Determines whether the path exists, following symlinks.
+    /// This is based on [`std::path::Path::try_exists`].
+    ///
+    /// # Errors
+    ///
+    /// * The path could not be resolved for a reason other than a missing node,
+    ///   e.g. a symlink loop or insufficient permissions.
+    ///
+    /// [`std::path::Path::try_exists`]: https://doc.rust-lang.org/std/path/struct.Path.html#method.try_exists
+    fn try_exists<P: AsRef<Path>>(&self, path: P) -> Result<bool>;
 
     /// Creates a new directory.
     /// This is based on [`std::fs::create_dir`].
@@ -80,11 +117,17 @@ pub trait FileSystem {
     /// Removes an empty directory.
     /// This is based on [`std::fs::remove_dir`].
     ///
+    /// With the Windows flavour of the fake, fails with `ErrorKind::ResourceBusy`
+    /// if a handle is open on `path` or a node below it.
+    ///
     /// [`std::fs::remove_dir`]: https://doc.rust-lang.org/std/fs/fn.remove_dir.html
     fn remove_dir<P: AsRef<Path>>(&self, path: P) -> Result<()>;
     /// Removes a directory and any child files or directories.
     /// This is based on [`std::fs::remove_dir_all`].
     ///
+    /// With the Windows flavour of the fake, fails with `ErrorKind::ResourceBusy`
+    /// if a handle is open on `path` or a node below it.
+    ///
     /// [`std::fs::remove_dir_all`]: https://doc.rust-lang.org/std/fs/fn.remove_dir_all.html
     fn remove_dir_all<P: AsRef<Path>>(&self, path: P) -> Result<()>;
     /// Returns an iterator over the entries in a directory.
@@ -127,6 +170,59 @@ pub trait FileSystem {
     where
         P: AsRef<Path>,
         B: AsRef<[u8]>;
+    /// Appends `buf` to the file at `path`, creating it if it does not exist.
+    ///
+    /// # Errors
+    ///
+    /// * The parent directory of `path` does not exist.
+    /// * The node at `path` is a directory.
+    /// * Current user has insufficient permissions.
+    fn append_file<P, B>(&self, path: P, buf: B) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>;
+    /// Appends `buf` to an existing file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist.
+    /// * The node at `path` is a directory.
+    /// * Current user has insufficient permissions.
+    fn append_existing_file<P, B>(&self, path: P, buf: B) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>;
+    /// Replaces the contents of the file at `path` with `buf` in a single step,
+    /// so that readers see either the old or the new contents but never a mix.
+    /// The file is created if it does not exist, and keeps its mode otherwise.
+    /// If `path` is a symlink, the file it points to is replaced.
+    ///
+    /// # Errors
+    ///
+    /// * The parent directory of `path` does not exist.
+    /// * `path` is a directory.
+    /// * Current user has insufficient permissions.
+    fn write_file_atomic<P, B>(&self, path: P, buf: B) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>;
+    /// Flushes the contents and metadata of the file at `path` to durable storage.
+    /// If `path` is a directory, this is the same as `sync_dir`.
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist.
+    /// * Current user has insufficient permissions.
+    fn sync<P: AsRef<Path>>(&self, path: P) -> Result<()>;
+    /// Flushes the entries of the directory at `path` to durable storage, making
+    /// the creation, removal and renaming of its children durable.
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist.
+    /// * `path` is not a directory.
+    /// * Current user has insufficient permissions.
+    fn sync_dir<P: AsRef<Path>>(&self, path: P) -> Result<()>;
     /// Returns the contents of `path`.
     ///
     /// # Errors
@@ -156,9 +252,41 @@ pub trait FileSystem {
     where
         P: AsRef<Path>,
         B: AsMut<Vec<u8>>;
+    /// Returns up to `len` bytes of `path`, starting at `offset`.
+    /// Fewer bytes are returned if the file ends first.
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist.
+    /// * `path` is a directory.
+    /// * Current user has insufficient permissions.
+    fn read_file_range<P: AsRef<Path>>(&self, path: P, offset: u64, len: u64) -> Result<Vec<u8>>;
+    /// Writes `buf` to an existing file at `path`, starting at `offset`. The rest of
+    /// the file is left untouched, and any gap past its end is filled with zeros.
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist.
+    /// * `path` is a directory.
+    /// * Current user has insufficient permissions.
+    fn write_file_at<P, B>(&self, path: P, offset: u64, buf: B) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>;
+    /// Truncates or extends the file at `path` to `size` bytes, padding it with zeros.
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist.
+    /// * `path` is a directory.
+    /// * Current user has insufficient permissions.
+    fn set_len<P: AsRef<Path>>(&self, path: P, size: u64) -> Result<()>;
     /// Removes the file at `path`.
     /// This is based on [`std::fs::remove_file`].
     ///
+    /// With the Windows flavour of the fake, fails with `ErrorKind::ResourceBusy`
+    /// if a handle is open on `path`.
+    ///
     /// [`std::fs::remove_file`]: https://doc.rust-lang.org/std/fs/fn.remove_file.html
     fn remove_file<P: AsRef<Path>>(&self, path: P) -> Result<()>;
     /// Copies the file at path `from` to the path `to`.
@@ -166,6 +294,27 @@ pub trait FileSystem {
     ///
     /// [`std::fs::copy`]: https://doc.rust-lang.org/std/fs/fn.copy.html
     fn copy_file<P, Q>(&self, from: P, to: Q) -> Result<()>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>;
+    /// Recursively copies the directory at path `from` to the path `to`.
+    /// If `to` already exists, the contents of `from` are merged into it.
+    ///
+    /// See [`CopyOptions`] for how conflicts, modes, timestamps and symlinks are
+    /// handled. To copy between two different file systems, use
+    /// [`copy_dir_all_between`].
+    ///
+    /// # Errors
+    ///
+    /// * `from` does not exist or is not a directory.
+    /// * `to` is inside `from`.
+    /// * The parent of `to` does not exist.
+    /// * A node already exists at the destination and `CopyConflict::Error` is set.
+    /// * Current user has insufficient permissions.
+    ///
+    /// [`CopyOptions`]: struct.CopyOptions.html
+    /// [`copy_dir_all_between`]: fn.copy_dir_all_between.html
+    fn copy_dir_all<P, Q>(&self, from: P, to: Q, options: &CopyOptions) -> Result<()>
     where
         P: AsRef<Path>,
         Q: AsRef<Path>;
@@ -174,6 +323,9 @@ pub trait FileSystem {
     /// If both `from` and `to` are files, `to` will be replaced.
     /// Based on [`std::fs::rename`].
     ///
+    /// With the Windows flavour of the fake, fails with `ErrorKind::ResourceBusy`
+    /// if a handle is open on `from`, a node below it, or `to`.
+    ///
     /// [`std::fs::rename`]: https://doc.rust-lang.org/std/fs/fn.rename.html
     fn rename<P, Q>(&self, from: P, to: Q) -> Result<()>
     where
@@ -198,11 +350,125 @@ pub trait FileSystem {
     /// Returns the length of the node at the path
     /// or 0 if the node does not exist.
     fn len<P: AsRef<Path>>(&self, path: P) -> u64;
+
+    /// Returns the last modification time of `path`.
+    /// This is based on [`std::fs::Metadata::modified`].
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist.
+    /// * Current user has insufficient permissions.
+    ///
+    /// [`std::fs::Metadata::modified`]: https://doc.rust-lang.org/std/fs/struct.Metadata.html#method.modified
+    fn modified<P: AsRef<Path>>(&self, path: P) -> Result<SystemTime>;
+    /// Sets the last modification time of `path`.
+    /// This is based on [`std::fs::File::set_modified`].
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist.
+    /// * Current user has insufficient permissions.
+    ///
+    /// [`std::fs::File::set_modified`]: https://doc.rust-lang.org/std/fs/struct.File.html#method.set_modified
+    fn set_modified<P: AsRef<Path>>(&self, path: P, time: SystemTime) -> Result<()>;
+
+    /// Returns the space of the file system containing `path`.
+    /// This is based on `statvfs(3)`.
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist.
+    /// * Current user has insufficient permissions.
+    /// * The platform cannot report file system space.
+    fn space<P: AsRef<Path>>(&self, path: P) -> Result<Space>;
+}
+
+/// Space of a file system, in bytes, as returned by [`FileSystem::space`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Space {
+    /// Size of the file system.
+    pub total: u64,
+    /// Unused space.
+    pub free: u64,
+    /// Unused space the current user may write to, after reserved blocks
+    /// and quotas.
+    pub available: u64,
 }
 
 pub trait DirEntry {
     fn file_name(&self) -> OsString;
     fn path(&self) -> PathBuf;
+    /// Returns the type of the entry, without following symlinks.
+    /// This is based on [`std::fs::DirEntry::file_type`].
+    ///
+    ///
+    /// # Errors
+    ///
+    /// * The implementation does not report entry types
+    ///   (`ErrorKind::Unsupported`). This is the default.
+    ///
+    /// [`std::fs::DirEntry::file_type`]: https://doc.rust-lang.org/std/fs/struct.DirEntry.html#method.file_type
+    fn file_type(&self) -> Result<FileType> {
+        Err(Error::new(
+            ErrorKind::Unsupported,
+            "entry type is not available",
+        ))
+    }
+}
+
+/// Type of a node, as returned by [`DirEntry::file_type`].
+///
+/// [`DirEntry::file_type`]: trait.DirEntry.html#method.file_type
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FileType {
+    File,
+    Dir,
+    Symlink,
+    /// A named pipe, as created by `UnixFileSystem::mkfifo`.
+    Fifo,
+    /// A Unix domain socket.
+    Socket,
+    CharDevice,
+    BlockDevice,
+}
+
+impl FileType {
+    const ALL: [FileType; 7] = [
+        FileType::File,
+        FileType::Dir,
+        FileType::Symlink,
+        FileType::Fifo,
+        FileType::Socket,
+        FileType::CharDevice,
+        FileType::BlockDevice,
+    ];
+
+    /// Returns the file type bits of an `st_mode` for this type.
+    pub fn mode_bits(self) -> u32 {
+        match self {
+            FileType::File => 0o100_000,
+            FileType::Dir => 0o040_000,
+            FileType::Symlink => 0o120_000,
+            FileType::Fifo => 0o010_000,
+            FileType::Socket => 0o140_000,
+            FileType::CharDevice => 0o020_000,
+            FileType::BlockDevice => 0o060_000,
+        }
+    }
+
+    /// Returns the type given by the file type bits of `mode`, as returned by
+    /// `UnixFileSystem::mode`, or `None` if it has none.
+    pub fn from_mode(mode: u32) -> Option<FileType> {
+        FileType::ALL
+            .iter()
+            .copied()
+            .find(|file_type| mode & 0o170_000 == file_type.mode_bits())
+    }
+
+    /// Returns whether this is neither a file, a directory nor a symlink.
+    pub fn is_special(self) -> bool {
+        !matches!(self, FileType::File | FileType::Dir | FileType::Symlink)
+    }
 }
 
 pub trait ReadDir<T: DirEntry>: Iterator<Item = Result<T>> {}
@@ -235,6 +501,232 @@ pub trait UnixFileSystem {
     ///
     /// Based on [`std::fs::read_link`]
     fn get_symlink_src<P: AsRef<Path>>(&self, dst: P) -> Result<PathBuf>;
+    /// Determines whether the path points to a symlink, without following it.
+    /// This is based on [`std::path::Path::is_symlink`].
+    ///
+    /// [`std::path::Path::is_symlink`]: https://doc.rust-lang.org/std/path/struct.Path.html#method.is_symlink
+    fn is_symlink<P: AsRef<Path>>(&self, path: P) -> bool;
+    /// Determines whether any node exists at the path, without following a symlink
+    /// in the last component. Unlike [`FileSystem::exists`], this is `true` for
+    /// broken symlinks.
+    ///
+    /// [`FileSystem::exists`]: trait.FileSystem.html#tymethod.exists
+    fn symlink_exists<P: AsRef<Path>>(&self, path: P) -> bool;
+    /// Returns the id of the device containing `path`, following symlinks.
+    /// Nodes on different file systems have different device ids.
+    /// This is based on [`std::os::unix::fs::MetadataExt::dev`].
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist.
+    /// * Current user has insufficient permissions.
+    ///
+    /// [`std::os::unix::fs::MetadataExt::dev`]: https://doc.rust-lang.org/std/os/unix/fs/trait.MetadataExt.html#tymethod.dev
+    fn device<P: AsRef<Path>>(&self, path: P) -> Result<u64>;
+    /// Checks whether the current user may access `path`, following symlinks.
+    /// `mode` is a mask of `R_OK` (4), `W_OK` (2) and `X_OK` (1), or `F_OK`
+    /// (0) to only check that `path` exists.
+    /// This is based on `access(2)`.
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist.
+    /// * Current user lacks one of the permissions in `mode`, or cannot
+    ///   search a directory above `path`.
+    /// * `mode` includes `W_OK` and `path` is on a read-only file system.
+    fn access<P: AsRef<Path>>(&self, path: P, mode: u32) -> Result<()>;
+    /// Returns the user and group ids owning `path`, following symlinks.
+    /// This is based on [`std::os::unix::fs::MetadataExt::uid`] and
+    /// [`std::os::unix::fs::MetadataExt::gid`].
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist.
+    /// * Current user has insufficient permissions.
+    ///
+    /// [`std::os::unix::fs::MetadataExt::uid`]: https://doc.rust-lang.org/std/os/unix/fs/trait.MetadataExt.html#tymethod.uid
+    /// [`std::os::unix::fs::MetadataExt::gid`]: https://doc.rust-lang.org/std/os/unix/fs/trait.MetadataExt.html#tymethod.gid
+    fn owner<P: AsRef<Path>>(&self, path: P) -> Result<(u32, u32)>;
+    /// Changes the user and group owning `path`, following symlinks. `None`
+    /// leaves the id unchanged.
+    /// This is based on [`std::os::unix::fs::chown`].
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist.
+    /// * Current user is not root and either does not own `path` or tries to
+    ///   give it to another user or a group they are not in.
+    ///
+    /// [`std::os::unix::fs::chown`]: https://doc.rust-lang.org/std/os/unix/fs/fn.chown.html
+    fn chown<P: AsRef<Path>>(&self, path: P, uid: Option<u32>, gid: Option<u32>) -> Result<()>;
+    /// Like [`chown`], but changes a symlink itself rather than its target.
+    /// This is based on [`std::os::unix::fs::lchown`].
+    ///
+    /// [`chown`]: #tymethod.chown
+    /// [`std::os::unix::fs::lchown`]: https://doc.rust-lang.org/std/os/unix/fs/fn.lchown.html
+    fn lchown<P: AsRef<Path>>(&self, path: P, uid: Option<u32>, gid: Option<u32>) -> Result<()>;
+    /// Returns the file mode creation mask, the permission bits cleared from
+    /// the mode of new files, directories and symlinks.
+    /// This is based on `umask(2)`.
+    ///
+    /// Where the mask cannot be read from `/proc/self/status`, `OsFileSystem`
+    /// reads it by setting it and restoring it, so files that other threads
+    /// create in between may get the wrong mode.
+    fn umask(&self) -> u32;
+    /// Sets the file mode creation mask. Only the permission bits of `umask`
+    /// are used.
+    /// This is based on `umask(2)`, so for [`OsFileSystem`] it applies to the
+    /// whole process.
+    ///
+    /// [`OsFileSystem`]: struct.OsFileSystem.html
+    fn set_umask(&self, umask: u32);
+    /// Creates a new directory with the permission bits `mode`, less the
+    /// umask.
+    /// This is based on [`std::os::unix::fs::DirBuilderExt::mode`].
+    ///
+    /// # Errors
+    ///
+    /// * A file or directory already exists at `path`.
+    /// * The parent directory of `path` does not exist.
+    /// * Current user has insufficient permissions.
+    ///
+    /// [`std::os::unix::fs::DirBuilderExt::mode`]: https://doc.rust-lang.org/std/os/unix/fs/trait.DirBuilderExt.html#tymethod.mode
+    fn create_dir_with_mode<P: AsRef<Path>>(&self, path: P, mode: u32) -> Result<()>;
+    /// Writes `buf` to a new file at `path` with the permission bits `mode`,
+    /// less the umask.
+    /// This is based on [`std::os::unix::fs::OpenOptionsExt::mode`].
+    ///
+    /// # Errors
+    ///
+    /// * A file or directory already exists at `path`.
+    /// * The parent directory of `path` does not exist.
+    /// * Current user has insufficient permissions.
+    ///
+    /// [`std::os::unix::fs::OpenOptionsExt::mode`]: https://doc.rust-lang.org/std/os/unix/fs/trait.OpenOptionsExt.html#tymethod.mode
+    fn create_file_with_mode<P, B>(&self, path: P, buf: B, mode: u32) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>;
+    /// Creates a named pipe at `path` with the permission bits `mode`, less
+    /// the umask.
+    /// This is based on `mkfifo(3)`.
+    ///
+    /// # Errors
+    ///
+    /// * A node already exists at `path`.
+    /// * The parent directory of `path` does not exist.
+    /// * Current user has insufficient permissions.
+    fn mkfifo<P: AsRef<Path>>(&self, path: P, mode: u32) -> Result<()>;
+    /// Creates a node of type `file_type` at `path` with the permission bits
+    /// `mode`, less the umask. `dev` is the device number of character and
+    /// block devices, and is ignored for other types.
+    /// This is based on `mknod(2)`.
+    ///
+    /// # Errors
+    ///
+    /// * `file_type` is `FileType::Dir` or `FileType::Symlink`.
+    /// * A node already exists at `path`.
+    /// * The parent directory of `path` does not exist.
+    /// * Current user has insufficient permissions. Only root may create
+    ///   devices.
+    fn mknod<P: AsRef<Path>>(
+        &self,
+        path: P,
+        file_type: FileType,
+        mode: u32,
+        dev: u64,
+    ) -> Result<()>;
+    /// Returns the value of the extended attribute `name` of `path`, following
+    /// symlinks.
+    /// This is based on `getxattr(2)`.
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist.
+    /// * `path` has no attribute `name`. The error has the raw OS error
+    ///   `ENODATA` (`ENOATTR` on macOS).
+    /// * The file system does not support extended attributes, or `name` is
+    ///   not in a namespace it knows. The error kind is
+    ///   `ErrorKind::Unsupported`.
+    /// * Current user has insufficient permissions.
+    fn get_xattr<P, N>(&self, path: P, name: N) -> Result<Vec<u8>>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>;
+    /// Like [`get_xattr`], but reads an attribute of a symlink itself rather
+    /// than of its target.
+    /// This is based on `lgetxattr(2)`.
+    ///
+    /// [`get_xattr`]: #tymethod.get_xattr
+    fn lget_xattr<P, N>(&self, path: P, name: N) -> Result<Vec<u8>>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>;
+    /// Sets the extended attribute `name` of `path` to `value`, following
+    /// symlinks. The attribute is created if it does not exist.
+    /// This is based on `setxattr(2)`.
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist.
+    /// * The file system does not support extended attributes, or `name` is
+    ///   not in a namespace it knows.
+    /// * Current user has insufficient permissions. Attributes in the `user`
+    ///   namespace can only be set on files and directories.
+    fn set_xattr<P, N, V>(&self, path: P, name: N, value: V) -> Result<()>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>,
+        V: AsRef<[u8]>;
+    /// Like [`set_xattr`], but sets an attribute of a symlink itself rather
+    /// than of its target.
+    /// This is based on `lsetxattr(2)`.
+    ///
+    /// [`set_xattr`]: #tymethod.set_xattr
+    fn lset_xattr<P, N, V>(&self, path: P, name: N, value: V) -> Result<()>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>,
+        V: AsRef<[u8]>;
+    /// Returns the names of the extended attributes of `path`, following
+    /// symlinks. The order of the names is unspecified.
+    /// This is based on `listxattr(2)`.
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist.
+    /// * The file system does not support extended attributes.
+    fn list_xattrs<P: AsRef<Path>>(&self, path: P) -> Result<Vec<OsString>>;
+    /// Like [`list_xattrs`], but lists the attributes of a symlink itself
+    /// rather than of its target.
+    /// This is based on `llistxattr(2)`.
+    ///
+    /// [`list_xattrs`]: #tymethod.list_xattrs
+    fn llist_xattrs<P: AsRef<Path>>(&self, path: P) -> Result<Vec<OsString>>;
+    /// Removes the extended attribute `name` of `path`, following symlinks.
+    /// This is based on `removexattr(2)`.
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist.
+    /// * `path` has no attribute `name`, as for [`get_xattr`].
+    /// * The file system does not support extended attributes.
+    /// * Current user has insufficient permissions.
+    ///
+    /// [`get_xattr`]: #tymethod.get_xattr
+    fn remove_xattr<P, N>(&self, path: P, name: N) -> Result<()>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>;
+    /// Like [`remove_xattr`], but removes an attribute of a symlink itself
+    /// rather than of its target.
+    /// This is based on `lremovexattr(2)`.
+    ///
+    /// [`remove_xattr`]: #tymethod.remove_xattr
+    fn lremove_xattr<P, N>(&self, path: P, name: N) -> Result<()>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>;
 }
 
 #[cfg(feature = "temp")]
@@ -251,5 +743,11 @@ pub trait TempFileSystem {
     type TempDir: TempDir;
 
     /// Creates a new temporary directory.
-    fn temp_dir<S: AsRef<str>>(&self, prefix: S) -> Result<Self::TempDir>;
+    fn temp_dir<S: AsRef<str>>(&self, prefix: S) -> Result<Self::TempDir> {
+        self.temp_dir_os(prefix.as_ref())
+    }
+
+    /// Creates a new temporary directory whose name starts with `prefix`,
+    /// which does not need to be valid Unicode.
+    fn temp_dir_os<S: AsRef<OsStr>>(&self, prefix: S) -> Result<Self::TempDir>;
 }
@@ -23,11 +23,12 @@ use std::error::Error as StdError;
 use std::ffi::OsString;
 use std::io::{Error, ErrorKind};
 use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::vec::IntoIter;
 
 use pseudo::Mock;
 
-use FileSystem;
+use {CopyOptions, FileSystem, FileType, Space};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FakeError {
@@ -37,14 +38,15 @@ pub struct FakeError {
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct DirEntry {
-    file_name: PathBuf,
+    path: PathBuf,
     is_file: bool,
 }
 
 impl DirEntry {
-    pub fn new<P: AsRef<Path>>(file_name: P, is_file: bool) -> Self {
+    /// Creates an entry for `path`, which may be a bare file name.
+    pub fn new<P: AsRef<Path>>(path: P, is_file: bool) -> Self {
         DirEntry {
-            file_name: file_name.as_ref().to_path_buf(),
+            path: path.as_ref().to_path_buf(),
             is_file,
         }
     }
@@ -52,11 +54,22 @@ impl DirEntry {
 
 impl crate::DirEntry for DirEntry {
     fn path(&self) -> PathBuf {
-        self.file_name.clone()
+        self.path.clone()
     }
 
     fn file_name(&self) -> OsString {
-        self.file_name.clone().into_os_string()
+        self.path
+            .file_name()
+            .unwrap_or_else(|| self.path.as_os_str())
+            .to_os_string()
+    }
+
+    fn file_type(&self) -> Result<FileType, Error> {
+        Ok(if self.is_file {
+            FileType::File
+        } else {
+            FileType::Dir
+        })
     }
 }
 
@@ -107,6 +120,8 @@ pub struct MockFileSystem {
 
     pub is_dir: Mock<PathBuf, bool>,
     pub is_file: Mock<PathBuf, bool>,
+    pub exists: Mock<PathBuf, bool>,
+    pub try_exists: Mock<PathBuf, Result<bool, FakeError>>,
 
     pub create_dir: Mock<PathBuf, Result<(), FakeError>>,
     pub create_dir_all: Mock<PathBuf, Result<(), FakeError>>,
@@ -116,12 +131,21 @@ pub struct MockFileSystem {
 
     pub write_file: Mock<(PathBuf, Vec<u8>), Result<(), FakeError>>,
     pub overwrite_file: Mock<(PathBuf, Vec<u8>), Result<(), FakeError>>,
+    pub append_file: Mock<(PathBuf, Vec<u8>), Result<(), FakeError>>,
+    pub append_existing_file: Mock<(PathBuf, Vec<u8>), Result<(), FakeError>>,
+    pub write_file_atomic: Mock<(PathBuf, Vec<u8>), Result<(), FakeError>>,
+    pub sync: Mock<PathBuf, Result<(), FakeError>>,
+    pub sync_dir: Mock<PathBuf, Result<(), FakeError>>,
     pub read_file: Mock<(PathBuf), Result<Vec<u8>, FakeError>>,
     pub read_file_to_string: Mock<(PathBuf), Result<String, FakeError>>,
     pub read_file_into: Mock<(PathBuf, Vec<u8>), Result<usize, FakeError>>,
+    pub read_file_range: Mock<(PathBuf, u64, u64), Result<Vec<u8>, FakeError>>,
+    pub write_file_at: Mock<(PathBuf, u64, Vec<u8>), Result<(), FakeError>>,
+    pub set_len: Mock<(PathBuf, u64), Result<(), FakeError>>,
     pub create_file: Mock<(PathBuf, Vec<u8>), Result<(), FakeError>>,
     pub remove_file: Mock<(PathBuf), Result<(), FakeError>>,
     pub copy_file: Mock<(PathBuf, PathBuf), Result<(), FakeError>>,
+    pub copy_dir_all: Mock<(PathBuf, PathBuf), Result<(), FakeError>>,
 
     pub rename: Mock<(PathBuf, PathBuf), Result<(), FakeError>>,
 
@@ -129,6 +153,11 @@ pub struct MockFileSystem {
     pub set_readonly: Mock<(PathBuf, bool), Result<(), FakeError>>,
 
     pub len: Mock<(PathBuf), u64>,
+
+    pub modified: Mock<PathBuf, Result<SystemTime, FakeError>>,
+    pub set_modified: Mock<(PathBuf, SystemTime), Result<(), FakeError>>,
+
+    pub space: Mock<PathBuf, Result<Space, FakeError>>,
 }
 
 impl MockFileSystem {
@@ -139,6 +168,8 @@ impl MockFileSystem {
 
             is_dir: Mock::new(true),
             is_file: Mock::new(true),
+            exists: Mock::new(true),
+            try_exists: Mock::new(Ok(true)),
 
             create_dir: Mock::new(Ok(())),
             create_dir_all: Mock::new(Ok(())),
@@ -148,12 +179,21 @@ impl MockFileSystem {
 
             write_file: Mock::new(Ok(())),
             overwrite_file: Mock::new(Ok(())),
+            append_file: Mock::new(Ok(())),
+            append_existing_file: Mock::new(Ok(())),
+            write_file_atomic: Mock::new(Ok(())),
+            sync: Mock::new(Ok(())),
+            sync_dir: Mock::new(Ok(())),
             read_file: Mock::new(Ok(vec![])),
             read_file_to_string: Mock::new(Ok(String::new())),
             read_file_into: Mock::new(Ok(0)),
+            read_file_range: Mock::new(Ok(vec![])),
+            write_file_at: Mock::new(Ok(())),
+            set_len: Mock::new(Ok(())),
             create_file: Mock::new(Ok(())),
             remove_file: Mock::new(Ok(())),
             copy_file: Mock::new(Ok(())),
+            copy_dir_all: Mock::new(Ok(())),
 
             rename: Mock::new(Ok(())),
 
@@ -161,6 +201,11 @@ impl MockFileSystem {
             set_readonly: Mock::new(Ok(())),
 
             len: Mock::new(u64::default()),
+
+            modified: Mock::new(Ok(UNIX_EPOCH)),
+            set_modified: Mock::new(Ok(())),
+
+            space: Mock::new(Ok(Space::default())),
         }
     }
 }
@@ -193,6 +238,16 @@ impl FileSystem for MockFileSystem {
         self.is_file.call(path.as_ref().to_path_buf())
     }
 
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.exists.call(path.as_ref().to_path_buf())
+    }
+
+    fn try_exists<P: AsRef<Path>>(&self, path: P) -> Result<bool, Error> {
+        self.try_exists
+            .call(path.as_ref().to_path_buf())
+            .map_err(Error::from)
+    }
+
     fn create_dir<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
         self.create_dir
             .call(path.as_ref().to_path_buf())
@@ -251,6 +306,48 @@ impl FileSystem for MockFileSystem {
             .map_err(Error::from)
     }
 
+    fn append_file<P, B>(&self, path: P, buf: B) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        self.append_file
+            .call((path.as_ref().to_path_buf(), buf.as_ref().to_vec()))
+            .map_err(Error::from)
+    }
+
+    fn append_existing_file<P, B>(&self, path: P, buf: B) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        self.append_existing_file
+            .call((path.as_ref().to_path_buf(), buf.as_ref().to_vec()))
+            .map_err(Error::from)
+    }
+
+    fn write_file_atomic<P, B>(&self, path: P, buf: B) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        self.write_file_atomic
+            .call((path.as_ref().to_path_buf(), buf.as_ref().to_vec()))
+            .map_err(Error::from)
+    }
+
+    fn sync<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        self.sync
+            .call(path.as_ref().to_path_buf())
+            .map_err(Error::from)
+    }
+
+    fn sync_dir<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        self.sync_dir
+            .call(path.as_ref().to_path_buf())
+            .map_err(Error::from)
+    }
+
     fn read_file<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>, Error> {
         self.read_file
             .call(path.as_ref().to_path_buf())
@@ -273,6 +370,33 @@ impl FileSystem for MockFileSystem {
             .map_err(Error::from)
     }
 
+    fn read_file_range<P: AsRef<Path>>(
+        &self,
+        path: P,
+        offset: u64,
+        len: u64,
+    ) -> Result<Vec<u8>, Error> {
+        self.read_file_range
+            .call((path.as_ref().to_path_buf(), offset, len))
+            .map_err(Error::from)
+    }
+
+    fn write_file_at<P, B>(&self, path: P, offset: u64, buf: B) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        self.write_file_at
+            .call((path.as_ref().to_path_buf(), offset, buf.as_ref().to_vec()))
+            .map_err(Error::from)
+    }
+
+    fn set_len<P: AsRef<Path>>(&self, path: P, size: u64) -> Result<(), Error> {
+        self.set_len
+            .call((path.as_ref().to_path_buf(), size))
+            .map_err(Error::from)
+    }
+
     fn create_file<P, B>(&self, path: P, buf: B) -> Result<(), Error>
     where
         P: AsRef<Path>,
@@ -299,6 +423,16 @@ impl FileSystem for MockFileSystem {
             .map_err(Error::from)
     }
 
+    fn copy_dir_all<P, Q>(&self, from: P, to: Q, _options: &CopyOptions) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        self.copy_dir_all
+            .call((from.as_ref().to_path_buf(), to.as_ref().to_path_buf()))
+            .map_err(Error::from)
+    }
+
     fn rename<P, Q>(&self, from: P, to: Q) -> Result<(), Error>
     where
         P: AsRef<Path>,
@@ -324,4 +458,22 @@ impl FileSystem for MockFileSystem {
     fn len<P: AsRef<Path>>(&self, path: P) -> u64 {
         self.len.call(path.as_ref().to_path_buf())
     }
+
+    fn modified<P: AsRef<Path>>(&self, path: P) -> Result<SystemTime, Error> {
+        self.modified
+            .call(path.as_ref().to_path_buf())
+            .map_err(Error::from)
+    }
+
+    fn set_modified<P: AsRef<Path>>(&self, path: P, time: SystemTime) -> Result<(), Error> {
+        self.set_modified
+            .call((path.as_ref().to_path_buf(), time))
+            .map_err(Error::from)
+    }
+
+    fn space<P: AsRef<Path>>(&self, path: P) -> Result<Space, Error> {
+        self.space
+            .call(path.as_ref().to_path_buf())
+            .map_err(Error::from)
+    }
 }
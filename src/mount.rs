@@ -0,0 +1,1128 @@
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+#[cfg(unix)]
+use std::ffi::OsStr;
+use std::ffi::OsString;
+use std::fmt;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::SystemTime;
+use std::vec::IntoIter;
+
+#[cfg(not(unix))]
+use copy::copy_dir_all_between;
+#[cfg(unix)]
+use copy::copy_dir_all_between_unix;
+#[cfg(unix)]
+use UnixFileSystem;
+use {CopyOptions, FileSystem, FileType, Space};
+
+/// A file system made of other file systems mounted at different paths, e.g.
+/// an `OsFileSystem` at `/` and a `FakeFileSystem` at `/scratch`.
+///
+/// Each call goes to the file system mounted at the longest prefix of its path.
+/// Paths are made absolute against the current directory of the
+/// `MountFileSystem` and `..` is resolved lexically before routing, so the
+/// current directory of the mounted file systems is never used. Symlinks are
+/// resolved by the file system they are on and never lead to another mount.
+///
+/// Clones share the mount table and the current directory.
+#[derive(Clone, Default)]
+pub struct MountFileSystem {
+    mounts: Arc<RwLock<Vec<Mount>>>,
+    cwd: Arc<Mutex<PathBuf>>,
+}
+
+#[derive(Clone)]
+struct Mount {
+    path: PathBuf,
+    root: PathBuf,
+    fs: Arc<dyn Backend>,
+    #[cfg(unix)]
+    unix: Option<Arc<dyn UnixBackend>>,
+}
+
+/// Where a path leads: the mount it is on and the path within that mount.
+struct Route {
+    mount: Mount,
+    path: PathBuf,
+}
+
+impl MountFileSystem {
+    pub fn new() -> Self {
+        MountFileSystem {
+            mounts: Arc::default(),
+            cwd: Arc::new(Mutex::new(PathBuf::from("/"))),
+        }
+    }
+
+    /// Mounts `fs` at the absolute `path`, so that `path` leads to `root` in `fs`.
+    ///
+    /// The Unix operations of a `MountFileSystem` fail with
+    /// `ErrorKind::Unsupported` for paths on file systems mounted this way; use
+    /// `mount_unix` to support them.
+    ///
+    /// # Errors
+    ///
+    /// * `path` or `root` is relative.
+    /// * A file system is already mounted at `path`.
+    pub fn mount<P, Q, F>(&self, path: P, fs: F, root: Q) -> Result<()>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+        F: FileSystem + Send + Sync + 'static,
+    {
+        self.insert(Mount {
+            path: path.as_ref().to_path_buf(),
+            root: root.as_ref().to_path_buf(),
+            fs: Arc::new(fs),
+            #[cfg(unix)]
+            unix: None,
+        })
+    }
+
+    /// Like `mount`, for file systems that also support the Unix operations.
+    #[cfg(unix)]
+    pub fn mount_unix<P, Q, F>(&self, path: P, fs: F, root: Q) -> Result<()>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+        F: FileSystem + UnixFileSystem + Send + Sync + 'static,
+    {
+        let fs = Arc::new(fs);
+        self.insert(Mount {
+            path: path.as_ref().to_path_buf(),
+            root: root.as_ref().to_path_buf(),
+            fs: fs.clone(),
+            unix: Some(fs),
+        })
+    }
+
+    /// Removes the file system mounted at `path`.
+    ///
+    /// # Errors
+    ///
+    /// * No file system is mounted at `path`.
+    pub fn unmount<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = self.absolute(path.as_ref());
+        let mut mounts = self.mounts.write().unwrap();
+        match mounts.iter().position(|mount| mount.path == path) {
+            Some(index) => {
+                mounts.remove(index);
+                Ok(())
+            }
+            None => Err(Error::new(ErrorKind::InvalidInput, "not a mount point")),
+        }
+    }
+
+    fn insert(&self, mount: Mount) -> Result<()> {
+        if mount.path.is_relative() || mount.root.is_relative() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "mount paths must be absolute",
+            ));
+        }
+        let mount = Mount {
+            path: normalize(&mount.path),
+            ..mount
+        };
+
+        let mut mounts = self.mounts.write().unwrap();
+        if mounts.iter().any(|m| m.path == mount.path) {
+            return Err(Error::new(ErrorKind::AlreadyExists, "already mounted"));
+        }
+        mounts.push(mount);
+
+        Ok(())
+    }
+
+    fn absolute(&self, path: &Path) -> PathBuf {
+        if path.is_relative() {
+            normalize(&self.cwd.lock().unwrap().join(path))
+        } else {
+            normalize(path)
+        }
+    }
+
+    /// Finds the mount with the longest prefix of `path`.
+    fn route(&self, path: &Path) -> Result<Route> {
+        let path = self.absolute(path);
+        let mounts = self.mounts.read().unwrap();
+        let mount = mounts
+            .iter()
+            .filter(|mount| path.starts_with(&mount.path))
+            .max_by_key(|mount| mount.path.components().count())
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "no file system mounted"))?;
+        let rest = path.strip_prefix(&mount.path).unwrap_or(Path::new(""));
+
+        Ok(Route {
+            path: if rest.as_os_str().is_empty() {
+                mount.root.clone()
+            } else {
+                mount.root.join(rest)
+            },
+            mount: mount.clone(),
+        })
+    }
+
+    fn is_mount_point(&self, path: &Path) -> bool {
+        let path = self.absolute(path);
+        self.mounts.read().unwrap().iter().any(|m| m.path == path)
+    }
+
+    /// Fails if a file system is mounted at or below `path`.
+    fn check_not_mounted(&self, path: &Path) -> Result<()> {
+        let path = self.absolute(path);
+        if self
+            .mounts
+            .read()
+            .unwrap()
+            .iter()
+            .any(|m| m.path.starts_with(&path))
+        {
+            return Err(Error::new(ErrorKind::ResourceBusy, "mount point is busy"));
+        }
+
+        Ok(())
+    }
+
+    /// Routes `from` and `to`, failing if they are on different mounts.
+    fn route_same(&self, from: &Path, to: &Path) -> Result<(Route, Route)> {
+        let (from, to) = (self.route(from)?, self.route(to)?);
+        if from.mount.path != to.mount.path {
+            return Err(Error::new(
+                ErrorKind::CrossesDevices,
+                "cross-device link or rename",
+            ));
+        }
+
+        Ok((from, to))
+    }
+}
+
+impl fmt::Debug for MountFileSystem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mounts = self.mounts.read().unwrap();
+        f.debug_struct("MountFileSystem")
+            .field(
+                "mounts",
+                &mounts
+                    .iter()
+                    .map(|m| (&m.path, &m.root))
+                    .collect::<Vec<_>>(),
+            )
+            .field("cwd", &*self.cwd.lock().unwrap())
+            .finish()
+    }
+}
+
+impl FileSystem for MountFileSystem {
+    type DirEntry = DirEntry;
+    type ReadDir = ReadDir;
+
+    fn current_dir(&self) -> Result<PathBuf> {
+        Ok(self.cwd.lock().unwrap().clone())
+    }
+
+    fn set_current_dir<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let route = self.route(path.as_ref())?;
+        if !route.mount.fs.is_dir(&route.path) {
+            return Err(if route.mount.fs.exists(&route.path) {
+                Error::new(ErrorKind::NotADirectory, "not a directory")
+            } else {
+                Error::new(ErrorKind::NotFound, "entity not found")
+            });
+        }
+
+        *self.cwd.lock().unwrap() = self.absolute(path.as_ref());
+
+        Ok(())
+    }
+
+    fn is_dir<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.route(path.as_ref())
+            .map(|r| r.mount.fs.is_dir(&r.path))
+            .unwrap_or(false)
+    }
+
+    fn is_file<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.route(path.as_ref())
+            .map(|r| r.mount.fs.is_file(&r.path))
+            .unwrap_or(false)
+    }
+
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.route(path.as_ref())
+            .map(|r| r.mount.fs.exists(&r.path))
+            .unwrap_or(false)
+    }
+
+    fn try_exists<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        match self.route(path.as_ref()) {
+            Ok(r) => r.mount.fs.try_exists(&r.path),
+            Err(_) => Ok(false),
+        }
+    }
+
+    fn create_dir<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        if self.is_mount_point(path.as_ref()) {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                "entity already exists",
+            ));
+        }
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.create_dir(&r.path)
+    }
+
+    fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.create_dir_all(&r.path)
+    }
+
+    fn remove_dir<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.check_not_mounted(path.as_ref())?;
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.remove_dir(&r.path)
+    }
+
+    fn remove_dir_all<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.check_not_mounted(path.as_ref())?;
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.remove_dir_all(&r.path)
+    }
+
+    /// Lists the entries of the directory at `path`, followed by any mount
+    /// points directly below it that it does not contain.
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<Self::ReadDir> {
+        let path = self.absolute(path.as_ref());
+        let r = self.route(&path)?;
+        let mut entries: Vec<_> = r
+            .mount
+            .fs
+            .read_dir(&r.path)?
+            .into_iter()
+            .map(|entry| entry.map(|(name, file_type)| DirEntry::new(&path, name, file_type)))
+            .collect();
+
+        let mut mount_points: Vec<_> = self
+            .mounts
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|m| m.path.parent() == Some(&path))
+            .filter_map(|m| m.path.file_name().map(|name| name.to_os_string()))
+            .collect();
+        mount_points.sort();
+        for name in mount_points {
+            let listed = entries
+                .iter()
+                .any(|entry| entry.as_ref().ok().map(|e| &e.file_name) == Some(&name));
+            if !listed {
+                entries.push(Ok(DirEntry::new(&path, name, FileType::Dir)));
+            }
+        }
+
+        Ok(ReadDir(entries.into_iter()))
+    }
+
+    fn create_file<P, B>(&self, path: P, buf: B) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.create_file(&r.path, buf.as_ref())
+    }
+
+    fn write_file<P, B>(&self, path: P, buf: B) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.write_file(&r.path, buf.as_ref())
+    }
+
+    fn overwrite_file<P, B>(&self, path: P, buf: B) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.overwrite_file(&r.path, buf.as_ref())
+    }
+
+    fn append_file<P, B>(&self, path: P, buf: B) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.append_file(&r.path, buf.as_ref())
+    }
+
+    fn append_existing_file<P, B>(&self, path: P, buf: B) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.append_existing_file(&r.path, buf.as_ref())
+    }
+
+    fn write_file_atomic<P, B>(&self, path: P, buf: B) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.write_file_atomic(&r.path, buf.as_ref())
+    }
+
+    fn sync<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.sync(&r.path)
+    }
+
+    fn sync_dir<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.sync_dir(&r.path)
+    }
+
+    fn read_file<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.read_file(&r.path)
+    }
+
+    fn read_file_to_string<P: AsRef<Path>>(&self, path: P) -> Result<String> {
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.read_file_to_string(&r.path)
+    }
+
+    fn read_file_into<P, B>(&self, path: P, mut buf: B) -> Result<usize>
+    where
+        P: AsRef<Path>,
+        B: AsMut<Vec<u8>>,
+    {
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.read_file_into(&r.path, buf.as_mut())
+    }
+
+    fn read_file_range<P: AsRef<Path>>(&self, path: P, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.read_file_range(&r.path, offset, len)
+    }
+
+    fn write_file_at<P, B>(&self, path: P, offset: u64, buf: B) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.write_file_at(&r.path, offset, buf.as_ref())
+    }
+
+    fn set_len<P: AsRef<Path>>(&self, path: P, size: u64) -> Result<()> {
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.set_len(&r.path, size)
+    }
+
+    fn remove_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.remove_file(&r.path)
+    }
+
+    /// Copies within a mount with the mounted file system, and across mounts
+    /// by reading `from` and writing its contents to `to`.
+    fn copy_file<P, Q>(&self, from: P, to: Q) -> Result<()>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        match self.route_same(from.as_ref(), to.as_ref()) {
+            Ok((from, to)) => from.mount.fs.copy_file(&from.path, &to.path),
+            Err(ref e) if e.kind() == ErrorKind::CrossesDevices => {
+                let (from, to) = (self.route(from.as_ref())?, self.route(to.as_ref())?);
+                if from.mount.fs.is_dir(&from.path) {
+                    return Err(Error::new(ErrorKind::InvalidInput, "source is a directory"));
+                }
+                let contents = from.mount.fs.read_file(&from.path)?;
+                to.mount.fs.write_file(&to.path, &contents)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Copies within a mount with the mounted file system. Copies that span
+    /// mounts go node by node like [`copy_dir_all_between_unix`], which needs
+    /// the modes of files on mounts made with `mount` to be left alone with
+    /// `CopyOptions::preserve_mode(false)`. Outside Unix, they go like
+    /// [`copy_dir_all_between`] instead, which also needs
+    /// `CopyOptions::follow_symlinks(true)`.
+    ///
+    /// [`copy_dir_all_between`]: fn.copy_dir_all_between.html
+    /// [`copy_dir_all_between_unix`]: fn.copy_dir_all_between_unix.html
+    fn copy_dir_all<P, Q>(&self, from: P, to: Q, options: &CopyOptions) -> Result<()>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let (from, to) = (self.absolute(from.as_ref()), self.absolute(to.as_ref()));
+        if to.starts_with(&from) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "cannot copy a directory into itself",
+            ));
+        }
+        let spans_mounts = self.check_not_mounted(&from).is_err()
+            || self.check_not_mounted(&to).is_err()
+            || self.route_same(&from, &to).is_err();
+        if !spans_mounts {
+            let (from, to) = self.route_same(&from, &to)?;
+            return from.mount.fs.copy_dir_all(&from.path, &to.path, options);
+        }
+
+        #[cfg(unix)]
+        return copy_dir_all_between_unix(self, &from, self, &to, options);
+        #[cfg(not(unix))]
+        copy_dir_all_between(self, &from, self, &to, options)
+    }
+
+    /// Renames within a mount.
+    ///
+    /// # Errors
+    ///
+    /// Besides the errors of the mounted file system:
+    ///
+    /// * `from` and `to` are on different mounts (`ErrorKind::CrossesDevices`).
+    /// * `from` or `to` is a mount point, or `from` contains one
+    ///   (`ErrorKind::ResourceBusy`).
+    fn rename<P, Q>(&self, from: P, to: Q) -> Result<()>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        self.check_not_mounted(from.as_ref())?;
+        if self.is_mount_point(to.as_ref()) {
+            return Err(Error::new(ErrorKind::ResourceBusy, "mount point is busy"));
+        }
+        let (from, to) = self.route_same(from.as_ref(), to.as_ref())?;
+        from.mount.fs.rename(&from.path, &to.path)
+    }
+
+    fn readonly<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.readonly(&r.path)
+    }
+
+    fn set_readonly<P: AsRef<Path>>(&self, path: P, readonly: bool) -> Result<()> {
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.set_readonly(&r.path, readonly)
+    }
+
+    fn len<P: AsRef<Path>>(&self, path: P) -> u64 {
+        self.route(path.as_ref())
+            .map(|r| r.mount.fs.len(&r.path))
+            .unwrap_or(0)
+    }
+
+    fn modified<P: AsRef<Path>>(&self, path: P) -> Result<SystemTime> {
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.modified(&r.path)
+    }
+
+    fn set_modified<P: AsRef<Path>>(&self, path: P, time: SystemTime) -> Result<()> {
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.set_modified(&r.path, time)
+    }
+
+    fn space<P: AsRef<Path>>(&self, path: P) -> Result<Space> {
+        let r = self.route(path.as_ref())?;
+        r.mount.fs.space(&r.path)
+    }
+}
+
+#[cfg(unix)]
+impl MountFileSystem {
+    fn route_unix(&self, path: &Path) -> Result<(Arc<dyn UnixBackend>, PathBuf)> {
+        let r = self.route(path)?;
+        match r.mount.unix {
+            Some(unix) => Ok((unix, r.path)),
+            None => Err(Error::new(
+                ErrorKind::Unsupported,
+                "file system is not mounted with mount_unix",
+            )),
+        }
+    }
+}
+
+/// Symlink sources are passed to and returned from the mounted file system
+/// verbatim, so absolute sources are paths within that file system.
+#[cfg(unix)]
+impl UnixFileSystem for MountFileSystem {
+    fn mode<P: AsRef<Path>>(&self, path: P) -> Result<u32> {
+        let (fs, path) = self.route_unix(path.as_ref())?;
+        fs.mode(&path)
+    }
+
+    fn set_mode<P: AsRef<Path>>(&self, path: P, mode: u32) -> Result<()> {
+        let (fs, path) = self.route_unix(path.as_ref())?;
+        fs.set_mode(&path, mode)
+    }
+
+    fn symlink<P: AsRef<Path>, Q: AsRef<Path>>(&self, src: P, dst: Q) -> Result<()> {
+        let (fs, dst) = self.route_unix(dst.as_ref())?;
+        fs.symlink(src.as_ref(), &dst)
+    }
+
+    fn get_symlink_src<P: AsRef<Path>>(&self, dst: P) -> Result<PathBuf> {
+        let (fs, dst) = self.route_unix(dst.as_ref())?;
+        fs.get_symlink_src(&dst)
+    }
+
+    fn is_symlink<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.route_unix(path.as_ref())
+            .map(|(fs, path)| fs.is_symlink(&path))
+            .unwrap_or(false)
+    }
+
+    fn symlink_exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        match self.route_unix(path.as_ref()) {
+            Ok((fs, path)) => fs.symlink_exists(&path),
+            Err(_) => FileSystem::exists(self, path),
+        }
+    }
+
+    fn device<P: AsRef<Path>>(&self, path: P) -> Result<u64> {
+        let (fs, path) = self.route_unix(path.as_ref())?;
+        fs.device(&path)
+    }
+
+    fn access<P: AsRef<Path>>(&self, path: P, mode: u32) -> Result<()> {
+        let (fs, path) = self.route_unix(path.as_ref())?;
+        fs.access(&path, mode)
+    }
+
+    fn owner<P: AsRef<Path>>(&self, path: P) -> Result<(u32, u32)> {
+        let (fs, path) = self.route_unix(path.as_ref())?;
+        fs.owner(&path)
+    }
+
+    fn chown<P: AsRef<Path>>(&self, path: P, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+        let (fs, path) = self.route_unix(path.as_ref())?;
+        fs.chown(&path, uid, gid)
+    }
+
+    fn lchown<P: AsRef<Path>>(&self, path: P, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+        let (fs, path) = self.route_unix(path.as_ref())?;
+        fs.lchown(&path, uid, gid)
+    }
+
+    /// Returns the umask of the file system mounted at `/`, or the usual
+    /// `0o022` if it does not support Unix operations.
+    fn umask(&self) -> u32 {
+        self.route_unix(Path::new("/"))
+            .map(|(fs, _)| fs.umask())
+            .unwrap_or(0o022)
+    }
+
+    /// Sets the umask of every mounted file system that supports Unix
+    /// operations, as they are all used by the same process.
+    fn set_umask(&self, umask: u32) {
+        for mount in self.mounts.read().unwrap().iter() {
+            if let Some(ref unix) = mount.unix {
+                unix.set_umask(umask);
+            }
+        }
+    }
+
+    fn create_dir_with_mode<P: AsRef<Path>>(&self, path: P, mode: u32) -> Result<()> {
+        if self.is_mount_point(path.as_ref()) {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                "entity already exists",
+            ));
+        }
+        let (fs, path) = self.route_unix(path.as_ref())?;
+        fs.create_dir_with_mode(&path, mode)
+    }
+
+    fn create_file_with_mode<P, B>(&self, path: P, buf: B, mode: u32) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        let (fs, path) = self.route_unix(path.as_ref())?;
+        fs.create_file_with_mode(&path, buf.as_ref(), mode)
+    }
+
+    fn mkfifo<P: AsRef<Path>>(&self, path: P, mode: u32) -> Result<()> {
+        let (fs, path) = self.route_unix(path.as_ref())?;
+        fs.mkfifo(&path, mode)
+    }
+
+    fn mknod<P: AsRef<Path>>(
+        &self,
+        path: P,
+        file_type: FileType,
+        mode: u32,
+        dev: u64,
+    ) -> Result<()> {
+        let (fs, path) = self.route_unix(path.as_ref())?;
+        fs.mknod(&path, file_type, mode, dev)
+    }
+
+    fn get_xattr<P, N>(&self, path: P, name: N) -> Result<Vec<u8>>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>,
+    {
+        let (fs, path) = self.route_unix(path.as_ref())?;
+        fs.get_xattr(&path, name.as_ref(), true)
+    }
+
+    fn lget_xattr<P, N>(&self, path: P, name: N) -> Result<Vec<u8>>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>,
+    {
+        let (fs, path) = self.route_unix(path.as_ref())?;
+        fs.get_xattr(&path, name.as_ref(), false)
+    }
+
+    fn set_xattr<P, N, V>(&self, path: P, name: N, value: V) -> Result<()>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>,
+        V: AsRef<[u8]>,
+    {
+        let (fs, path) = self.route_unix(path.as_ref())?;
+        fs.set_xattr(&path, name.as_ref(), value.as_ref(), true)
+    }
+
+    fn lset_xattr<P, N, V>(&self, path: P, name: N, value: V) -> Result<()>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>,
+        V: AsRef<[u8]>,
+    {
+        let (fs, path) = self.route_unix(path.as_ref())?;
+        fs.set_xattr(&path, name.as_ref(), value.as_ref(), false)
+    }
+
+    fn list_xattrs<P: AsRef<Path>>(&self, path: P) -> Result<Vec<OsString>> {
+        let (fs, path) = self.route_unix(path.as_ref())?;
+        fs.list_xattrs(&path, true)
+    }
+
+    fn llist_xattrs<P: AsRef<Path>>(&self, path: P) -> Result<Vec<OsString>> {
+        let (fs, path) = self.route_unix(path.as_ref())?;
+        fs.list_xattrs(&path, false)
+    }
+
+    fn remove_xattr<P, N>(&self, path: P, name: N) -> Result<()>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>,
+    {
+        let (fs, path) = self.route_unix(path.as_ref())?;
+        fs.remove_xattr(&path, name.as_ref(), true)
+    }
+
+    fn lremove_xattr<P, N>(&self, path: P, name: N) -> Result<()>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>,
+    {
+        let (fs, path) = self.route_unix(path.as_ref())?;
+        fs.remove_xattr(&path, name.as_ref(), false)
+    }
+}
+
+/// Resolves `.` and `..` in `path` without looking at the file system.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component.as_os_str()),
+        }
+    }
+
+    normalized
+}
+
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    parent: PathBuf,
+    file_name: OsString,
+    file_type: FileType,
+}
+
+impl DirEntry {
+    fn new(parent: &Path, file_name: OsString, file_type: FileType) -> Self {
+        DirEntry {
+            parent: parent.to_path_buf(),
+            file_name,
+            file_type,
+        }
+    }
+}
+
+impl ::DirEntry for DirEntry {
+    fn file_name(&self) -> OsString {
+        self.file_name.clone()
+    }
+
+    fn path(&self) -> PathBuf {
+        self.parent.join(&self.file_name)
+    }
+
+    fn file_type(&self) -> Result<FileType> {
+        Ok(self.file_type)
+    }
+}
+
+#[derive(Debug)]
+pub struct ReadDir(IntoIter<Result<DirEntry>>);
+
+impl Iterator for ReadDir {
+    type Item = Result<DirEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+impl ::ReadDir<DirEntry> for ReadDir {}
+
+/// The `FileSystem` operations as a trait object, to mount different
+/// implementations side by side.
+trait Backend: Send + Sync {
+    fn is_dir(&self, path: &Path) -> bool;
+    fn is_file(&self, path: &Path) -> bool;
+    fn exists(&self, path: &Path) -> bool;
+    fn try_exists(&self, path: &Path) -> Result<bool>;
+    fn create_dir(&self, path: &Path) -> Result<()>;
+    fn create_dir_all(&self, path: &Path) -> Result<()>;
+    fn remove_dir(&self, path: &Path) -> Result<()>;
+    fn remove_dir_all(&self, path: &Path) -> Result<()>;
+    fn read_dir(&self, path: &Path) -> Result<Vec<Result<(OsString, FileType)>>>;
+    fn create_file(&self, path: &Path, buf: &[u8]) -> Result<()>;
+    fn write_file(&self, path: &Path, buf: &[u8]) -> Result<()>;
+    fn overwrite_file(&self, path: &Path, buf: &[u8]) -> Result<()>;
+    fn append_file(&self, path: &Path, buf: &[u8]) -> Result<()>;
+    fn append_existing_file(&self, path: &Path, buf: &[u8]) -> Result<()>;
+    fn write_file_atomic(&self, path: &Path, buf: &[u8]) -> Result<()>;
+    fn sync(&self, path: &Path) -> Result<()>;
+    fn sync_dir(&self, path: &Path) -> Result<()>;
+    fn read_file(&self, path: &Path) -> Result<Vec<u8>>;
+    fn read_file_to_string(&self, path: &Path) -> Result<String>;
+    fn read_file_into(&self, path: &Path, buf: &mut Vec<u8>) -> Result<usize>;
+    fn read_file_range(&self, path: &Path, offset: u64, len: u64) -> Result<Vec<u8>>;
+    fn write_file_at(&self, path: &Path, offset: u64, buf: &[u8]) -> Result<()>;
+    fn set_len(&self, path: &Path, size: u64) -> Result<()>;
+    fn remove_file(&self, path: &Path) -> Result<()>;
+    fn copy_file(&self, from: &Path, to: &Path) -> Result<()>;
+    fn copy_dir_all(&self, from: &Path, to: &Path, options: &CopyOptions) -> Result<()>;
+    fn rename(&self, from: &Path, to: &Path) -> Result<()>;
+    fn readonly(&self, path: &Path) -> Result<bool>;
+    fn set_readonly(&self, path: &Path, readonly: bool) -> Result<()>;
+    fn len(&self, path: &Path) -> u64;
+    fn modified(&self, path: &Path) -> Result<SystemTime>;
+    fn set_modified(&self, path: &Path, time: SystemTime) -> Result<()>;
+    fn space(&self, path: &Path) -> Result<Space>;
+}
+
+impl<F: FileSystem + Send + Sync> Backend for F {
+    fn is_dir(&self, path: &Path) -> bool {
+        FileSystem::is_dir(self, path)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        FileSystem::is_file(self, path)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        FileSystem::exists(self, path)
+    }
+
+    fn try_exists(&self, path: &Path) -> Result<bool> {
+        FileSystem::try_exists(self, path)
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        FileSystem::create_dir(self, path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<()> {
+        FileSystem::create_dir_all(self, path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<()> {
+        FileSystem::remove_dir(self, path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> Result<()> {
+        FileSystem::remove_dir_all(self, path)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<Result<(OsString, FileType)>>> {
+        Ok(FileSystem::read_dir(self, path)?
+            .map(|entry| {
+                entry.and_then(|e| Ok((::DirEntry::file_name(&e), ::DirEntry::file_type(&e)?)))
+            })
+            .collect())
+    }
+
+    fn create_file(&self, path: &Path, buf: &[u8]) -> Result<()> {
+        FileSystem::create_file(self, path, buf)
+    }
+
+    fn write_file(&self, path: &Path, buf: &[u8]) -> Result<()> {
+        FileSystem::write_file(self, path, buf)
+    }
+
+    fn overwrite_file(&self, path: &Path, buf: &[u8]) -> Result<()> {
+        FileSystem::overwrite_file(self, path, buf)
+    }
+
+    fn append_file(&self, path: &Path, buf: &[u8]) -> Result<()> {
+        FileSystem::append_file(self, path, buf)
+    }
+
+    fn append_existing_file(&self, path: &Path, buf: &[u8]) -> Result<()> {
+        FileSystem::append_existing_file(self, path, buf)
+    }
+
+    fn write_file_atomic(&self, path: &Path, buf: &[u8]) -> Result<()> {
+        FileSystem::write_file_atomic(self, path, buf)
+    }
+
+    fn sync(&self, path: &Path) -> Result<()> {
+        FileSystem::sync(self, path)
+    }
+
+    fn sync_dir(&self, path: &Path) -> Result<()> {
+        FileSystem::sync_dir(self, path)
+    }
+
+    fn read_file(&self, path: &Path) -> Result<Vec<u8>> {
+        FileSystem::read_file(self, path)
+    }
+
+    fn read_file_to_string(&self, path: &Path) -> Result<String> {
+        FileSystem::read_file_to_string(self, path)
+    }
+
+    fn read_file_into(&self, path: &Path, buf: &mut Vec<u8>) -> Result<usize> {
+        FileSystem::read_file_into(self, path, buf)
+    }
+
+    fn read_file_range(&self, path: &Path, offset: u64, len: u64) -> Result<Vec<u8>> {
+        FileSystem::read_file_range(self, path, offset, len)
+    }
+
+    fn write_file_at(&self, path: &Path, offset: u64, buf: &[u8]) -> Result<()> {
+        FileSystem::write_file_at(self, path, offset, buf)
+    }
+
+    fn set_len(&self, path: &Path, size: u64) -> Result<()> {
+        FileSystem::set_len(self, path, size)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        FileSystem::remove_file(self, path)
+    }
+
+    fn copy_file(&self, from: &Path, to: &Path) -> Result<()> {
+        FileSystem::copy_file(self, from, to)
+    }
+
+    fn copy_dir_all(&self, from: &Path, to: &Path, options: &CopyOptions) -> Result<()> {
+        FileSystem::copy_dir_all(self, from, to, options)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        FileSystem::rename(self, from, to)
+    }
+
+    fn readonly(&self, path: &Path) -> Result<bool> {
+        FileSystem::readonly(self, path)
+    }
+
+    fn set_readonly(&self, path: &Path, readonly: bool) -> Result<()> {
+        FileSystem::set_readonly(self, path, readonly)
+    }
+
+    fn len(&self, path: &Path) -> u64 {
+        FileSystem::len(self, path)
+    }
+
+    fn modified(&self, path: &Path) -> Result<SystemTime> {
+        FileSystem::modified(self, path)
+    }
+
+    fn set_modified(&self, path: &Path, time: SystemTime) -> Result<()> {
+        FileSystem::set_modified(self, path, time)
+    }
+
+    fn space(&self, path: &Path) -> Result<Space> {
+        FileSystem::space(self, path)
+    }
+}
+
+/// The `UnixFileSystem` operations as a trait object.
+#[cfg(unix)]
+trait UnixBackend: Send + Sync {
+    fn mode(&self, path: &Path) -> Result<u32>;
+    fn set_mode(&self, path: &Path, mode: u32) -> Result<()>;
+    fn symlink(&self, src: &Path, dst: &Path) -> Result<()>;
+    fn get_symlink_src(&self, dst: &Path) -> Result<PathBuf>;
+    fn is_symlink(&self, path: &Path) -> bool;
+    fn symlink_exists(&self, path: &Path) -> bool;
+    fn device(&self, path: &Path) -> Result<u64>;
+    fn access(&self, path: &Path, mode: u32) -> Result<()>;
+    fn owner(&self, path: &Path) -> Result<(u32, u32)>;
+    fn chown(&self, path: &Path, uid: Option<u32>, gid: Option<u32>) -> Result<()>;
+    fn lchown(&self, path: &Path, uid: Option<u32>, gid: Option<u32>) -> Result<()>;
+    fn umask(&self) -> u32;
+    fn set_umask(&self, umask: u32);
+    fn create_dir_with_mode(&self, path: &Path, mode: u32) -> Result<()>;
+    fn create_file_with_mode(&self, path: &Path, buf: &[u8], mode: u32) -> Result<()>;
+    fn mkfifo(&self, path: &Path, mode: u32) -> Result<()>;
+    fn mknod(&self, path: &Path, file_type: FileType, mode: u32, dev: u64) -> Result<()>;
+    // The `l*` variants of the xattr methods are the same methods with
+    // `follow` set to false.
+    fn get_xattr(&self, path: &Path, name: &OsStr, follow: bool) -> Result<Vec<u8>>;
+    fn set_xattr(&self, path: &Path, name: &OsStr, value: &[u8], follow: bool) -> Result<()>;
+    fn list_xattrs(&self, path: &Path, follow: bool) -> Result<Vec<OsString>>;
+    fn remove_xattr(&self, path: &Path, name: &OsStr, follow: bool) -> Result<()>;
+}
+
+#[cfg(unix)]
+impl<F: UnixFileSystem + Send + Sync> UnixBackend for F {
+    fn mode(&self, path: &Path) -> Result<u32> {
+        UnixFileSystem::mode(self, path)
+    }
+
+    fn set_mode(&self, path: &Path, mode: u32) -> Result<()> {
+        UnixFileSystem::set_mode(self, path, mode)
+    }
+
+    fn symlink(&self, src: &Path, dst: &Path) -> Result<()> {
+        UnixFileSystem::symlink(self, src, dst)
+    }
+
+    fn get_symlink_src(&self, dst: &Path) -> Result<PathBuf> {
+        UnixFileSystem::get_symlink_src(self, dst)
+    }
+
+    fn is_symlink(&self, path: &Path) -> bool {
+        UnixFileSystem::is_symlink(self, path)
+    }
+
+    fn symlink_exists(&self, path: &Path) -> bool {
+        UnixFileSystem::symlink_exists(self, path)
+    }
+
+    fn device(&self, path: &Path) -> Result<u64> {
+        UnixFileSystem::device(self, path)
+    }
+
+    fn access(&self, path: &Path, mode: u32) -> Result<()> {
+        UnixFileSystem::access(self, path, mode)
+    }
+
+    fn owner(&self, path: &Path) -> Result<(u32, u32)> {
+        UnixFileSystem::owner(self, path)
+    }
+
+    fn chown(&self, path: &Path, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+        UnixFileSystem::chown(self, path, uid, gid)
+    }
+
+    fn lchown(&self, path: &Path, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+        UnixFileSystem::lchown(self, path, uid, gid)
+    }
+
+    fn umask(&self) -> u32 {
+        UnixFileSystem::umask(self)
+    }
+
+    fn set_umask(&self, umask: u32) {
+        UnixFileSystem::set_umask(self, umask)
+    }
+
+    fn create_dir_with_mode(&self, path: &Path, mode: u32) -> Result<()> {
+        UnixFileSystem::create_dir_with_mode(self, path, mode)
+    }
+
+    fn create_file_with_mode(&self, path: &Path, buf: &[u8], mode: u32) -> Result<()> {
+        UnixFileSystem::create_file_with_mode(self, path, buf, mode)
+    }
+
+    fn mkfifo(&self, path: &Path, mode: u32) -> Result<()> {
+        UnixFileSystem::mkfifo(self, path, mode)
+    }
+
+    fn mknod(&self, path: &Path, file_type: FileType, mode: u32, dev: u64) -> Result<()> {
+        UnixFileSystem::mknod(self, path, file_type, mode, dev)
+    }
+
+    fn get_xattr(&self, path: &Path, name: &OsStr, follow: bool) -> Result<Vec<u8>> {
+        if follow {
+            UnixFileSystem::get_xattr(self, path, name)
+        } else {
+            UnixFileSystem::lget_xattr(self, path, name)
+        }
+    }
+
+    fn set_xattr(&self, path: &Path, name: &OsStr, value: &[u8], follow: bool) -> Result<()> {
+        if follow {
+            UnixFileSystem::set_xattr(self, path, name, value)
+        } else {
+            UnixFileSystem::lset_xattr(self, path, name, value)
+        }
+    }
+
+    fn list_xattrs(&self, path: &Path, follow: bool) -> Result<Vec<OsString>> {
+        if follow {
+            UnixFileSystem::list_xattrs(self, path)
+        } else {
+            UnixFileSystem::llist_xattrs(self, path)
+        }
+    }
+
+    fn remove_xattr(&self, path: &Path, name: &OsStr, follow: bool) -> Result<()> {
+        if follow {
+            UnixFileSystem::remove_xattr(self, path, name)
+        } else {
+            UnixFileSystem::lremove_xattr(self, path, name)
+        }
+    }
+}
@@ -20,30 +20,51 @@
 // SOFTWARE.
 
 use std::env;
+#[cfg(unix)]
+use std::ffi::CString;
+#[cfg(any(unix, feature = "temp"))]
+use std::ffi::OsStr;
 use std::ffi::OsString;
 use std::fs::{self, File, OpenOptions, Permissions};
-use std::io::{Read, Result, Write};
+use std::io::{self, Error, ErrorKind, Read, Result, Write};
+#[cfg(unix)]
+use std::mem;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
 #[cfg(unix)]
-use std::os::unix::fs::PermissionsExt;
+use std::os::unix::fs::{
+    DirBuilderExt, FileExt, FileTypeExt, MetadataExt, OpenOptionsExt, PermissionsExt,
+};
+#[cfg(windows)]
+use std::os::windows::fs::FileExt;
 use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
 
 #[cfg(feature = "temp")]
-use tempdir;
+use tempfile;
 
+use copy::CopyCounter;
 #[cfg(unix)]
 use UnixFileSystem;
-use {DirEntry, FileSystem, ReadDir};
+use {CopyConflict, CopyOptions, DirEntry, FileSystem, FileType, ReadDir, Space};
 #[cfg(feature = "temp")]
 use {TempDir, TempFileSystem};
 
+/// Matches the Linux limit on symlinks followed while resolving a path.
+const MAX_SYMLINK_DEPTH: usize = 40;
+
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
 /// Tracks a temporary directory that will be deleted once the struct goes out of scope.
 ///
 /// This is a wrapper around a [`TempDir`].
 ///
-/// [`TempDir`]: https://doc.rust-lang.org/tempdir/tempdir/struct.TempDir.html
+/// [`TempDir`]: https://docs.rs/tempfile/3/tempfile/struct.TempDir.html
 #[cfg(feature = "temp")]
 #[derive(Debug)]
-pub struct OsTempDir(tempdir::TempDir);
+pub struct OsTempDir(tempfile::TempDir);
 
 #[cfg(feature = "temp")]
 impl TempDir for OsTempDir {
@@ -86,6 +107,14 @@ impl FileSystem for OsFileSystem {
         path.as_ref().is_file()
     }
 
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        path.as_ref().exists()
+    }
+
+    fn try_exists<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        path.as_ref().try_exists()
+    }
+
     fn create_dir<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         fs::create_dir(path)
     }
@@ -124,6 +153,87 @@ impl FileSystem for OsFileSystem {
         file.write_all(buf.as_ref())
     }
 
+    fn append_file<P, B>(&self, path: P, buf: B) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        let mut file = OpenOptions::new().append(true).create(true).open(path)?;
+        file.write_all(buf.as_ref())
+    }
+
+    fn append_existing_file<P, B>(&self, path: P, buf: B) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        let mut file = OpenOptions::new().append(true).open(path)?;
+        file.write_all(buf.as_ref())
+    }
+
+    fn write_file_atomic<P, B>(&self, path: P, buf: B) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        let path = follow_symlinks(path.as_ref())?;
+        let permissions = match fs::metadata(&path) {
+            Ok(ref metadata) if metadata.is_dir() => {
+                return Err(Error::new(ErrorKind::IsADirectory, "is a directory"))
+            }
+            Ok(metadata) => Some(metadata.permissions()),
+            Err(ref err) if err.kind() == ErrorKind::NotFound => None,
+            Err(err) => return Err(err),
+        };
+
+        let (temp, mut file) = create_temp_sibling(&path)?;
+        let result = file
+            .write_all(buf.as_ref())
+            .and_then(|_| match permissions {
+                Some(permissions) => file.set_permissions(permissions),
+                None => Ok(()),
+            })
+            .and_then(|_| file.sync_all())
+            .and_then(|_| fs::rename(&temp, &path));
+
+        if result.is_err() {
+            let _ = fs::remove_file(&temp);
+            return result;
+        }
+
+        sync_parent(&path)
+    }
+
+    fn sync<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if fs::metadata(path)?.is_dir() {
+            return self.sync_dir(path);
+        }
+
+        File::open(path)?.sync_all()
+    }
+
+    #[cfg(unix)]
+    fn sync_dir<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let dir = File::open(path)?;
+        if !dir.metadata()?.is_dir() {
+            return Err(Error::new(ErrorKind::NotADirectory, "not a directory"));
+        }
+
+        dir.sync_all()
+    }
+
+    #[cfg(not(unix))]
+    fn sync_dir<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        // Directories cannot be opened as files here; their entries are
+        // flushed along with the files they refer to.
+        if !fs::metadata(path)?.is_dir() {
+            return Err(Error::new(ErrorKind::NotADirectory, "not a directory"));
+        }
+
+        Ok(())
+    }
+
     fn read_file<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>> {
         let mut contents = Vec::<u8>::new();
         let mut file = File::open(path)?;
@@ -142,6 +252,53 @@ impl FileSystem for OsFileSystem {
         file.read_to_end(buf.as_mut())
     }
 
+    fn read_file_range<P: AsRef<Path>>(&self, path: P, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let file = File::open(path)?;
+        let available = file.metadata()?.len().saturating_sub(offset);
+        let mut buf = vec![0; len.min(available) as usize];
+        let mut read = 0;
+
+        while read < buf.len() {
+            match read_at(&file, &mut buf[read..], offset + read as u64) {
+                Ok(0) => break,
+                Ok(n) => read += n,
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => (),
+                Err(err) => return Err(err),
+            }
+        }
+        buf.truncate(read);
+
+        Ok(buf)
+    }
+
+    fn write_file_at<P, B>(&self, path: P, offset: u64, buf: B) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        let file = OpenOptions::new().write(true).open(path)?;
+        let mut buf = buf.as_ref();
+        let mut offset = offset;
+
+        while !buf.is_empty() {
+            match write_at(&file, buf, offset) {
+                Ok(0) => return Err(Error::from(ErrorKind::WriteZero)),
+                Ok(n) => {
+                    buf = &buf[n..];
+                    offset += n as u64;
+                }
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => (),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn set_len<P: AsRef<Path>>(&self, path: P, size: u64) -> Result<()> {
+        OpenOptions::new().write(true).open(path)?.set_len(size)
+    }
+
     fn read_file_to_string<P: AsRef<Path>>(&self, path: P) -> Result<String> {
         let mut contents = String::new();
         let mut file = File::open(path)?;
@@ -173,6 +330,30 @@ impl FileSystem for OsFileSystem {
         fs::copy(from, to).and(Ok(()))
     }
 
+    fn copy_dir_all<P, Q>(&self, from: P, to: Q, options: &CopyOptions) -> Result<()>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let from = from.as_ref();
+        let to = to.as_ref();
+
+        if !fs::metadata(from)?.is_dir() {
+            return Err(Error::new(
+                ErrorKind::NotADirectory,
+                "source is not a directory",
+            ));
+        }
+        if canonical_destination(to)?.starts_with(fs::canonicalize(from)?) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "cannot copy a directory into itself",
+            ));
+        }
+
+        copy_dir(from, to, options, &mut CopyCounter::default())
+    }
+
     fn rename<P, Q>(&self, from: P, to: Q) -> Result<()>
     where
         P: AsRef<Path>,
@@ -196,6 +377,38 @@ impl FileSystem for OsFileSystem {
     fn len<P: AsRef<Path>>(&self, path: P) -> u64 {
         fs::metadata(path.as_ref()).map(|md| md.len()).unwrap_or(0)
     }
+
+    fn modified<P: AsRef<Path>>(&self, path: P) -> Result<SystemTime> {
+        fs::metadata(path)?.modified()
+    }
+
+    fn set_modified<P: AsRef<Path>>(&self, path: P, time: SystemTime) -> Result<()> {
+        File::open(path)?.set_modified(time)
+    }
+
+    #[cfg(unix)]
+    #[allow(clippy::unnecessary_cast)]
+    fn space<P: AsRef<Path>>(&self, path: P) -> Result<Space> {
+        let path = c_path(path.as_ref())?;
+        let mut stat = mem::MaybeUninit::<libc::statvfs>::uninit();
+        if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+            return Err(Error::last_os_error());
+        }
+        let stat = unsafe { stat.assume_init() };
+        let block = stat.f_frsize as u64;
+
+        Ok(Space {
+            total: stat.f_blocks as u64 * block,
+            free: stat.f_bfree as u64 * block,
+            available: stat.f_bavail as u64 * block,
+        })
+    }
+
+    #[cfg(not(unix))]
+    fn space<P: AsRef<Path>>(&self, path: P) -> Result<Space> {
+        fs::metadata(path)?;
+        Err(Error::new(ErrorKind::Unsupported, "space is not supported"))
+    }
 }
 
 impl DirEntry for fs::DirEntry {
@@ -206,10 +419,36 @@ impl DirEntry for fs::DirEntry {
     fn path(&self) -> PathBuf {
         self.path()
     }
+
+    fn file_type(&self) -> Result<FileType> {
+        self.file_type().map(file_type)
+    }
 }
 
 impl ReadDir<fs::DirEntry> for fs::ReadDir {}
 
+fn file_type(file_type: fs::FileType) -> FileType {
+    #[cfg(unix)]
+    {
+        if file_type.is_fifo() {
+            return FileType::Fifo;
+        } else if file_type.is_socket() {
+            return FileType::Socket;
+        } else if file_type.is_char_device() {
+            return FileType::CharDevice;
+        } else if file_type.is_block_device() {
+            return FileType::BlockDevice;
+        }
+    }
+    if file_type.is_dir() {
+        FileType::Dir
+    } else if file_type.is_symlink() {
+        FileType::Symlink
+    } else {
+        FileType::File
+    }
+}
+
 #[cfg(unix)]
 impl UnixFileSystem for OsFileSystem {
     fn mode<P: AsRef<Path>>(&self, path: P) -> Result<u32> {
@@ -230,14 +469,417 @@ impl UnixFileSystem for OsFileSystem {
     fn get_symlink_src<P: AsRef<Path>>(&self, dst: P) -> Result<PathBuf> {
         std::fs::read_link(dst)
     }
+
+    fn is_symlink<P: AsRef<Path>>(&self, path: P) -> bool {
+        path.as_ref().is_symlink()
+    }
+
+    fn symlink_exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        fs::symlink_metadata(path).is_ok()
+    }
+
+    fn device<P: AsRef<Path>>(&self, path: P) -> Result<u64> {
+        fs::metadata(path).map(|metadata| metadata.dev())
+    }
+
+    fn access<P: AsRef<Path>>(&self, path: P, mode: u32) -> Result<()> {
+        let path = c_path(path.as_ref())?;
+        if unsafe { libc::access(path.as_ptr(), mode as libc::c_int) } != 0 {
+            return Err(Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    fn owner<P: AsRef<Path>>(&self, path: P) -> Result<(u32, u32)> {
+        fs::metadata(path).map(|metadata| (metadata.uid(), metadata.gid()))
+    }
+
+    fn chown<P: AsRef<Path>>(&self, path: P, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+        std::os::unix::fs::chown(path, uid, gid)
+    }
+
+    fn lchown<P: AsRef<Path>>(&self, path: P, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+        std::os::unix::fs::lchown(path, uid, gid)
+    }
+
+    #[allow(clippy::unnecessary_cast)]
+    fn umask(&self) -> u32 {
+        if let Some(umask) = proc_umask() {
+            return umask;
+        }
+        // The mask can only be read by setting it, so restore it right away.
+        unsafe {
+            let umask = libc::umask(0o022);
+            libc::umask(umask);
+            umask as u32
+        }
+    }
+
+    #[allow(clippy::unnecessary_cast)]
+    fn set_umask(&self, umask: u32) {
+        unsafe {
+            libc::umask((umask & 0o777) as libc::mode_t);
+        }
+    }
+
+    fn create_dir_with_mode<P: AsRef<Path>>(&self, path: P, mode: u32) -> Result<()> {
+        fs::DirBuilder::new().mode(mode).create(path)
+    }
+
+    fn create_file_with_mode<P, B>(&self, path: P, buf: B, mode: u32) -> Result<()>
+    where
+        P: AsRef<Path>,
+        B: AsRef<[u8]>,
+    {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(mode)
+            .open(path)?;
+
+        file.write_all(buf.as_ref())
+    }
+
+    #[allow(clippy::unnecessary_cast)]
+    fn mkfifo<P: AsRef<Path>>(&self, path: P, mode: u32) -> Result<()> {
+        let path = c_path(path.as_ref())?;
+        if unsafe { libc::mkfifo(path.as_ptr(), mode as libc::mode_t) } != 0 {
+            return Err(Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    #[allow(clippy::unnecessary_cast)]
+    fn mknod<P: AsRef<Path>>(
+        &self,
+        path: P,
+        file_type: FileType,
+        mode: u32,
+        dev: u64,
+    ) -> Result<()> {
+        if let FileType::Dir | FileType::Symlink = file_type {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "mknod cannot create directories or symlinks",
+            ));
+        }
+        let path = c_path(path.as_ref())?;
+        let mode = file_type.mode_bits() | (mode & 0o7777);
+        if unsafe { libc::mknod(path.as_ptr(), mode as libc::mode_t, dev as libc::dev_t) } != 0 {
+            return Err(Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    fn get_xattr<P, N>(&self, path: P, name: N) -> Result<Vec<u8>>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>,
+    {
+        get_xattr(path.as_ref(), name.as_ref(), true)
+    }
+
+    fn lget_xattr<P, N>(&self, path: P, name: N) -> Result<Vec<u8>>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>,
+    {
+        get_xattr(path.as_ref(), name.as_ref(), false)
+    }
+
+    fn set_xattr<P, N, V>(&self, path: P, name: N, value: V) -> Result<()>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>,
+        V: AsRef<[u8]>,
+    {
+        let path = c_path(path.as_ref())?;
+        let name = c_name(name.as_ref())?;
+        xattr::set(&path, &name, value.as_ref(), true)
+    }
+
+    fn lset_xattr<P, N, V>(&self, path: P, name: N, value: V) -> Result<()>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>,
+        V: AsRef<[u8]>,
+    {
+        let path = c_path(path.as_ref())?;
+        let name = c_name(name.as_ref())?;
+        xattr::set(&path, &name, value.as_ref(), false)
+    }
+
+    fn list_xattrs<P: AsRef<Path>>(&self, path: P) -> Result<Vec<OsString>> {
+        list_xattrs(path.as_ref(), true)
+    }
+
+    fn llist_xattrs<P: AsRef<Path>>(&self, path: P) -> Result<Vec<OsString>> {
+        list_xattrs(path.as_ref(), false)
+    }
+
+    fn remove_xattr<P, N>(&self, path: P, name: N) -> Result<()>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>,
+    {
+        let path = c_path(path.as_ref())?;
+        let name = c_name(name.as_ref())?;
+        xattr::remove(&path, &name, true)
+    }
+
+    fn lremove_xattr<P, N>(&self, path: P, name: N) -> Result<()>
+    where
+        P: AsRef<Path>,
+        N: AsRef<OsStr>,
+    {
+        let path = c_path(path.as_ref())?;
+        let name = c_name(name.as_ref())?;
+        xattr::remove(&path, &name, false)
+    }
+}
+
+/// Reads the umask of the process from `/proc/self/status`, which Linux 4.7
+/// and later report without changing it.
+#[cfg(target_os = "linux")]
+fn proc_umask() -> Option<u32> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("Umask:"))
+        .and_then(|umask| u32::from_str_radix(umask.trim(), 8).ok())
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn proc_umask() -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+fn c_path(path: &Path) -> Result<CString> {
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, "path contains a nul byte"))
+}
+
+#[cfg(unix)]
+fn c_name(name: &OsStr) -> Result<CString> {
+    CString::new(name.as_bytes())
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, "name contains a nul byte"))
+}
+
+/// Asks for the size of the value first, and asks again if it grew before it
+/// could be read.
+#[cfg(unix)]
+fn get_xattr(path: &Path, name: &OsStr, follow: bool) -> Result<Vec<u8>> {
+    let path = c_path(path)?;
+    let name = c_name(name)?;
+    loop {
+        let len = xattr::get(&path, &name, &mut [], follow)?;
+        let mut value = vec![0; len];
+        match xattr::get(&path, &name, &mut value, follow) {
+            Ok(len) => {
+                value.truncate(len);
+                return Ok(value);
+            }
+            Err(ref err) if err.raw_os_error() == Some(libc::ERANGE) => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn list_xattrs(path: &Path, follow: bool) -> Result<Vec<OsString>> {
+    let path = c_path(path)?;
+    loop {
+        let len = xattr::list(&path, &mut [], follow)?;
+        let mut names = vec![0; len];
+        match xattr::list(&path, &mut names, follow) {
+            Ok(len) => {
+                return Ok(names[..len]
+                    .split(|&b| b == 0)
+                    .filter(|name| !name.is_empty())
+                    .map(|name| OsStr::from_bytes(name).to_os_string())
+                    .collect());
+            }
+            Err(ref err) if err.raw_os_error() == Some(libc::ERANGE) => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// The extended attribute syscalls, which take different arguments on each
+/// platform.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod xattr {
+    use std::ffi::CStr;
+    use std::io::{Error, Result};
+
+    use libc::{self, c_void};
+
+    pub fn get(path: &CStr, name: &CStr, value: &mut [u8], follow: bool) -> Result<usize> {
+        let (path, name) = (path.as_ptr(), name.as_ptr());
+        let (buf, size) = (value.as_mut_ptr() as *mut c_void, value.len());
+        let len = unsafe {
+            if follow {
+                libc::getxattr(path, name, buf, size)
+            } else {
+                libc::lgetxattr(path, name, buf, size)
+            }
+        };
+        if len < 0 {
+            return Err(Error::last_os_error());
+        }
+
+        Ok(len as usize)
+    }
+
+    pub fn set(path: &CStr, name: &CStr, value: &[u8], follow: bool) -> Result<()> {
+        let (path, name) = (path.as_ptr(), name.as_ptr());
+        let (buf, size) = (value.as_ptr() as *const c_void, value.len());
+        let ret = unsafe {
+            if follow {
+                libc::setxattr(path, name, buf, size, 0)
+            } else {
+                libc::lsetxattr(path, name, buf, size, 0)
+            }
+        };
+        if ret != 0 {
+            return Err(Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    pub fn list(path: &CStr, names: &mut [u8], follow: bool) -> Result<usize> {
+        let path = path.as_ptr();
+        let (buf, size) = (names.as_mut_ptr() as *mut libc::c_char, names.len());
+        let len = unsafe {
+            if follow {
+                libc::listxattr(path, buf, size)
+            } else {
+                libc::llistxattr(path, buf, size)
+            }
+        };
+        if len < 0 {
+            return Err(Error::last_os_error());
+        }
+
+        Ok(len as usize)
+    }
+
+    pub fn remove(path: &CStr, name: &CStr, follow: bool) -> Result<()> {
+        let (path, name) = (path.as_ptr(), name.as_ptr());
+        let ret = unsafe {
+            if follow {
+                libc::removexattr(path, name)
+            } else {
+                libc::lremovexattr(path, name)
+            }
+        };
+        if ret != 0 {
+            return Err(Error::last_os_error());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod xattr {
+    use std::ffi::CStr;
+    use std::io::{Error, Result};
+
+    use libc::{self, c_int, c_void};
+
+    fn options(follow: bool) -> c_int {
+        if follow {
+            0
+        } else {
+            libc::XATTR_NOFOLLOW
+        }
+    }
+
+    pub fn get(path: &CStr, name: &CStr, value: &mut [u8], follow: bool) -> Result<usize> {
+        let (buf, size) = (value.as_mut_ptr() as *mut c_void, value.len());
+        let len =
+            unsafe { libc::getxattr(path.as_ptr(), name.as_ptr(), buf, size, 0, options(follow)) };
+        if len < 0 {
+            return Err(Error::last_os_error());
+        }
+
+        Ok(len as usize)
+    }
+
+    pub fn set(path: &CStr, name: &CStr, value: &[u8], follow: bool) -> Result<()> {
+        let (buf, size) = (value.as_ptr() as *const c_void, value.len());
+        let ret =
+            unsafe { libc::setxattr(path.as_ptr(), name.as_ptr(), buf, size, 0, options(follow)) };
+        if ret != 0 {
+            return Err(Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    pub fn list(path: &CStr, names: &mut [u8], follow: bool) -> Result<usize> {
+        let (buf, size) = (names.as_mut_ptr() as *mut libc::c_char, names.len());
+        let len = unsafe { libc::listxattr(path.as_ptr(), buf, size, options(follow)) };
+        if len < 0 {
+            return Err(Error::last_os_error());
+        }
+
+        Ok(len as usize)
+    }
+
+    pub fn remove(path: &CStr, name: &CStr, follow: bool) -> Result<()> {
+        if unsafe { libc::removexattr(path.as_ptr(), name.as_ptr(), options(follow)) } != 0 {
+            return Err(Error::last_os_error());
+        }
+
+        Ok(())
+    }
+}
+
+/// Other platforms have no `xattr` syscalls, so every call fails as if the
+/// file system did not support extended attributes.
+#[cfg(all(
+    unix,
+    not(any(target_os = "linux", target_os = "android", target_os = "macos"))
+))]
+mod xattr {
+    use std::ffi::CStr;
+    use std::io::{Error, Result};
+
+    use libc;
+
+    pub fn get(_: &CStr, _: &CStr, _: &mut [u8], _: bool) -> Result<usize> {
+        Err(Error::from_raw_os_error(libc::ENOTSUP))
+    }
+
+    pub fn set(_: &CStr, _: &CStr, _: &[u8], _: bool) -> Result<()> {
+        Err(Error::from_raw_os_error(libc::ENOTSUP))
+    }
+
+    pub fn list(_: &CStr, _: &mut [u8], _: bool) -> Result<usize> {
+        Err(Error::from_raw_os_error(libc::ENOTSUP))
+    }
+
+    pub fn remove(_: &CStr, _: &CStr, _: bool) -> Result<()> {
+        Err(Error::from_raw_os_error(libc::ENOTSUP))
+    }
 }
 
 #[cfg(feature = "temp")]
 impl TempFileSystem for OsFileSystem {
     type TempDir = OsTempDir;
 
-    fn temp_dir<S: AsRef<str>>(&self, prefix: S) -> Result<Self::TempDir> {
-        tempdir::TempDir::new(prefix.as_ref()).map(OsTempDir)
+    fn temp_dir_os<S: AsRef<OsStr>>(&self, prefix: S) -> Result<Self::TempDir> {
+        tempfile::Builder::new()
+            .prefix(prefix.as_ref())
+            .tempdir()
+            .map(OsTempDir)
     }
 }
 
@@ -246,3 +888,185 @@ fn permissions(path: &Path) -> Result<Permissions> {
 
     Ok(metadata.permissions())
 }
+
+#[cfg(unix)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> Result<usize> {
+    file.read_at(buf, offset)
+}
+
+#[cfg(windows)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> Result<usize> {
+    file.seek_read(buf, offset)
+}
+
+#[cfg(unix)]
+fn write_at(file: &File, buf: &[u8], offset: u64) -> Result<usize> {
+    file.write_at(buf, offset)
+}
+
+#[cfg(windows)]
+fn write_at(file: &File, buf: &[u8], offset: u64) -> Result<usize> {
+    file.seek_write(buf, offset)
+}
+
+/// Follows `path` through any symlinks. Unlike `fs::canonicalize`, the final
+/// target does not need to exist.
+fn follow_symlinks(path: &Path) -> Result<PathBuf> {
+    let mut path = path.to_path_buf();
+
+    for _ in 0..MAX_SYMLINK_DEPTH {
+        match fs::symlink_metadata(&path) {
+            Ok(ref metadata) if metadata.file_type().is_symlink() => {
+                let src = fs::read_link(&path)?;
+                path = match path.parent() {
+                    Some(parent) => parent.join(src),
+                    None => src,
+                };
+            }
+            Err(ref err) if err.kind() == ErrorKind::NotFound => return Ok(path),
+            Err(err) => return Err(err),
+            Ok(_) => return Ok(path),
+        }
+    }
+
+    Err(Error::new(
+        ErrorKind::FilesystemLoop,
+        "too many levels of symbolic links",
+    ))
+}
+
+/// Creates a new, uniquely named file next to `path`.
+fn create_temp_sibling(path: &Path) -> Result<(PathBuf, File)> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "path has no file name"))?;
+
+    loop {
+        let mut temp_name = OsString::from(".");
+        temp_name.push(name);
+        temp_name.push(format!(
+            ".{}.{}.tmp",
+            process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let temp = path.with_file_name(temp_name);
+
+        match OpenOptions::new().write(true).create_new(true).open(&temp) {
+            Ok(file) => return Ok((temp, file)),
+            Err(ref err) if err.kind() == ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Flushes the directory entry of `path` to disk.
+fn sync_parent(path: &Path) -> Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => OsFileSystem::new().sync_dir(parent),
+        _ => OsFileSystem::new().sync_dir("."),
+    }
+}
+
+/// Returns the canonical form of `to`, which may not exist yet.
+fn canonical_destination(to: &Path) -> Result<PathBuf> {
+    match fs::canonicalize(to) {
+        Err(ref e) if e.kind() == ErrorKind::NotFound => {}
+        result => return result,
+    }
+    match (to.parent(), to.file_name()) {
+        (Some(parent), Some(name)) if parent != Path::new("") => {
+            fs::canonicalize(parent).map(|parent| parent.join(name))
+        }
+        (_, Some(name)) => env::current_dir().map(|cwd| cwd.join(name)),
+        _ => Err(Error::new(ErrorKind::InvalidInput, "invalid destination")),
+    }
+}
+
+fn copy_dir(
+    from: &Path,
+    to: &Path,
+    options: &CopyOptions,
+    counter: &mut CopyCounter,
+) -> Result<()> {
+    if !to.is_dir() {
+        if !replace_existing(to, options)? {
+            return Ok(());
+        }
+        fs::create_dir(to)?;
+    }
+
+    let mut entries = fs::read_dir(from)?
+        .map(|entry| entry.map(|e| e.file_name()))
+        .collect::<Result<Vec<_>>>()?;
+    entries.sort();
+
+    for name in entries {
+        let from = from.join(&name);
+        let to = to.join(&name);
+
+        if fs::symlink_metadata(&from)?.file_type().is_symlink() && !options.follow_symlinks {
+            if replace_existing(&to, options)? {
+                copy_symlink(&from, &to)?;
+                counter.record(options, &from, &to, 0);
+            }
+        } else if from.is_dir() {
+            copy_dir(&from, &to, options, counter)?;
+        } else if replace_existing(&to, options)? {
+            let mut reader = File::open(&from)?;
+            let mut writer = OpenOptions::new().write(true).create_new(true).open(&to)?;
+            let bytes = io::copy(&mut reader, &mut writer)?;
+
+            copy_attributes(&from, &to, options)?;
+            counter.record(options, &from, &to, bytes);
+        }
+    }
+
+    copy_attributes(from, to, options)?;
+    counter.record(options, from, to, 0);
+
+    Ok(())
+}
+
+/// Clears the way for a non-directory node at `to`. Returns `false` if the
+/// node should be skipped instead.
+fn replace_existing(to: &Path, options: &CopyOptions) -> Result<bool> {
+    match fs::symlink_metadata(to) {
+        Err(ref e) if e.kind() == ErrorKind::NotFound => Ok(true),
+        Err(e) => Err(e),
+        Ok(ref metadata) if metadata.is_dir() => Err(Error::new(
+            ErrorKind::IsADirectory,
+            "destination is a directory",
+        )),
+        Ok(_) => match options.on_conflict {
+            CopyConflict::Skip => Ok(false),
+            CopyConflict::Error => Err(Error::new(
+                ErrorKind::AlreadyExists,
+                "destination already exists",
+            )),
+            CopyConflict::Overwrite => fs::remove_file(to).and(Ok(true)),
+        },
+    }
+}
+
+fn copy_attributes(from: &Path, to: &Path, options: &CopyOptions) -> Result<()> {
+    let metadata = fs::metadata(from)?;
+
+    if options.preserve_timestamps {
+        File::open(to)?.set_modified(metadata.modified()?)?;
+    }
+    if options.preserve_mode {
+        fs::set_permissions(to, metadata.permissions())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(from)?, to)
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(from: &Path, to: &Path) -> Result<()> {
+    fs::copy(from, to).and(Ok(()))
+}
@@ -0,0 +1,441 @@
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+#[cfg(unix)]
+use backend::Unix;
+use backend::{Backend, Kind, Plain};
+use mount::normalize;
+#[cfg(unix)]
+use UnixFileSystem;
+use {DirEntry, FileSystem};
+
+/// How [`sync`] decides whether a file needs to be copied again.
+///
+/// [`sync`]: fn.sync.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SyncCompare {
+    /// Files are unchanged if they have the same length and modification time.
+    #[default]
+    SizeAndModified,
+    /// Files are unchanged if they have the same length and contents. Files
+    /// are compared a chunk at a time, so neither is held in memory as a whole.
+    Content,
+}
+
+/// Options for [`sync`] and [`sync_unix`].
+///
+/// By default, files are compared by size and modification time, extraneous
+/// destination entries are kept, modes are preserved and symlinks are recreated
+/// rather than followed.
+///
+/// [`sync`]: fn.sync.html
+/// [`sync_unix`]: fn.sync_unix.html
+#[derive(Clone, Debug)]
+pub struct SyncOptions {
+    compare: SyncCompare,
+    delete: bool,
+    preserve_mode: bool,
+    follow_symlinks: bool,
+    dry_run: bool,
+}
+
+impl SyncOptions {
+    pub fn new() -> Self {
+        SyncOptions {
+            compare: SyncCompare::default(),
+            delete: false,
+            preserve_mode: true,
+            follow_symlinks: false,
+            dry_run: false,
+        }
+    }
+
+    /// Sets how files are compared.
+    pub fn compare(mut self, compare: SyncCompare) -> Self {
+        self.compare = compare;
+        self
+    }
+
+    /// Sets whether destination entries missing from the source are removed.
+    pub fn delete(mut self, delete: bool) -> Self {
+        self.delete = delete;
+        self
+    }
+
+    /// Sets whether destination nodes get the mode bits of the source.
+    /// Only used by [`sync_unix`].
+    ///
+    /// [`sync_unix`]: fn.sync_unix.html
+    pub fn preserve_mode(mut self, preserve_mode: bool) -> Self {
+        self.preserve_mode = preserve_mode;
+        self
+    }
+
+    /// Sets whether source symlinks are synced as the node they point to.
+    /// [`sync`] always follows them.
+    ///
+    /// [`sync`]: fn.sync.html
+    pub fn follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Sets whether to only report the actions that would be taken.
+    ///
+    /// As the default mode of new nodes is not known in advance, a dry run
+    /// reports a `SyncAction::SetMode` for every node it would create.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A change made to the destination, identified by its destination path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SyncAction {
+    CreateDir(PathBuf),
+    CopyFile(PathBuf),
+    CreateSymlink(PathBuf),
+    SetMode(PathBuf),
+    Remove(PathBuf),
+}
+
+/// The actions taken by [`sync`], in the order they were taken.
+///
+/// [`sync`]: fn.sync.html
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    pub actions: Vec<SyncAction>,
+    /// Source symlinks that were not synced because they were followed and
+    /// their target does not exist.
+    pub skipped: Vec<PathBuf>,
+}
+
+impl SyncReport {
+    /// Returns `true` if the destination was already up to date.
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+}
+
+/// Makes `dst_path` in `dst_fs` a copy of `src_path` in `src_fs`, rsync-style:
+/// only changed files are copied, and synced files get the modification time
+/// of their source so that later runs can skip them.
+///
+/// `src_fs` and `dst_fs` may be different implementations, e.g. to seed a
+/// `FakeFileSystem` from the disk. Symlinks in the source are followed and modes
+/// are not synced; use [`sync_unix`] to preserve them.
+///
+/// # Errors
+///
+/// * `src_path` does not exist.
+/// * `src_fs` and `dst_fs` are the same value and `dst_path` is `src_path` or
+///   inside it (`ErrorKind::InvalidInput`).
+/// * The parent of `dst_path` does not exist.
+/// * Current user has insufficient permissions.
+///
+/// [`sync_unix`]: fn.sync_unix.html
+pub fn sync<S, D, P, Q>(
+    src_fs: &S,
+    src_path: P,
+    dst_fs: &D,
+    dst_path: Q,
+    options: &SyncOptions,
+) -> Result<SyncReport>
+where
+    S: FileSystem,
+    D: FileSystem,
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let (src_path, dst_path) = (src_path.as_ref(), dst_path.as_ref());
+    check_not_into_itself(src_fs, src_path, dst_fs, dst_path)?;
+
+    let mut syncer = Syncer {
+        src: Plain(src_fs),
+        dst: Plain(dst_fs),
+        options,
+        report: SyncReport::default(),
+    };
+
+    syncer.sync_node(src_path, dst_path)?;
+
+    Ok(syncer.report)
+}
+
+/// Like [`sync`], but also syncs symlinks and mode bits.
+///
+/// [`sync`]: fn.sync.html
+#[cfg(unix)]
+pub fn sync_unix<S, D, P, Q>(
+    src_fs: &S,
+    src_path: P,
+    dst_fs: &D,
+    dst_path: Q,
+    options: &SyncOptions,
+) -> Result<SyncReport>
+where
+    S: FileSystem + UnixFileSystem,
+    D: FileSystem + UnixFileSystem,
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let (src_path, dst_path) = (src_path.as_ref(), dst_path.as_ref());
+    check_not_into_itself(src_fs, src_path, dst_fs, dst_path)?;
+
+    let mut syncer = Syncer {
+        src: Unix(src_fs),
+        dst: Unix(dst_fs),
+        options,
+        report: SyncReport::default(),
+    };
+
+    syncer.sync_node(src_path, dst_path)?;
+
+    Ok(syncer.report)
+}
+
+/// Fails if `src_fs` and `dst_fs` are the same value and `to` is `from` or
+/// inside it, which would sync the destination into itself.
+fn check_not_into_itself<S, D>(src_fs: &S, from: &Path, dst_fs: &D, to: &Path) -> Result<()>
+where
+    S: FileSystem,
+    D: FileSystem,
+{
+    let same = src_fs as *const S as *const () == dst_fs as *const D as *const ();
+    if same && absolute(dst_fs, to)?.starts_with(absolute(src_fs, from)?) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "cannot sync a directory into itself",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns `path` relative to the current directory of `fs`, without `.` and
+/// `..` components.
+fn absolute<F: FileSystem>(fs: &F, path: &Path) -> Result<PathBuf> {
+    if path.is_relative() {
+        fs.current_dir().map(|cwd| normalize(&cwd.join(path)))
+    } else {
+        Ok(normalize(path))
+    }
+}
+
+struct Syncer<'a, S, D> {
+    src: S,
+    dst: D,
+    options: &'a SyncOptions,
+    report: SyncReport,
+}
+
+impl<'a, S: Backend, D: Backend> Syncer<'a, S, D> {
+    fn sync_node(&mut self, from: &Path, to: &Path) -> Result<()> {
+        let dst_kind = self.dst.kind(to, false)?;
+
+        match self.src.kind(from, self.options.follow_symlinks)? {
+            Kind::Missing => Err(Error::new(ErrorKind::NotFound, "source does not exist")),
+            src_kind => self.sync_kind(from, to, src_kind, dst_kind),
+        }
+    }
+
+    /// Syncs a source node of `src_kind`. Below the root, a missing source is
+    /// a dangling symlink that was followed, and is skipped.
+    fn sync_kind(&mut self, from: &Path, to: &Path, src_kind: Kind, dst_kind: Kind) -> Result<()> {
+        match src_kind {
+            Kind::Missing => {
+                self.report.skipped.push(from.to_path_buf());
+                Ok(())
+            }
+            Kind::Dir => self.sync_dir(from, to, dst_kind),
+            Kind::File => self.sync_file(from, to, dst_kind),
+            Kind::Symlink(src) => {
+                if dst_kind == Kind::Symlink(src.clone()) {
+                    return Ok(());
+                }
+                self.remove_existing(to, &dst_kind)?;
+                self.act(SyncAction::CreateSymlink(to.to_path_buf()), |dst| {
+                    dst.symlink(&src, to)
+                })
+            }
+        }
+    }
+
+    fn sync_dir(&mut self, from: &Path, to: &Path, dst_kind: Kind) -> Result<()> {
+        if dst_kind != Kind::Dir {
+            self.remove_existing(to, &dst_kind)?;
+            self.act(SyncAction::CreateDir(to.to_path_buf()), |dst| {
+                dst.fs().create_dir(to)
+            })?;
+        }
+
+        let src_names = list_dir(self.src.fs(), from)?;
+        for name in &src_names {
+            let (from, to) = (from.join(name), to.join(name));
+            let src_kind = self.src.kind(&from, self.options.follow_symlinks)?;
+            // A destination directory that is missing or replaced has no
+            // children, even if a dry run left it in place.
+            let child_kind = if dst_kind == Kind::Dir {
+                self.dst.kind(&to, false)?
+            } else {
+                Kind::Missing
+            };
+            self.sync_kind(&from, &to, src_kind, child_kind)?;
+        }
+
+        if self.options.delete && dst_kind == Kind::Dir {
+            for name in list_dir(self.dst.fs(), to)? {
+                if !src_names.contains(&name) {
+                    let path = to.join(&name);
+                    let kind = self.dst.kind(&path, false)?;
+                    self.remove_existing(&path, &kind)?;
+                }
+            }
+        }
+
+        self.sync_mode(from, to, dst_kind != Kind::Dir)
+    }
+
+    fn sync_file(&mut self, from: &Path, to: &Path, dst_kind: Kind) -> Result<()> {
+        let unchanged = dst_kind == Kind::File
+            && match self.options.compare {
+                SyncCompare::SizeAndModified => {
+                    self.src.fs().len(from) == self.dst.fs().len(to)
+                        && self.src.fs().modified(from)? == self.dst.fs().modified(to)?
+                }
+                SyncCompare::Content => {
+                    self.src.fs().len(from) == self.dst.fs().len(to)
+                        && same_contents(self.src.fs(), from, self.dst.fs(), to)?
+                }
+            };
+
+        if !unchanged {
+            let contents = self.src.fs().read_file(from)?;
+            let modified = self.src.fs().modified(from)?;
+
+            if dst_kind != Kind::File {
+                self.remove_existing(to, &dst_kind)?;
+            }
+            self.act(SyncAction::CopyFile(to.to_path_buf()), |dst| {
+                if dst_kind == Kind::File {
+                    dst.fs().remove_file(to)?;
+                }
+                dst.fs().create_file(to, &contents)?;
+                dst.fs().set_modified(to, modified)
+            })?;
+        }
+
+        self.sync_mode(from, to, dst_kind != Kind::File)
+    }
+
+    /// `created` tells whether the node at `to` is new, which a dry run cannot
+    /// ask the destination about.
+    fn sync_mode(&mut self, from: &Path, to: &Path, created: bool) -> Result<()> {
+        if !self.options.preserve_mode {
+            return Ok(());
+        }
+        let mode = match self.src.mode(from)? {
+            Some(mode) => mode,
+            None => return Ok(()),
+        };
+        let current = if self.options.dry_run && created {
+            None
+        } else {
+            self.dst.mode(to)?
+        };
+
+        if current != Some(mode) {
+            self.act(SyncAction::SetMode(to.to_path_buf()), |dst| {
+                dst.set_mode(to, mode)
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn remove_existing(&mut self, path: &Path, kind: &Kind) -> Result<()> {
+        match *kind {
+            Kind::Missing => Ok(()),
+            Kind::Dir => self.act(SyncAction::Remove(path.to_path_buf()), |dst| {
+                dst.fs().remove_dir_all(path)
+            }),
+            _ => self.act(SyncAction::Remove(path.to_path_buf()), |dst| {
+                dst.fs().remove_file(path)
+            }),
+        }
+    }
+
+    /// Records `action`, and performs it unless this is a dry run.
+    fn act<F>(&mut self, action: SyncAction, f: F) -> Result<()>
+    where
+        F: FnOnce(&D) -> Result<()>,
+    {
+        if !self.options.dry_run {
+            f(&self.dst)?;
+        }
+        self.report.actions.push(action);
+
+        Ok(())
+    }
+}
+
+/// Length of the chunks `same_contents` reads at a time.
+const COMPARE_CHUNK_LEN: u64 = 64 * 1024;
+
+/// Compares two files chunk by chunk, stopping at the first difference.
+fn same_contents<S, D>(src_fs: &S, from: &Path, dst_fs: &D, to: &Path) -> Result<bool>
+where
+    S: FileSystem,
+    D: FileSystem,
+{
+    let mut offset = 0;
+    loop {
+        let chunk = src_fs.read_file_range(from, offset, COMPARE_CHUNK_LEN)?;
+        if chunk != dst_fs.read_file_range(to, offset, COMPARE_CHUNK_LEN)? {
+            return Ok(false);
+        }
+        if chunk.is_empty() {
+            return Ok(true);
+        }
+        offset += chunk.len() as u64;
+    }
+}
+
+fn list_dir<F: FileSystem>(fs: &F, path: &Path) -> Result<BTreeSet<OsString>> {
+    let mut names = BTreeSet::new();
+    for entry in fs.read_dir(path)? {
+        names.insert(entry?.file_name());
+    }
+
+    Ok(names)
+}
@@ -0,0 +1,180 @@
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Tests for case-insensitive `FakeFileSystem`s.
+extern crate filesystem;
+
+use std::ffi::OsString;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+#[cfg(unix)]
+use filesystem::UnixFileSystem;
+use filesystem::{CaseFolding, CaseSensitivity, DirEntry, FakeFileSystem, FileSystem};
+
+fn insensitive(folding: CaseFolding) -> FakeFileSystem {
+    let fs = FakeFileSystem::new();
+
+    fs.set_case_sensitivity(CaseSensitivity::Insensitive(folding));
+
+    fs
+}
+
+fn names(fs: &FakeFileSystem, path: &str) -> Vec<OsString> {
+    let mut names: Vec<_> = fs
+        .read_dir(path)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn names_are_case_sensitive_by_default() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_file("/README", "upper").unwrap();
+    fs.create_file("/readme", "lower").unwrap();
+
+    assert_eq!(fs.read_file("/README").unwrap(), b"upper");
+    assert_eq!(fs.read_file("/readme").unwrap(), b"lower");
+}
+
+#[test]
+fn create_file_fails_if_name_differs_only_in_case() {
+    let fs = insensitive(CaseFolding::Ascii);
+
+    fs.create_file("/README", "contents").unwrap();
+
+    let result = fs.create_file("/readme", "");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::AlreadyExists);
+
+    let result = fs.create_dir("/ReadMe");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::AlreadyExists);
+    assert_eq!(names(&fs, "/"), vec![OsString::from("README")]);
+}
+
+#[test]
+fn lookups_ignore_case_and_keep_original_spelling() {
+    let fs = insensitive(CaseFolding::Ascii);
+
+    fs.create_dir("/Dir").unwrap();
+    fs.write_file("/DIR/File.txt", "contents").unwrap();
+    fs.write_file("/dir/FILE.TXT", "new").unwrap();
+
+    assert!(fs.is_dir("/dir"));
+    assert!(fs.is_file("/dIr/file.txt"));
+    assert_eq!(fs.read_file("/dir/file.txt").unwrap(), b"new");
+    assert_eq!(names(&fs, "/DIR"), vec![OsString::from("File.txt")]);
+
+    let paths: Vec<_> = fs
+        .read_dir("/dir")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+
+    assert_eq!(paths, vec![PathBuf::from("/dir/File.txt")]);
+
+    fs.remove_file("/DIR/FILE.txt").unwrap();
+
+    assert!(names(&fs, "/Dir").is_empty());
+}
+
+#[test]
+fn ascii_folding_leaves_other_letters_alone() {
+    let fs = insensitive(CaseFolding::Ascii);
+
+    fs.create_file("/Äpfel", "").unwrap();
+    fs.create_file("/äpfel", "").unwrap();
+
+    assert_eq!(names(&fs, "/").len(), 2);
+}
+
+#[test]
+fn unicode_folding_matches_non_ascii_letters() {
+    let fs = insensitive(CaseFolding::Unicode);
+
+    fs.create_file("/Äpfel", "contents").unwrap();
+
+    let result = fs.create_file("/äPFEL", "");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::AlreadyExists);
+    assert_eq!(fs.read_file("/ÄPFEL").unwrap(), b"contents");
+}
+
+#[test]
+fn custom_folding_is_used_to_compare_names() {
+    let fs = insensitive(CaseFolding::custom(|name| {
+        name.to_lowercase().replace('ß', "ss")
+    }));
+
+    fs.create_file("/Straße", "contents").unwrap();
+
+    assert_eq!(fs.read_file("/STRASSE").unwrap(), b"contents");
+}
+
+#[test]
+fn rename_can_change_case_only() {
+    let fs = insensitive(CaseFolding::Ascii);
+
+    fs.create_file("/readme", "contents").unwrap();
+    fs.create_dir("/dir").unwrap();
+    fs.create_file("/dir/file", "").unwrap();
+    fs.rename("/readme", "/README").unwrap();
+    fs.rename("/dir", "/Dir").unwrap();
+
+    assert_eq!(
+        names(&fs, "/"),
+        vec![OsString::from("Dir"), OsString::from("README")]
+    );
+    assert_eq!(names(&fs, "/dir"), vec![OsString::from("file")]);
+    assert_eq!(fs.read_file("/readme").unwrap(), b"contents");
+}
+
+#[test]
+fn rename_replaces_file_with_differently_cased_name() {
+    let fs = insensitive(CaseFolding::Ascii);
+
+    fs.create_file("/a", "a").unwrap();
+    fs.create_file("/B", "b").unwrap();
+    fs.rename("/a", "/b").unwrap();
+
+    assert_eq!(names(&fs, "/"), vec![OsString::from("b")]);
+    assert_eq!(fs.read_file("/B").unwrap(), b"a");
+}
+
+#[cfg(unix)]
+#[test]
+fn unix_operations_ignore_case() {
+    let fs = insensitive(CaseFolding::Ascii);
+
+    fs.create_file("/File", "").unwrap();
+    fs.set_mode("/FILE", 0o600).unwrap();
+    fs.symlink("/file", "/Link").unwrap();
+
+    assert_eq!(fs.mode("/file").unwrap(), 0o100_600);
+    assert!(fs.is_symlink("/LINK"));
+    assert_eq!(fs.get_symlink_src("/link").unwrap(), PathBuf::from("/file"));
+    assert!(fs.is_file("/link"));
+}
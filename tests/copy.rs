@@ -0,0 +1,400 @@
+#![cfg(unix)]
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Tests for copying whole directory trees, both within a single file system and
+//! between two different implementations.
+extern crate filesystem;
+
+#[macro_use]
+mod utils;
+
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+use filesystem::{
+    copy_dir_all_between, copy_dir_all_between_unix, CopyConflict, CopyOptions, FakeFileSystem,
+    FileSystem, OsFileSystem, TempDir, TempFileSystem, UnixFileSystem,
+};
+
+macro_rules! test_fs {
+    ($name:ident, $fs:expr) => {
+        mod $name {
+            use super::*;
+
+            make_test!(copy_dir_all_copies_all_descendants, $fs);
+            make_test!(copy_dir_all_merges_into_existing_dir, $fs);
+            make_test!(copy_dir_all_fails_on_conflict_by_default, $fs);
+            make_test!(copy_dir_all_skips_conflicts, $fs);
+            make_test!(copy_dir_all_overwrites_conflicts, $fs);
+            make_test!(copy_dir_all_preserves_mode, $fs);
+            make_test!(copy_dir_all_preserves_timestamps, $fs);
+            make_test!(copy_dir_all_copies_symlinks_as_symlinks, $fs);
+            make_test!(copy_dir_all_follows_symlinks, $fs);
+            make_test!(copy_dir_all_reports_progress, $fs);
+            make_test!(copy_dir_all_fails_if_source_is_not_a_directory, $fs);
+            make_test!(copy_dir_all_fails_if_destination_is_inside_source, $fs);
+
+            make_test!(copy_dir_all_between_copies_to_fake, $fs);
+            make_test!(copy_dir_all_between_copies_from_fake, $fs);
+            make_test!(copy_dir_all_between_follows_symlinks_and_skips_modes, $fs);
+            make_test!(copy_dir_all_between_fails_if_options_need_unix, $fs);
+        }
+    };
+}
+
+test_fs!(os, OsFileSystem::new);
+test_fs!(fake, FakeFileSystem::new);
+
+fn make_tree<T: FileSystem + UnixFileSystem>(fs: &T, root: &Path) {
+    fs.create_dir(root).unwrap();
+    fs.create_dir(root.join("dir")).unwrap();
+    fs.create_file(root.join("file"), "file").unwrap();
+    fs.create_file(root.join("dir").join("nested"), "nested")
+        .unwrap();
+    fs.set_mode(root, 0o755).unwrap();
+    fs.set_mode(root.join("dir"), 0o755).unwrap();
+}
+
+fn copy_dir_all_copies_all_descendants<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    make_tree(fs, &from);
+
+    fs.copy_dir_all(&from, &to, &CopyOptions::new()).unwrap();
+
+    assert!(fs.is_dir(&to));
+    assert!(fs.is_dir(to.join("dir")));
+    assert_eq!(fs.read_file(to.join("file")).unwrap(), b"file");
+    assert_eq!(
+        fs.read_file(to.join("dir").join("nested")).unwrap(),
+        b"nested"
+    );
+    assert_eq!(fs.read_file(from.join("file")).unwrap(), b"file");
+}
+
+fn copy_dir_all_merges_into_existing_dir<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    make_tree(fs, &from);
+    fs.create_dir(&to).unwrap();
+    fs.create_file(to.join("other"), "other").unwrap();
+
+    fs.copy_dir_all(&from, &to, &CopyOptions::new()).unwrap();
+
+    assert_eq!(fs.read_file(to.join("file")).unwrap(), b"file");
+    assert_eq!(fs.read_file(to.join("other")).unwrap(), b"other");
+}
+
+fn copy_dir_all_fails_on_conflict_by_default<T: FileSystem + UnixFileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    make_tree(fs, &from);
+    fs.create_dir(&to).unwrap();
+    fs.create_file(to.join("file"), "old").unwrap();
+
+    let result = fs.copy_dir_all(&from, &to, &CopyOptions::new());
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::AlreadyExists);
+    assert_eq!(fs.read_file(to.join("file")).unwrap(), b"old");
+}
+
+fn copy_dir_all_skips_conflicts<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    make_tree(fs, &from);
+    fs.create_dir(&to).unwrap();
+    fs.create_file(to.join("file"), "old").unwrap();
+
+    let options = CopyOptions::new().on_conflict(CopyConflict::Skip);
+    fs.copy_dir_all(&from, &to, &options).unwrap();
+
+    assert_eq!(fs.read_file(to.join("file")).unwrap(), b"old");
+    assert_eq!(
+        fs.read_file(to.join("dir").join("nested")).unwrap(),
+        b"nested"
+    );
+}
+
+fn copy_dir_all_overwrites_conflicts<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    make_tree(fs, &from);
+    fs.create_dir(&to).unwrap();
+    fs.create_file(to.join("file"), "old").unwrap();
+    fs.set_readonly(to.join("file"), true).unwrap();
+
+    let options = CopyOptions::new().on_conflict(CopyConflict::Overwrite);
+    fs.copy_dir_all(&from, &to, &options).unwrap();
+
+    assert_eq!(fs.read_file(to.join("file")).unwrap(), b"file");
+}
+
+fn copy_dir_all_preserves_mode<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let preserved = parent.join("preserved");
+    let not_preserved = parent.join("not_preserved");
+    make_tree(fs, &from);
+    fs.set_mode(from.join("file"), 0o600).unwrap();
+    fs.set_mode(from.join("dir"), 0o700).unwrap();
+
+    fs.copy_dir_all(&from, &preserved, &CopyOptions::new())
+        .unwrap();
+    fs.copy_dir_all(
+        &from,
+        &not_preserved,
+        &CopyOptions::new().preserve_mode(false),
+    )
+    .unwrap();
+
+    assert_eq!(fs.mode(preserved.join("file")).unwrap() & 0o7777, 0o600);
+    assert_eq!(fs.mode(preserved.join("dir")).unwrap() & 0o7777, 0o700);
+    assert_ne!(fs.mode(not_preserved.join("file")).unwrap() & 0o7777, 0o600);
+}
+
+fn copy_dir_all_preserves_timestamps<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    let file_time = UNIX_EPOCH + Duration::from_secs(1_000_000);
+    let dir_time = UNIX_EPOCH + Duration::from_secs(2_000_000);
+    make_tree(fs, &from);
+    fs.set_modified(from.join("file"), file_time).unwrap();
+    fs.set_modified(from.join("dir"), dir_time).unwrap();
+
+    let options = CopyOptions::new().preserve_timestamps(true);
+    fs.copy_dir_all(&from, &to, &options).unwrap();
+
+    assert_eq!(fs.modified(to.join("file")).unwrap(), file_time);
+    assert_eq!(fs.modified(to.join("dir")).unwrap(), dir_time);
+}
+
+fn copy_dir_all_copies_symlinks_as_symlinks<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    make_tree(fs, &from);
+    fs.symlink("file", from.join("link")).unwrap();
+    fs.symlink("does_not_exist", from.join("broken_link"))
+        .unwrap();
+
+    fs.copy_dir_all(&from, &to, &CopyOptions::new()).unwrap();
+
+    assert!(fs.is_symlink(to.join("link")));
+    assert_eq!(
+        fs.get_symlink_src(to.join("link")).unwrap(),
+        Path::new("file")
+    );
+    assert!(fs.is_symlink(to.join("broken_link")));
+}
+
+fn copy_dir_all_follows_symlinks<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    make_tree(fs, &from);
+    fs.symlink("file", from.join("link")).unwrap();
+    fs.symlink("dir", from.join("dir_link")).unwrap();
+
+    let options = CopyOptions::new().follow_symlinks(true);
+    fs.copy_dir_all(&from, &to, &options).unwrap();
+
+    assert!(!fs.is_symlink(to.join("link")));
+    assert_eq!(fs.read_file(to.join("link")).unwrap(), b"file");
+    assert!(!fs.is_symlink(to.join("dir_link")));
+    assert_eq!(
+        fs.read_file(to.join("dir_link").join("nested")).unwrap(),
+        b"nested"
+    );
+}
+
+fn copy_dir_all_reports_progress<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    let copied = Arc::new(Mutex::new(Vec::new()));
+    make_tree(fs, &from);
+
+    let progress = copied.clone();
+    let options = CopyOptions::new().progress(move |p| {
+        progress
+            .lock()
+            .unwrap()
+            .push((p.to.to_path_buf(), p.copied_entries, p.copied_bytes))
+    });
+    fs.copy_dir_all(&from, &to, &options).unwrap();
+
+    let copied = copied.lock().unwrap();
+    let paths: Vec<PathBuf> = copied.iter().map(|(p, _, _)| p.clone()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            to.join("dir").join("nested"),
+            to.join("dir"),
+            to.join("file"),
+            to.clone()
+        ]
+    );
+    assert_eq!(copied.last().unwrap().1, 4);
+    assert_eq!(copied.last().unwrap().2, 10);
+}
+
+fn copy_dir_all_fails_if_source_is_not_a_directory<T: FileSystem + UnixFileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let from = parent.join("file");
+    fs.create_file(&from, "").unwrap();
+
+    let result = fs.copy_dir_all(&from, parent.join("to"), &CopyOptions::new());
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotADirectory);
+
+    let result = fs.copy_dir_all(
+        parent.join("does_not_exist"),
+        parent.join("to"),
+        &CopyOptions::new(),
+    );
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+fn copy_dir_all_fails_if_destination_is_inside_source<T: FileSystem + UnixFileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let from = parent.join("from");
+    make_tree(fs, &from);
+
+    let result = fs.copy_dir_all(&from, from.join("dir").join("to"), &CopyOptions::new());
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidInput);
+}
+
+fn copy_dir_all_between_copies_to_fake<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let fake = FakeFileSystem::new();
+    let fake_dir = fake.temp_dir("copy").unwrap();
+    let to = fake_dir.path().join("to");
+    make_tree(fs, &from);
+    fs.symlink("file", from.join("link")).unwrap();
+    fs.set_mode(from.join("file"), 0o600).unwrap();
+
+    copy_dir_all_between_unix(fs, &from, &fake, &to, &CopyOptions::new()).unwrap();
+
+    assert_eq!(fake.read_file(to.join("file")).unwrap(), b"file");
+    assert_eq!(fake.mode(to.join("file")).unwrap() & 0o7777, 0o600);
+    assert_eq!(
+        fake.read_file(to.join("dir").join("nested")).unwrap(),
+        b"nested"
+    );
+    assert_eq!(
+        fake.get_symlink_src(to.join("link")).unwrap(),
+        Path::new("file")
+    );
+}
+
+fn copy_dir_all_between_copies_from_fake<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let fake = FakeFileSystem::new();
+    let fake_dir = fake.temp_dir("copy").unwrap();
+    let from = fake_dir.path().join("from");
+    let to = parent.join("to");
+    make_tree(&fake, &from);
+    fake.symlink("file", from.join("link")).unwrap();
+
+    copy_dir_all_between_unix(&fake, &from, fs, &to, &CopyOptions::new()).unwrap();
+
+    assert_eq!(fs.read_file(to.join("file")).unwrap(), b"file");
+    assert_eq!(
+        fs.read_file(to.join("dir").join("nested")).unwrap(),
+        b"nested"
+    );
+    assert_eq!(fs.read_file(to.join("link")).unwrap(), b"file");
+    assert!(fs.is_symlink(to.join("link")));
+}
+
+fn copy_dir_all_between_follows_symlinks_and_skips_modes<T: FileSystem + UnixFileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let from = parent.join("from");
+    let fake = FakeFileSystem::new();
+    let fake_dir = fake.temp_dir("copy").unwrap();
+    let to = fake_dir.path().join("to");
+    make_tree(fs, &from);
+    fs.symlink("file", from.join("link")).unwrap();
+    fs.symlink("does_not_exist", from.join("dangling")).unwrap();
+    fs.mkfifo(from.join("fifo"), 0o644).unwrap();
+    fs.set_mode(from.join("file"), 0o600).unwrap();
+    let options = CopyOptions::new()
+        .follow_symlinks(true)
+        .preserve_mode(false);
+
+    copy_dir_all_between(fs, &from, &fake, &to, &options).unwrap();
+
+    assert_eq!(fake.read_file(to.join("file")).unwrap(), b"file");
+    assert_eq!(fake.mode(to.join("file")).unwrap() & 0o7777, 0o644);
+    assert_eq!(fake.read_file(to.join("link")).unwrap(), b"file");
+    assert!(!fake.is_symlink(to.join("link")));
+    assert!(!fake.symlink_exists(to.join("dangling")));
+    assert!(!fake.symlink_exists(to.join("fifo")));
+}
+
+fn copy_dir_all_between_fails_if_options_need_unix<T: FileSystem + UnixFileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let from = parent.join("from");
+    let fake = FakeFileSystem::new();
+    make_tree(fs, &from);
+
+    for options in &[
+        CopyOptions::new().preserve_mode(false),
+        CopyOptions::new().follow_symlinks(true),
+    ] {
+        let result = copy_dir_all_between(fs, &from, &fake, "/to", options);
+
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::Unsupported);
+    }
+    assert!(!fake.exists("/to"));
+}
+
+#[test]
+fn copy_dir_all_between_copies_below_the_same_path_on_another_fake() {
+    let src = FakeFileSystem::new();
+    let dst = FakeFileSystem::new();
+    src.create_dir("/data").unwrap();
+    src.create_file("/data/file", "file").unwrap();
+    dst.create_dir("/data").unwrap();
+
+    copy_dir_all_between_unix(&src, "/data", &dst, "/data/out", &CopyOptions::new()).unwrap();
+    copy_dir_all_between_unix(&src, "/", &dst, "/dst", &CopyOptions::new()).unwrap();
+
+    assert_eq!(dst.read_file("/data/out/file").unwrap(), b"file");
+    assert_eq!(dst.read_file("/dst/data/file").unwrap(), b"file");
+    assert!(!src.exists("/data/out"));
+}
@@ -0,0 +1,254 @@
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Tests for the durability model of `FakeFileSystem`: which changes survive a
+//! simulated crash, depending on what was synced.
+extern crate filesystem;
+
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+use filesystem::{ContentSource, CrashPolicy, FakeFileSystem, FileSystem};
+
+/// Returns a fake with a durable, empty `/data` directory.
+fn setup() -> FakeFileSystem {
+    let fs = FakeFileSystem::new();
+    fs.create_dir("/data").unwrap();
+    fs.sync_all();
+    fs
+}
+
+#[test]
+fn crash_discards_unsynced_file() {
+    let fs = setup();
+
+    fs.create_file("/data/file", "contents").unwrap();
+    fs.crash();
+
+    assert!(!fs.exists("/data/file"));
+    assert!(fs.is_dir("/data"));
+}
+
+#[test]
+fn crash_discards_file_if_only_contents_were_synced() {
+    let fs = setup();
+
+    fs.create_file("/data/file", "contents").unwrap();
+    fs.sync("/data/file").unwrap();
+    fs.crash();
+
+    assert!(!fs.exists("/data/file"));
+}
+
+#[test]
+fn crash_keeps_empty_file_if_only_directory_was_synced() {
+    let fs = setup();
+
+    fs.create_file("/data/file", "contents").unwrap();
+    fs.sync_dir("/data").unwrap();
+    fs.crash();
+
+    assert_eq!(fs.read_file("/data/file").unwrap(), b"");
+}
+
+#[test]
+fn crash_keeps_file_if_contents_and_directory_were_synced() {
+    let fs = setup();
+
+    fs.create_file("/data/file", "contents").unwrap();
+    fs.sync("/data/file").unwrap();
+    fs.sync_dir("/data").unwrap();
+    fs.crash();
+
+    assert_eq!(fs.read_file("/data/file").unwrap(), b"contents");
+}
+
+#[test]
+fn crash_restores_synced_contents() {
+    let fs = setup();
+
+    fs.create_file("/data/file", "old").unwrap();
+    fs.sync_all();
+    fs.write_file("/data/file", "new").unwrap();
+    fs.crash();
+
+    assert_eq!(fs.read_file("/data/file").unwrap(), b"old");
+}
+
+#[test]
+fn crash_undoes_unsynced_rename_and_removal() {
+    let fs = setup();
+
+    fs.create_file("/data/a", "a").unwrap();
+    fs.create_file("/data/b", "b").unwrap();
+    fs.sync_all();
+    fs.rename("/data/a", "/data/c").unwrap();
+    fs.remove_file("/data/b").unwrap();
+    fs.crash();
+
+    assert_eq!(fs.read_file("/data/a").unwrap(), b"a");
+    assert_eq!(fs.read_file("/data/b").unwrap(), b"b");
+    assert!(!fs.exists("/data/c"));
+}
+
+#[test]
+fn crash_keeps_rename_once_directory_is_synced() {
+    let fs = setup();
+
+    fs.create_file("/data/a", "a").unwrap();
+    fs.sync_all();
+    fs.rename("/data/a", "/data/c").unwrap();
+    fs.sync_dir("/data").unwrap();
+    fs.crash();
+
+    assert!(!fs.exists("/data/a"));
+    assert_eq!(fs.read_file("/data/c").unwrap(), b"a");
+}
+
+#[test]
+fn crash_discards_children_of_unsynced_directory() {
+    let fs = setup();
+
+    fs.create_dir("/data/dir").unwrap();
+    fs.create_file("/data/dir/file", "contents").unwrap();
+    fs.sync("/data/dir/file").unwrap();
+    fs.sync_dir("/data/dir").unwrap();
+    fs.crash();
+
+    assert!(!fs.exists("/data/dir"));
+}
+
+#[test]
+fn crash_keeps_atomic_writes() {
+    let fs = setup();
+
+    fs.create_file("/data/file", "old").unwrap();
+    fs.sync_all();
+    fs.write_file_atomic("/data/file", "new").unwrap();
+    fs.crash();
+
+    assert_eq!(fs.read_file("/data/file").unwrap(), b"new");
+}
+
+fn crash_with_seed(seed: u64) -> FakeFileSystem {
+    let fs = setup();
+
+    fs.create_file("/data/file", "old contents").unwrap();
+    fs.sync_all();
+    fs.write_file("/data/file", "new contents").unwrap();
+    fs.create_file("/data/new", "").unwrap();
+    fs.set_crash_policy(CrashPolicy::Random { seed });
+    fs.crash();
+
+    fs
+}
+
+#[test]
+fn random_crash_is_deterministic_for_a_seed() {
+    for seed in 0..16 {
+        let first = crash_with_seed(seed);
+        let second = crash_with_seed(seed);
+
+        assert_eq!(
+            first.read_file("/data/file").unwrap(),
+            second.read_file("/data/file").unwrap()
+        );
+        assert_eq!(first.exists("/data/new"), second.exists("/data/new"));
+    }
+}
+
+#[test]
+fn random_crash_partially_applies_unsynced_changes() {
+    let outcomes: Vec<(Vec<u8>, bool)> = (0..64)
+        .map(crash_with_seed)
+        .map(|fs| (fs.read_file("/data/file").unwrap(), fs.exists("/data/new")))
+        .collect();
+
+    assert!(outcomes.iter().any(|o| o.0 == b"old contents"));
+    assert!(outcomes.iter().any(|o| o.0 == b"new contents"));
+    assert!(outcomes
+        .iter()
+        .any(|o| o.0 != b"old contents" && o.0 != b"new contents"));
+    assert!(outcomes.iter().any(|o| o.1));
+    assert!(outcomes.iter().any(|o| !o.1));
+}
+
+#[test]
+fn random_crash_never_tears_atomic_writes() {
+    for seed in 0..64 {
+        let fs = setup();
+
+        fs.create_file("/data/file", "old").unwrap();
+        fs.sync_all();
+        fs.set_crash_policy(CrashPolicy::Random { seed });
+        fs.write_file_atomic("/data/file", "new").unwrap();
+        fs.write_file_atomic("/data/file", "newer").unwrap();
+        fs.crash();
+
+        assert_eq!(fs.read_file(Path::new("/data/file")).unwrap(), b"newer");
+    }
+}
+
+#[test]
+fn random_crash_keeps_files_whose_bytes_did_not_change() {
+    let synced = UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+    for seed in 0..16 {
+        let fs = setup();
+
+        fs.create_generated_file("/data/file", 8, ContentSource::Pattern(b"ab".to_vec()))
+            .unwrap();
+        fs.set_modified("/data/file", synced).unwrap();
+        fs.sync_all();
+        fs.write_file_at("/data/file", 2, "abab").unwrap();
+        fs.set_crash_policy(CrashPolicy::Random { seed });
+        fs.crash();
+
+        assert_eq!(fs.read_file("/data/file").unwrap(), b"abababab");
+        assert_eq!(fs.modified("/data/file").unwrap(), synced);
+    }
+}
+
+#[test]
+fn random_crash_does_not_materialize_generated_files() {
+    const HUGE: u64 = 1 << 40;
+
+    for seed in 0..16 {
+        let fs = setup();
+
+        fs.create_generated_file("/data/huge", HUGE, ContentSource::Zeros)
+            .unwrap();
+        fs.sync_all();
+        fs.write_file_at("/data/huge", HUGE / 2, "new").unwrap();
+        fs.create_generated_file("/data/unsynced", HUGE, ContentSource::Zeros)
+            .unwrap();
+        fs.sync_dir("/data").unwrap();
+        fs.set_crash_policy(CrashPolicy::Random { seed });
+        fs.crash();
+
+        assert_eq!(fs.len("/data/huge"), HUGE);
+        assert!(fs.len("/data/unsynced") <= HUGE);
+        assert_eq!(
+            fs.read_file_range("/data/huge", HUGE / 2, 3).unwrap().len(),
+            3
+        );
+    }
+}
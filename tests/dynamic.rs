@@ -0,0 +1,127 @@
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Tests for `FakeFileSystem` files whose contents are computed by callbacks,
+//! as used to emulate procfs and sysfs.
+extern crate filesystem;
+
+use std::io::{Error, ErrorKind};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use filesystem::{DirEntry, DynamicFile, FakeFileSystem, FileSystem};
+
+fn counter_file(fs: &FakeFileSystem, path: &str) {
+    let reads = AtomicUsize::new(0);
+    let dynamic = DynamicFile::new(move || {
+        let n = reads.fetch_add(1, Ordering::SeqCst) + 1;
+        format!("reads: {}\n", n).into_bytes()
+    });
+
+    fs.create_dynamic_file(path, dynamic).unwrap();
+}
+
+#[test]
+fn dynamic_file_is_computed_on_every_read() {
+    let fs = FakeFileSystem::new();
+    counter_file(&fs, "/status");
+
+    assert_eq!(fs.read_file_to_string("/status").unwrap(), "reads: 1\n");
+    assert_eq!(fs.read_file_to_string("/status").unwrap(), "reads: 2\n");
+    assert_eq!(fs.read_file_range("/status", 7, 2).unwrap(), b"3\n");
+}
+
+#[test]
+fn dynamic_file_looks_like_a_regular_file() {
+    let fs = FakeFileSystem::new();
+    fs.create_dir("/proc").unwrap();
+    counter_file(&fs, "/proc/status");
+
+    let entries: Vec<_> = fs
+        .read_dir("/proc")
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+
+    assert_eq!(entries, vec!["status"]);
+    assert!(fs.is_file("/proc/status"));
+    assert_eq!(fs.len("/proc/status"), "reads: 1\n".len() as u64);
+}
+
+#[test]
+fn dynamic_file_without_write_handler_is_readonly() {
+    let fs = FakeFileSystem::new();
+    counter_file(&fs, "/status");
+
+    let result = fs.write_file("/status", "data");
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+    assert!(fs.readonly("/status").unwrap());
+}
+
+#[test]
+fn dynamic_file_hands_writes_to_handler() {
+    let fs = FakeFileSystem::new();
+    let value = Arc::new(Mutex::new(b"0\n".to_vec()));
+    let read_value = value.clone();
+    let dynamic =
+        DynamicFile::new(move || read_value.lock().unwrap().clone()).on_write(move |buf| {
+            *value.lock().unwrap() = buf.to_vec();
+            Ok(())
+        });
+
+    fs.create_dynamic_file("/brightness", dynamic).unwrap();
+    fs.write_file("/brightness", "7\n").unwrap();
+
+    assert_eq!(fs.read_file("/brightness").unwrap(), b"7\n");
+
+    fs.append_file("/brightness", "9\n").unwrap();
+    fs.write_file_at("/brightness", 0, "3\n").unwrap();
+    fs.set_len("/brightness", 0).unwrap();
+
+    assert_eq!(fs.read_file("/brightness").unwrap(), b"3\n");
+}
+
+#[test]
+fn dynamic_file_write_handler_errors_are_returned() {
+    let fs = FakeFileSystem::new();
+    let dynamic = DynamicFile::new(Vec::new)
+        .on_write(|_| Err(Error::new(ErrorKind::InvalidInput, "invalid argument")));
+
+    fs.create_dynamic_file("/control", dynamic).unwrap();
+
+    let result = fs.write_file("/control", "bogus");
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn copying_dynamic_file_copies_current_contents() {
+    let fs = FakeFileSystem::new();
+    counter_file(&fs, "/status");
+
+    fs.copy_file("/status", "/copy").unwrap();
+
+    assert_eq!(fs.read_file_to_string("/copy").unwrap(), "reads: 1\n");
+    assert_eq!(fs.read_file_to_string("/copy").unwrap(), "reads: 1\n");
+}
@@ -21,16 +21,26 @@
 // SOFTWARE.
 
 extern crate filesystem;
+#[cfg(unix)]
+extern crate libc;
 
 #[macro_use]
 mod utils;
 
+#[cfg(target_os = "linux")]
+use std::ffi::OsStr;
 use std::io::ErrorKind;
+#[cfg(target_os = "linux")]
+use std::os::unix::ffi::OsStrExt;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
 
 #[cfg(unix)]
 use filesystem::UnixFileSystem;
-use filesystem::{DirEntry, FakeFileSystem, FileSystem, OsFileSystem, TempDir, TempFileSystem};
+use filesystem::{
+    DirEntry, FakeFileSystem, FakeVolume, FileSystem, FileType, OsFileSystem, TempDir,
+    TempFileSystem,
+};
 
 macro_rules! test_fs {
     ($name:ident, $fs:expr) => {
@@ -48,6 +58,11 @@ macro_rules! test_fs {
             make_test!(is_file_returns_false_if_node_is_dir, $fs);
             make_test!(is_file_returns_false_if_node_does_not_exist, $fs);
 
+            make_test!(exists_returns_true_if_node_is_file_or_dir, $fs);
+            make_test!(exists_returns_false_if_node_does_not_exist, $fs);
+            make_test!(try_exists_returns_true_if_node_exists, $fs);
+            make_test!(try_exists_returns_false_if_node_does_not_exist, $fs);
+
             make_test!(create_dir_creates_new_dir, $fs);
             make_test!(create_dir_fails_if_dir_already_exists, $fs);
             make_test!(create_dir_fails_if_parent_does_not_exist, $fs);
@@ -90,6 +105,36 @@ macro_rules! test_fs {
             make_test!(overwrite_file_fails_if_file_is_readonly, $fs);
             make_test!(overwrite_file_fails_if_node_is_a_directory, $fs);
 
+            make_test!(append_file_creates_new_file, $fs);
+            make_test!(append_file_appends_to_existing_file, $fs);
+            #[cfg(unix)]
+            make_test!(append_file_fails_if_file_is_readonly, $fs);
+            make_test!(append_file_fails_if_node_is_a_directory, $fs);
+            make_test!(append_existing_file_fails_if_node_does_not_exist, $fs);
+            make_test!(append_existing_file_appends_to_existing_file, $fs);
+
+            make_test!(read_file_range_returns_requested_bytes, $fs);
+            make_test!(read_file_range_stops_at_end_of_file, $fs);
+            make_test!(read_file_range_clamps_huge_length_to_file_size, $fs);
+            make_test!(read_file_range_fails_if_node_does_not_exist, $fs);
+            make_test!(write_file_at_overwrites_part_of_file, $fs);
+            make_test!(write_file_at_fills_gap_with_zeros, $fs);
+            #[cfg(unix)]
+            make_test!(write_file_at_fails_if_file_is_readonly, $fs);
+            make_test!(write_file_at_fails_if_node_does_not_exist, $fs);
+            make_test!(set_len_truncates_file, $fs);
+            make_test!(set_len_extends_file_with_zeros, $fs);
+            make_test!(set_len_fails_if_node_is_a_directory, $fs);
+
+            make_test!(write_file_atomic_creates_new_file, $fs);
+            make_test!(write_file_atomic_replaces_contents_of_existing_file, $fs);
+            make_test!(write_file_atomic_leaves_no_temporary_files, $fs);
+            make_test!(write_file_atomic_replaces_readonly_file, $fs);
+            #[cfg(unix)]
+            make_test!(write_file_atomic_preserves_mode, $fs);
+            make_test!(write_file_atomic_fails_if_node_is_a_directory, $fs);
+            make_test!(write_file_atomic_fails_if_parent_does_not_exist, $fs);
+
             make_test!(read_file_returns_contents_as_bytes, $fs);
             make_test!(read_file_fails_if_file_does_not_exist, $fs);
 
@@ -102,6 +147,18 @@ macro_rules! test_fs {
 
             make_test!(create_file_writes_to_new_file, $fs);
             make_test!(create_file_fails_if_file_already_exists, $fs);
+            make_test!(create_file_accepts_names_of_255_bytes, $fs);
+            make_test!(create_file_fails_if_name_is_too_long, $fs);
+            make_test!(create_file_fails_if_path_contains_nul, $fs);
+            make_test!(create_dir_fails_if_name_is_too_long, $fs);
+            #[cfg(target_os = "linux")]
+            make_test!(create_file_round_trips_non_utf8_names, $fs);
+            #[cfg(unix)]
+            make_test!(read_file_fails_if_path_is_too_long, $fs);
+            make_test!(rename_fails_if_name_is_too_long, $fs);
+            make_test!(rename_fails_if_path_contains_nul, $fs);
+            #[cfg(unix)]
+            make_test!(rename_fails_if_path_is_too_long, $fs);
 
             make_test!(remove_file_removes_a_file, $fs);
             make_test!(remove_file_fails_if_file_does_not_exist, $fs);
@@ -137,6 +194,18 @@ macro_rules! test_fs {
             make_test!(len_returns_size_of_directory, $fs);
             make_test!(len_returns_0_if_node_does_not_exist, $fs);
 
+            make_test!(set_modified_sets_modification_time, $fs);
+            make_test!(modified_fails_if_node_does_not_exist, $fs);
+
+            make_test!(sync_succeeds_for_files_and_directories, $fs);
+            make_test!(sync_fails_if_node_does_not_exist, $fs);
+            make_test!(sync_dir_fails_if_node_is_a_file, $fs);
+
+            #[cfg(unix)]
+            make_test!(space_reports_total_free_and_available, $fs);
+            #[cfg(unix)]
+            make_test!(space_fails_if_node_does_not_exist, $fs);
+
             #[cfg(unix)]
             make_test!(mode_returns_permissions, $fs);
             #[cfg(unix)]
@@ -147,8 +216,81 @@ macro_rules! test_fs {
             #[cfg(unix)]
             make_test!(set_mode_fails_if_node_does_not_exist, $fs);
 
+            #[cfg(unix)]
+            make_test!(device_is_shared_by_nodes_on_the_same_file_system, $fs);
+            #[cfg(unix)]
+            make_test!(device_fails_if_node_does_not_exist, $fs);
+
+            #[cfg(unix)]
+            make_test!(access_checks_permissions_of_current_user, $fs);
+            #[cfg(unix)]
+            make_test!(access_fails_if_node_does_not_exist, $fs);
+            #[cfg(unix)]
+            make_test!(lookup_fails_without_search_permission_on_parent, $fs);
+            #[cfg(unix)]
+            make_test!(rename_fails_without_search_permission_on_parent, $fs);
+            #[cfg(unix)]
+            make_test!(create_file_fails_without_write_permission_on_parent, $fs);
+            #[cfg(unix)]
+            make_test!(remove_file_fails_without_write_permission_on_parent, $fs);
+            #[cfg(unix)]
+            make_test!(read_dir_fails_without_read_permission, $fs);
+
+            #[cfg(unix)]
+            make_test!(owner_of_new_nodes_is_owner_of_parent, $fs);
+            #[cfg(unix)]
+            make_test!(owner_fails_if_node_does_not_exist, $fs);
+            #[cfg(unix)]
+            make_test!(chown_keeps_ids_that_are_none, $fs);
+            #[cfg(unix)]
+            make_test!(chown_fails_if_node_does_not_exist, $fs);
+
+            #[cfg(unix)]
+            make_test!(new_nodes_get_default_mode_less_umask, $fs);
+            #[cfg(unix)]
+            make_test!(create_dir_with_mode_sets_mode, $fs);
+            #[cfg(unix)]
+            make_test!(create_file_with_mode_sets_mode, $fs);
+            #[cfg(unix)]
+            make_test!(create_file_with_mode_fails_if_node_exists, $fs);
+
+            #[cfg(unix)]
+            make_test!(mode_includes_file_type_bits, $fs);
+            #[cfg(unix)]
+            make_test!(set_mode_keeps_special_bits, $fs);
+            #[cfg(unix)]
+            make_test!(new_nodes_inherit_group_of_setgid_dir, $fs);
+            #[cfg(unix)]
+            make_test!(read_dir_reports_file_types, $fs);
+            #[cfg(unix)]
+            make_test!(mkfifo_creates_named_pipe, $fs);
+            #[cfg(unix)]
+            make_test!(mkfifo_fails_if_node_exists, $fs);
+            #[cfg(unix)]
+            make_test!(mknod_fails_for_directories_and_symlinks, $fs);
+            #[cfg(target_os = "linux")]
+            make_test!(mknod_ignores_file_type_bits_in_mode, $fs);
+            #[cfg(unix)]
+            make_test!(remove_file_removes_fifo, $fs);
+            #[cfg(unix)]
+            make_test!(set_xattr_sets_value, $fs);
+            #[cfg(unix)]
+            make_test!(set_xattr_follows_symlinks, $fs);
+            #[cfg(target_os = "linux")]
+            make_test!(get_xattr_fails_if_attribute_does_not_exist, $fs);
+            #[cfg(target_os = "linux")]
+            make_test!(remove_xattr_removes_attribute, $fs);
+            #[cfg(target_os = "linux")]
+            make_test!(set_xattr_fails_for_unknown_namespace, $fs);
+            #[cfg(target_os = "linux")]
+            make_test!(lset_xattr_fails_for_user_namespace_on_symlink, $fs);
+            #[cfg(unix)]
+            make_test!(xattrs_fail_if_node_does_not_exist, $fs);
+
             make_test!(temp_dir_creates_tempdir, $fs);
             make_test!(temp_dir_creates_unique_dir, $fs);
+            #[cfg(target_os = "linux")]
+            make_test!(temp_dir_os_accepts_non_utf8_prefix, $fs);
         }
     };
 }
@@ -216,6 +358,35 @@ fn is_file_returns_false_if_node_does_not_exist<T: FileSystem>(fs: &T, parent: &
     assert!(!fs.is_file(parent.join("does_not_exist")));
 }
 
+fn exists_returns_true_if_node_is_file_or_dir<T: FileSystem>(fs: &T, parent: &Path) {
+    let file = parent.join("new_file");
+    let dir = parent.join("new_dir");
+
+    fs.create_file(&file, "").unwrap();
+    fs.create_dir(&dir).unwrap();
+
+    assert!(fs.exists(&file));
+    assert!(fs.exists(&dir));
+}
+
+fn exists_returns_false_if_node_does_not_exist<T: FileSystem>(fs: &T, parent: &Path) {
+    assert!(!fs.exists(parent.join("does_not_exist")));
+}
+
+fn try_exists_returns_true_if_node_exists<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("new_file");
+
+    fs.create_file(&path, "").unwrap();
+
+    assert!(fs.try_exists(&path).unwrap());
+}
+
+fn try_exists_returns_false_if_node_does_not_exist<T: FileSystem>(fs: &T, parent: &Path) {
+    let result = fs.try_exists(parent.join("does_not_exist"));
+
+    assert!(!result.unwrap());
+}
+
 fn create_dir_creates_new_dir<T: FileSystem>(fs: &T, parent: &Path) {
     let path = parent.join("new_dir");
 
@@ -567,6 +738,260 @@ fn overwrite_file_fails_if_node_is_a_directory<T: FileSystem>(fs: &T, parent: &P
     assert_eq!(result.unwrap_err().kind(), ErrorKind::IsADirectory);
 }
 
+fn append_file_creates_new_file<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("new_file");
+
+    let result = fs.append_file(&path, "new contents");
+
+    assert!(result.is_ok());
+    assert_eq!(fs.read_file(&path).unwrap(), b"new contents");
+}
+
+fn append_file_appends_to_existing_file<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("test_file");
+
+    fs.create_file(&path, "first line\n").unwrap();
+    fs.append_file(&path, "second line\n").unwrap();
+    fs.append_file(&path, "third line\n").unwrap();
+
+    assert_eq!(
+        fs.read_file_to_string(&path).unwrap(),
+        "first line\nsecond line\nthird line\n"
+    );
+}
+
+#[cfg(unix)]
+fn append_file_fails_if_file_is_readonly<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    if is_root(fs, parent) {
+        return;
+    }
+
+    let path = parent.join("test_file");
+
+    fs.create_file(&path, "contents").unwrap();
+    fs.set_readonly(&path, true).unwrap();
+
+    let result = fs.append_file(&path, "more contents");
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+    assert_eq!(fs.read_file(&path).unwrap(), b"contents");
+}
+
+fn append_file_fails_if_node_is_a_directory<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("test_dir");
+
+    fs.create_dir(&path).unwrap();
+
+    let result = fs.append_file(&path, "contents");
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::IsADirectory);
+}
+
+fn append_existing_file_fails_if_node_does_not_exist<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("new_file");
+
+    let result = fs.append_existing_file(&path, "contents");
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+    assert!(!fs.exists(&path));
+}
+
+fn append_existing_file_appends_to_existing_file<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("test_file");
+
+    fs.create_file(&path, "abc").unwrap();
+    fs.append_existing_file(&path, "def").unwrap();
+
+    assert_eq!(fs.read_file(&path).unwrap(), b"abcdef");
+}
+
+fn read_file_range_returns_requested_bytes<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("test_file");
+
+    fs.create_file(&path, "0123456789").unwrap();
+
+    assert_eq!(fs.read_file_range(&path, 2, 3).unwrap(), b"234");
+    assert_eq!(fs.read_file_range(&path, 0, 0).unwrap(), b"");
+}
+
+fn read_file_range_stops_at_end_of_file<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("test_file");
+
+    fs.create_file(&path, "0123456789").unwrap();
+
+    assert_eq!(fs.read_file_range(&path, 8, 10).unwrap(), b"89");
+    assert_eq!(fs.read_file_range(&path, 20, 10).unwrap(), b"");
+}
+
+fn read_file_range_clamps_huge_length_to_file_size<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("test_file");
+
+    fs.create_file(&path, "01234").unwrap();
+
+    assert_eq!(fs.read_file_range(&path, 0, 1 << 34).unwrap(), b"01234");
+    assert_eq!(fs.read_file_range(&path, 3, u64::MAX).unwrap(), b"34");
+}
+
+fn read_file_range_fails_if_node_does_not_exist<T: FileSystem>(fs: &T, parent: &Path) {
+    let result = fs.read_file_range(parent.join("does_not_exist"), 0, 1);
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+fn write_file_at_overwrites_part_of_file<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("test_file");
+
+    fs.create_file(&path, "0123456789").unwrap();
+    fs.write_file_at(&path, 3, "abc").unwrap();
+    fs.write_file_at(&path, 8, "xyz").unwrap();
+
+    assert_eq!(fs.read_file(&path).unwrap(), b"012abc67xyz");
+}
+
+fn write_file_at_fills_gap_with_zeros<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("test_file");
+
+    fs.create_file(&path, "ab").unwrap();
+    fs.write_file_at(&path, 4, "cd").unwrap();
+
+    assert_eq!(fs.read_file(&path).unwrap(), b"ab\0\0cd");
+}
+
+#[cfg(unix)]
+fn write_file_at_fails_if_file_is_readonly<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    if is_root(fs, parent) {
+        return;
+    }
+
+    let path = parent.join("test_file");
+
+    fs.create_file(&path, "contents").unwrap();
+    fs.set_readonly(&path, true).unwrap();
+
+    let result = fs.write_file_at(&path, 0, "new");
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+}
+
+fn write_file_at_fails_if_node_does_not_exist<T: FileSystem>(fs: &T, parent: &Path) {
+    let result = fs.write_file_at(parent.join("does_not_exist"), 0, "new");
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+fn set_len_truncates_file<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("test_file");
+
+    fs.create_file(&path, "0123456789").unwrap();
+    fs.set_len(&path, 4).unwrap();
+
+    assert_eq!(fs.read_file(&path).unwrap(), b"0123");
+    assert_eq!(fs.len(&path), 4);
+}
+
+fn set_len_extends_file_with_zeros<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("test_file");
+
+    fs.create_file(&path, "ab").unwrap();
+    fs.set_len(&path, 4).unwrap();
+
+    assert_eq!(fs.read_file(&path).unwrap(), b"ab\0\0");
+}
+
+fn set_len_fails_if_node_is_a_directory<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("test_dir");
+
+    fs.create_dir(&path).unwrap();
+
+    let result = fs.set_len(&path, 0);
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::IsADirectory);
+}
+
+fn write_file_atomic_creates_new_file<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("new_file");
+
+    let result = fs.write_file_atomic(&path, "new contents");
+
+    assert!(result.is_ok());
+    assert_eq!(fs.read_file(&path).unwrap(), b"new contents");
+}
+
+fn write_file_atomic_replaces_contents_of_existing_file<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("test_file");
+
+    fs.create_file(&path, "old contents that are longer")
+        .unwrap();
+
+    let result = fs.write_file_atomic(&path, "new contents");
+
+    assert!(result.is_ok());
+    assert_eq!(fs.read_file(&path).unwrap(), b"new contents");
+}
+
+fn write_file_atomic_leaves_no_temporary_files<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("test_file");
+
+    fs.write_file_atomic(&path, "first").unwrap();
+    fs.write_file_atomic(&path, "second").unwrap();
+
+    let entries: Vec<_> = fs.read_dir(parent).unwrap().collect();
+
+    assert_eq!(entries.len(), 1);
+}
+
+fn write_file_atomic_replaces_readonly_file<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("test_file");
+
+    fs.create_file(&path, "old contents").unwrap();
+    fs.set_readonly(&path, true).unwrap();
+
+    let result = fs.write_file_atomic(&path, "new contents");
+
+    assert!(result.is_ok());
+    assert_eq!(fs.read_file(&path).unwrap(), b"new contents");
+    assert!(fs.readonly(&path).unwrap());
+}
+
+#[cfg(unix)]
+fn write_file_atomic_preserves_mode<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("test_file");
+
+    fs.create_file(&path, "old contents").unwrap();
+    fs.set_mode(&path, 0o640).unwrap();
+
+    fs.write_file_atomic(&path, "new contents").unwrap();
+
+    assert_eq!(fs.mode(&path).unwrap() & 0o777, 0o640);
+}
+
+fn write_file_atomic_fails_if_node_is_a_directory<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("test_dir");
+
+    fs.create_dir(&path).unwrap();
+
+    let result = fs.write_file_atomic(&path, "test contents");
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::IsADirectory);
+}
+
+fn write_file_atomic_fails_if_parent_does_not_exist<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("parent").join("test_file");
+
+    let result = fs.write_file_atomic(&path, "test contents");
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+}
+
 fn read_file_returns_contents_as_bytes<T: FileSystem>(fs: &T, parent: &Path) {
     let path = parent.join("test.txt");
 
@@ -661,47 +1086,149 @@ fn create_file_fails_if_file_already_exists<T: FileSystem>(fs: &T, parent: &Path
     assert_eq!(result.unwrap_err().kind(), ErrorKind::AlreadyExists);
 }
 
-fn remove_file_removes_a_file<T: FileSystem>(fs: &T, parent: &Path) {
-    let path = parent.join("test_file");
+fn create_file_accepts_names_of_255_bytes<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("a".repeat(255));
 
-    fs.create_file(&path, "").unwrap();
+    fs.create_file(&path, "contents").unwrap();
 
-    let result = fs.remove_file(&path);
+    assert!(fs.is_file(&path));
+}
 
-    assert!(result.is_ok());
+fn create_file_fails_if_name_is_too_long<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("a".repeat(256));
 
-    let result = fs.read_file(&path);
+    let result = fs.create_file(&path, "contents");
 
     assert!(result.is_err());
-    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidFilename);
+    assert!(!fs.exists(&path));
 }
 
-fn remove_file_fails_if_file_does_not_exist<T: FileSystem>(fs: &T, parent: &Path) {
-    let result = fs.remove_file(parent.join("does_not_exist"));
+fn create_file_fails_if_path_contains_nul<T: FileSystem>(fs: &T, parent: &Path) {
+    let result = fs.create_file(parent.join("a\0b"), "contents");
 
     assert!(result.is_err());
-    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidInput);
 }
 
-fn remove_file_fails_if_node_is_a_directory<T: FileSystem>(fs: &T, parent: &Path) {
-    let path = parent.join("test_dir");
+#[cfg(target_os = "linux")]
+fn create_file_round_trips_non_utf8_names<T: FileSystem>(fs: &T, parent: &Path) {
+    let name = OsStr::from_bytes(b"caf\xe9");
+    let renamed = OsStr::from_bytes(b"\xff\xfe");
 
-    fs.create_dir(&path).unwrap();
+    fs.create_file(parent.join(name), "contents").unwrap();
 
-    let result = fs.remove_file(&path);
+    let entries: Vec<_> = fs.read_dir(parent).unwrap().map(Result::unwrap).collect();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].file_name(), name);
+    assert_eq!(entries[0].path(), parent.join(name));
 
-    assert!(result.is_err());
+    fs.rename(entries[0].path(), parent.join(renamed)).unwrap();
+
+    assert_eq!(fs.read_file(parent.join(renamed)).unwrap(), b"contents");
+    assert!(!fs.exists(parent.join(name)));
 }
 
-fn copy_file_copies_a_file<T: FileSystem>(fs: &T, parent: &Path) {
-    let from = parent.join("from");
-    let to = parent.join("to");
+fn create_dir_fails_if_name_is_too_long<T: FileSystem>(fs: &T, parent: &Path) {
+    let result = fs.create_dir(parent.join("a".repeat(256)));
 
-    fs.create_file(&from, "test").unwrap();
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidFilename);
+}
 
-    let result = fs.copy_file(&from, &to);
+#[cfg(unix)]
+fn read_file_fails_if_path_is_too_long<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("a/".repeat(2048)).join("file");
 
-    assert!(result.is_ok());
+    let result = fs.read_file(path);
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidFilename);
+}
+
+fn rename_fails_if_name_is_too_long<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("file");
+
+    fs.create_file(&path, "contents").unwrap();
+
+    let result = fs.rename(&path, parent.join("a".repeat(256)));
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidFilename);
+
+    let result = fs.rename(parent.join("a".repeat(256)), &path);
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidFilename);
+    assert!(fs.is_file(&path));
+}
+
+fn rename_fails_if_path_contains_nul<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("file");
+
+    fs.create_file(&path, "contents").unwrap();
+
+    let result = fs.rename(&path, parent.join("a\0b"));
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidInput);
+    assert!(fs.is_file(&path));
+}
+
+#[cfg(unix)]
+fn rename_fails_if_path_is_too_long<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("file");
+
+    fs.create_file(&path, "contents").unwrap();
+
+    let result = fs.rename(&path, parent.join("a/".repeat(2048)).join("file"));
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidFilename);
+    assert!(fs.is_file(&path));
+}
+
+fn remove_file_removes_a_file<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("test_file");
+
+    fs.create_file(&path, "").unwrap();
+
+    let result = fs.remove_file(&path);
+
+    assert!(result.is_ok());
+
+    let result = fs.read_file(&path);
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+fn remove_file_fails_if_file_does_not_exist<T: FileSystem>(fs: &T, parent: &Path) {
+    let result = fs.remove_file(parent.join("does_not_exist"));
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+fn remove_file_fails_if_node_is_a_directory<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("test_dir");
+
+    fs.create_dir(&path).unwrap();
+
+    let result = fs.remove_file(&path);
+
+    assert!(result.is_err());
+}
+
+fn copy_file_copies_a_file<T: FileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+
+    fs.create_file(&from, "test").unwrap();
+
+    let result = fs.copy_file(&from, &to);
+
+    assert!(result.is_ok());
 
     let result = fs.read_file(&to);
 
@@ -1027,6 +1554,78 @@ fn len_returns_0_if_node_does_not_exist<T: FileSystem>(fs: &T, parent: &Path) {
     assert_eq!(len, 0);
 }
 
+fn set_modified_sets_modification_time<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("file");
+    let time = UNIX_EPOCH + Duration::from_secs(1_000_000);
+
+    fs.create_file(&path, "").unwrap();
+    fs.set_modified(&path, time).unwrap();
+
+    assert_eq!(fs.modified(&path).unwrap(), time);
+}
+
+fn modified_fails_if_node_does_not_exist<T: FileSystem>(fs: &T, parent: &Path) {
+    let result = fs.modified(parent.join("does_not_exist"));
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+#[cfg(unix)]
+fn space_reports_total_free_and_available<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("file");
+
+    fs.create_file(&path, "contents").unwrap();
+
+    let space = fs.space(&path).unwrap();
+
+    assert!(space.total > 0);
+    assert!(space.free <= space.total);
+    assert!(space.available <= space.free);
+    assert_eq!(fs.space(parent).unwrap().total, space.total);
+}
+
+#[cfg(unix)]
+fn space_fails_if_node_does_not_exist<T: FileSystem>(fs: &T, parent: &Path) {
+    let result = fs.space(parent.join("does_not_exist"));
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+fn sync_succeeds_for_files_and_directories<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("file");
+
+    fs.create_file(&path, "contents").unwrap();
+
+    assert!(fs.sync(&path).is_ok());
+    assert!(fs.sync(parent).is_ok());
+    assert!(fs.sync_dir(parent).is_ok());
+}
+
+fn sync_fails_if_node_does_not_exist<T: FileSystem>(fs: &T, parent: &Path) {
+    let result = fs.sync(parent.join("does_not_exist"));
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+
+    let result = fs.sync_dir(parent.join("does_not_exist"));
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+fn sync_dir_fails_if_node_is_a_file<T: FileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("file");
+
+    fs.create_file(&path, "").unwrap();
+
+    let result = fs.sync_dir(&path);
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotADirectory);
+}
+
 #[cfg(unix)]
 fn mode_returns_permissions<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
     let path = parent.join("file");
@@ -1122,26 +1721,1028 @@ fn set_mode_fails_if_node_does_not_exist<T: UnixFileSystem>(fs: &T, parent: &Pat
     assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
 }
 
-fn temp_dir_creates_tempdir<T: FileSystem + TempFileSystem>(fs: &T, _: &Path) {
-    let path = {
-        let result = fs.temp_dir("test");
+#[cfg(unix)]
+fn device_is_shared_by_nodes_on_the_same_file_system<T: FileSystem + UnixFileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let path = parent.join("file");
+    let link = parent.join("link");
 
-        assert!(result.is_ok());
+    fs.create_file(&path, "").unwrap();
+    fs.symlink(&path, &link).unwrap();
 
-        let temp_dir = result.unwrap();
+    let device = fs.device(parent).unwrap();
 
-        assert!(fs.is_dir(temp_dir.path()));
+    assert_eq!(fs.device(&path).unwrap(), device);
+    assert_eq!(fs.device(&link).unwrap(), device);
+}
 
-        temp_dir.path().to_path_buf()
-    };
+#[cfg(unix)]
+fn device_fails_if_node_does_not_exist<T: UnixFileSystem>(fs: &T, parent: &Path) {
+    let result = fs.device(parent.join("does_not_exist"));
 
-    assert!(!fs.is_dir(&path));
-    assert!(fs.is_dir(path.parent().unwrap()));
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
 }
 
-fn temp_dir_creates_unique_dir<T: FileSystem + TempFileSystem>(fs: &T, _: &Path) {
-    let first = fs.temp_dir("test").unwrap();
-    let second = fs.temp_dir("test").unwrap();
+/// Returns whether `fs` acts on behalf of root, which bypasses permission
+/// checks. The temporary directory is owned by the effective user.
+#[cfg(unix)]
+fn is_root<T: UnixFileSystem>(fs: &T, parent: &Path) -> bool {
+    fs.owner(parent).unwrap().0 == 0
+}
 
-    assert_ne!(first.path(), second.path());
+#[cfg(unix)]
+fn access_checks_permissions_of_current_user<T: FileSystem + UnixFileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    if is_root(fs, parent) {
+        return;
+    }
+
+    let path = parent.join("file");
+
+    fs.create_file(&path, "").unwrap();
+    fs.set_mode(&path, 0o640).unwrap();
+
+    assert!(fs.access(&path, 0).is_ok());
+    assert!(fs.access(&path, 4 | 2).is_ok());
+
+    let result = fs.access(&path, 1);
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    fs.set_mode(&path, 0o440).unwrap();
+
+    let result = fs.access(&path, 2);
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+}
+
+#[cfg(unix)]
+fn access_fails_if_node_does_not_exist<T: UnixFileSystem>(fs: &T, parent: &Path) {
+    let result = fs.access(parent.join("does_not_exist"), 0);
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+#[cfg(unix)]
+fn lookup_fails_without_search_permission_on_parent<T: FileSystem + UnixFileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    if is_root(fs, parent) {
+        return;
+    }
+
+    let dir = parent.join("dir");
+    let path = dir.join("file");
+
+    fs.create_dir(&dir).unwrap();
+    fs.create_file(&path, "contents").unwrap();
+    fs.set_mode(&dir, 0o644).unwrap();
+
+    let result = fs.read_file(&path);
+
+    fs.set_mode(&dir, 0o755).unwrap();
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+}
+
+#[cfg(unix)]
+fn rename_fails_without_search_permission_on_parent<T: FileSystem + UnixFileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    if is_root(fs, parent) {
+        return;
+    }
+
+    let dir = parent.join("dir");
+    let path = dir.join("file");
+    let other = parent.join("other");
+
+    fs.create_dir(&dir).unwrap();
+    fs.create_file(&path, "contents").unwrap();
+    fs.create_file(&other, "").unwrap();
+    fs.set_mode(&dir, 0o644).unwrap();
+
+    let from_result = fs.rename(&path, parent.join("renamed"));
+    let to_result = fs.rename(&other, dir.join("other"));
+
+    fs.set_mode(&dir, 0o755).unwrap();
+
+    assert!(from_result.is_err());
+    assert_eq!(from_result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+    assert!(to_result.is_err());
+    assert_eq!(to_result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+    assert!(fs.is_file(&path));
+    assert!(fs.is_file(&other));
+}
+
+#[cfg(unix)]
+fn create_file_fails_without_write_permission_on_parent<T: FileSystem + UnixFileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    if is_root(fs, parent) {
+        return;
+    }
+
+    let dir = parent.join("dir");
+
+    fs.create_dir(&dir).unwrap();
+    fs.set_mode(&dir, 0o555).unwrap();
+
+    let result = fs.create_file(dir.join("file"), "");
+
+    fs.set_mode(&dir, 0o755).unwrap();
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+    assert!(!fs.exists(dir.join("file")));
+}
+
+#[cfg(unix)]
+fn remove_file_fails_without_write_permission_on_parent<T: FileSystem + UnixFileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    if is_root(fs, parent) {
+        return;
+    }
+
+    let dir = parent.join("dir");
+    let path = dir.join("file");
+
+    fs.create_dir(&dir).unwrap();
+    fs.create_file(&path, "").unwrap();
+    fs.set_mode(&dir, 0o555).unwrap();
+
+    let result = fs.remove_file(&path);
+
+    fs.set_mode(&dir, 0o755).unwrap();
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+    assert!(fs.is_file(&path));
+}
+
+#[cfg(unix)]
+fn read_dir_fails_without_read_permission<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    if is_root(fs, parent) {
+        return;
+    }
+
+    let dir = parent.join("dir");
+
+    fs.create_dir(&dir).unwrap();
+    fs.create_file(dir.join("file"), "").unwrap();
+    fs.set_mode(&dir, 0o311).unwrap();
+
+    let result = fs.read_dir(&dir).map(|_| ());
+
+    fs.set_mode(&dir, 0o755).unwrap();
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+}
+
+#[cfg(unix)]
+fn owner_of_new_nodes_is_owner_of_parent<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let file = parent.join("file");
+    let dir = parent.join("dir");
+
+    fs.create_file(&file, "").unwrap();
+    fs.create_dir(&dir).unwrap();
+
+    let owner = fs.owner(parent).unwrap();
+
+    assert_eq!(fs.owner(&file).unwrap(), owner);
+    assert_eq!(fs.owner(&dir).unwrap(), owner);
+}
+
+#[cfg(unix)]
+fn owner_fails_if_node_does_not_exist<T: UnixFileSystem>(fs: &T, parent: &Path) {
+    let result = fs.owner(parent.join("does_not_exist"));
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+#[cfg(unix)]
+fn chown_keeps_ids_that_are_none<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("file");
+
+    fs.create_file(&path, "").unwrap();
+
+    let (uid, gid) = fs.owner(&path).unwrap();
+
+    fs.chown(&path, None, None).unwrap();
+    fs.chown(&path, Some(uid), None).unwrap();
+    fs.chown(&path, None, Some(gid)).unwrap();
+
+    assert_eq!(fs.owner(&path).unwrap(), (uid, gid));
+}
+
+#[cfg(unix)]
+fn chown_fails_if_node_does_not_exist<T: UnixFileSystem>(fs: &T, parent: &Path) {
+    let result = fs.chown(parent.join("does_not_exist"), None, None);
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+#[cfg(unix)]
+fn new_nodes_get_default_mode_less_umask<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let file = parent.join("file");
+    let dir = parent.join("dir");
+    let umask = fs.umask();
+
+    fs.create_file(&file, "").unwrap();
+    fs.create_dir(&dir).unwrap();
+
+    assert_eq!(fs.mode(&file).unwrap() & 0o777, 0o666 & !umask);
+    assert_eq!(fs.mode(&dir).unwrap() & 0o777, 0o777 & !umask);
+}
+
+#[cfg(unix)]
+fn create_dir_with_mode_sets_mode<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("dir");
+
+    fs.create_dir_with_mode(&path, 0o700).unwrap();
+
+    assert!(fs.is_dir(&path));
+    assert_eq!(fs.mode(&path).unwrap() & 0o777, 0o700);
+}
+
+#[cfg(unix)]
+fn create_file_with_mode_sets_mode<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("file");
+
+    fs.create_file_with_mode(&path, "contents", 0o600).unwrap();
+
+    assert_eq!(fs.read_file(&path).unwrap(), b"contents");
+    assert_eq!(fs.mode(&path).unwrap() & 0o777, 0o600);
+}
+
+#[cfg(unix)]
+fn create_file_with_mode_fails_if_node_exists<T: FileSystem + UnixFileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let path = parent.join("file");
+
+    fs.create_file(&path, "contents").unwrap();
+
+    let result = fs.create_file_with_mode(&path, "", 0o600);
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::AlreadyExists);
+    assert_eq!(fs.read_file(&path).unwrap(), b"contents");
+}
+
+#[cfg(unix)]
+fn mode_includes_file_type_bits<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let file = parent.join("file");
+    let dir = parent.join("dir");
+    let link = parent.join("link");
+
+    fs.create_file(&file, "").unwrap();
+    fs.create_dir(&dir).unwrap();
+    fs.symlink(&dir, &link).unwrap();
+
+    assert_eq!(fs.mode(&file).unwrap() & 0o170_000, 0o100_000);
+    assert_eq!(fs.mode(&dir).unwrap() & 0o170_000, 0o040_000);
+    assert_eq!(fs.mode(&link).unwrap() & 0o170_000, 0o040_000);
+}
+
+#[cfg(unix)]
+fn set_mode_keeps_special_bits<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("dir");
+
+    fs.create_dir(&path).unwrap();
+    fs.set_mode(&path, 0o1777).unwrap();
+
+    assert_eq!(fs.mode(&path).unwrap() & 0o7777, 0o1777);
+
+    fs.set_mode(&path, 0o2755).unwrap();
+
+    assert_eq!(fs.mode(&path).unwrap() & 0o7777, 0o2755);
+}
+
+#[cfg(unix)]
+fn new_nodes_inherit_group_of_setgid_dir<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let dir = parent.join("dir");
+    let file = dir.join("file");
+    let child = dir.join("child");
+
+    fs.create_dir(&dir).unwrap();
+    fs.set_mode(&dir, 0o2775).unwrap();
+    fs.create_file(&file, "").unwrap();
+    fs.create_dir(&child).unwrap();
+
+    let (_, gid) = fs.owner(&dir).unwrap();
+
+    assert_eq!(fs.owner(&file).unwrap().1, gid);
+    assert_eq!(fs.owner(&child).unwrap().1, gid);
+    assert_eq!(fs.mode(&file).unwrap() & 0o2000, 0);
+    assert_eq!(fs.mode(&child).unwrap() & 0o2000, 0o2000);
+}
+
+#[cfg(unix)]
+fn read_dir_reports_file_types<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let file = parent.join("file");
+    let dir = parent.join("dir");
+    let link = parent.join("link");
+    let fifo = parent.join("fifo");
+
+    fs.create_file(&file, "").unwrap();
+    fs.create_dir(&dir).unwrap();
+    fs.symlink(&dir, &link).unwrap();
+    fs.mkfifo(&fifo, 0o644).unwrap();
+
+    let mut entries: Vec<(PathBuf, FileType)> = fs
+        .read_dir(parent)
+        .unwrap()
+        .map(|e| e.unwrap())
+        .map(|e| (e.path(), e.file_type().unwrap()))
+        .collect();
+
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    assert_eq!(
+        entries,
+        vec![
+            (dir, FileType::Dir),
+            (fifo, FileType::Fifo),
+            (file, FileType::File),
+            (link, FileType::Symlink),
+        ]
+    );
+}
+
+#[cfg(unix)]
+fn mkfifo_creates_named_pipe<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("fifo");
+
+    fs.set_umask(0o022);
+    fs.mkfifo(&path, 0o666).unwrap();
+
+    assert!(fs.exists(&path));
+    assert!(!fs.is_file(&path));
+    assert!(!fs.is_dir(&path));
+    assert_eq!(fs.mode(&path).unwrap(), 0o010_644);
+}
+
+#[cfg(unix)]
+fn mkfifo_fails_if_node_exists<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("fifo");
+
+    fs.create_file(&path, "").unwrap();
+
+    let result = fs.mkfifo(&path, 0o644);
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::AlreadyExists);
+}
+
+#[cfg(unix)]
+fn mknod_fails_for_directories_and_symlinks<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("node");
+
+    for &file_type in &[FileType::Dir, FileType::Symlink] {
+        let result = fs.mknod(&path, file_type, 0o644, 0);
+
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert!(!fs.exists(&path));
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn mknod_ignores_file_type_bits_in_mode<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("fifo");
+
+    fs.set_umask(0o022);
+    fs.mknod(&path, FileType::Fifo, 0o140_666, 0).unwrap();
+
+    assert_eq!(fs.mode(&path).unwrap(), 0o010_644);
+}
+
+#[cfg(unix)]
+fn remove_file_removes_fifo<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("fifo");
+
+    fs.mkfifo(&path, 0o644).unwrap();
+    fs.remove_file(&path).unwrap();
+
+    assert!(!fs.exists(&path));
+}
+
+#[cfg(unix)]
+fn set_xattr_sets_value<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("file");
+
+    fs.create_file(&path, "").unwrap();
+    fs.set_xattr(&path, "user.hash", "abc").unwrap();
+    fs.set_xattr(&path, "user.size", "0").unwrap();
+
+    assert_eq!(fs.get_xattr(&path, "user.hash").unwrap(), b"abc");
+
+    fs.set_xattr(&path, "user.hash", "def").unwrap();
+
+    assert_eq!(fs.get_xattr(&path, "user.hash").unwrap(), b"def");
+
+    let mut names = fs.list_xattrs(&path).unwrap();
+    names.retain(|name| name.to_string_lossy().starts_with("user."));
+    names.sort();
+
+    assert_eq!(names, vec!["user.hash", "user.size"]);
+}
+
+#[cfg(unix)]
+fn set_xattr_follows_symlinks<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let file = parent.join("file");
+    let link = parent.join("link");
+
+    fs.create_file(&file, "").unwrap();
+    fs.symlink(&file, &link).unwrap();
+    fs.set_xattr(&link, "user.hash", "abc").unwrap();
+
+    assert_eq!(fs.get_xattr(&file, "user.hash").unwrap(), b"abc");
+    assert!(fs.lget_xattr(&link, "user.hash").is_err());
+    assert!(!fs
+        .llist_xattrs(&link)
+        .unwrap()
+        .contains(&"user.hash".into()));
+}
+
+#[cfg(target_os = "linux")]
+fn get_xattr_fails_if_attribute_does_not_exist<T: FileSystem + UnixFileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let path = parent.join("file");
+
+    fs.create_file(&path, "").unwrap();
+
+    let result = fs.get_xattr(&path, "user.does_not_exist");
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().raw_os_error(), Some(libc::ENODATA));
+}
+
+#[cfg(target_os = "linux")]
+fn remove_xattr_removes_attribute<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("dir");
+
+    fs.create_dir(&path).unwrap();
+    fs.set_xattr(&path, "user.hash", "abc").unwrap();
+    fs.remove_xattr(&path, "user.hash").unwrap();
+
+    let result = fs.get_xattr(&path, "user.hash");
+
+    assert_eq!(result.unwrap_err().raw_os_error(), Some(libc::ENODATA));
+
+    let result = fs.remove_xattr(&path, "user.hash");
+
+    assert_eq!(result.unwrap_err().raw_os_error(), Some(libc::ENODATA));
+}
+
+#[cfg(target_os = "linux")]
+fn set_xattr_fails_for_unknown_namespace<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("file");
+
+    fs.create_file(&path, "").unwrap();
+
+    let result = fs.set_xattr(&path, "hash", "abc");
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::Unsupported);
+}
+
+#[cfg(target_os = "linux")]
+fn lset_xattr_fails_for_user_namespace_on_symlink<T: FileSystem + UnixFileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let file = parent.join("file");
+    let link = parent.join("link");
+
+    fs.create_file(&file, "").unwrap();
+    fs.symlink(&file, &link).unwrap();
+
+    let result = fs.lset_xattr(&link, "user.hash", "abc");
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+}
+
+#[cfg(unix)]
+fn xattrs_fail_if_node_does_not_exist<T: UnixFileSystem>(fs: &T, parent: &Path) {
+    let path = parent.join("does_not_exist");
+
+    let result = fs.get_xattr(&path, "user.hash");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+
+    let result = fs.set_xattr(&path, "user.hash", "abc");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+
+    let result = fs.list_xattrs(&path);
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+fn temp_dir_creates_tempdir<T: FileSystem + TempFileSystem>(fs: &T, _: &Path) {
+    let path = {
+        let result = fs.temp_dir("test");
+
+        assert!(result.is_ok());
+
+        let temp_dir = result.unwrap();
+
+        assert!(fs.is_dir(temp_dir.path()));
+
+        temp_dir.path().to_path_buf()
+    };
+
+    assert!(!fs.is_dir(&path));
+    assert!(fs.is_dir(path.parent().unwrap()));
+}
+
+fn temp_dir_creates_unique_dir<T: FileSystem + TempFileSystem>(fs: &T, _: &Path) {
+    let first = fs.temp_dir("test").unwrap();
+    let second = fs.temp_dir("test").unwrap();
+
+    assert_ne!(first.path(), second.path());
+}
+
+#[cfg(target_os = "linux")]
+fn temp_dir_os_accepts_non_utf8_prefix<T: FileSystem + TempFileSystem>(fs: &T, _: &Path) {
+    let prefix = OsStr::from_bytes(b"pre\xff");
+
+    let temp_dir = fs.temp_dir_os(prefix).unwrap();
+    let name = temp_dir.path().file_name().unwrap();
+
+    assert!(fs.is_dir(temp_dir.path()));
+    assert!(name.as_bytes().starts_with(prefix.as_bytes()));
+}
+
+#[test]
+fn dir_entry_file_type_is_unsupported_by_default() {
+    struct Entry;
+
+    impl DirEntry for Entry {
+        fn file_name(&self) -> std::ffi::OsString {
+            "entry".into()
+        }
+
+        fn path(&self) -> PathBuf {
+            PathBuf::from("/entry")
+        }
+    }
+
+    let result = Entry.file_type();
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::Unsupported);
+}
+
+#[test]
+fn fake_write_file_at_fails_if_end_overflows() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_file("/file", "contents").unwrap();
+
+    let result = fs.write_file_at("/file", u64::MAX - 1, "data");
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::FileTooLarge);
+    assert_eq!(fs.read_file("/file").unwrap(), b"contents");
+}
+
+#[test]
+fn fake_append_fails_if_end_overflows() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_file("/file", "").unwrap();
+    fs.set_len("/file", u64::MAX - 1).unwrap();
+
+    let result = fs.append_file("/file", "data");
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::FileTooLarge);
+    assert_eq!(fs.len("/file"), u64::MAX - 1);
+}
+
+#[test]
+fn fake_name_and_path_limits_are_configurable() {
+    let fs = FakeFileSystem::new();
+
+    fs.set_max_name_len(4);
+    fs.set_max_path_len(12);
+
+    fs.create_dir("/dir").unwrap();
+    fs.create_file("/dir/file", "").unwrap();
+
+    let result = fs.create_file("/dir/files", "");
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidFilename);
+
+    let result = fs.create_file("/dir/abc/def", "");
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidFilename);
+
+    fs.set_max_name_len(255);
+    fs.set_max_path_len(4096);
+
+    fs.create_file("/dir/files", "").unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn fake_permissions_are_checked_against_the_current_user() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_dir("/dir").unwrap();
+    fs.create_file("/dir/file", "contents").unwrap();
+    fs.set_mode("/dir/file", 0o640).unwrap();
+
+    fs.set_user(2000, 1000);
+
+    assert_eq!(fs.read_file("/dir/file").unwrap(), b"contents");
+
+    let result = fs.write_file("/dir/file", "");
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    let result = fs.create_file("/dir/other", "");
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    fs.set_user(2000, 2000);
+
+    let result = fs.read_file("/dir/file");
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    fs.set_user(1000, 1000);
+    fs.set_mode("/dir", 0o777).unwrap();
+    fs.set_user(2000, 2000);
+
+    fs.create_file("/dir/other", "").unwrap();
+    fs.set_mode("/dir/other", 0o600).unwrap();
+
+    assert!(fs.access("/dir/other", 4 | 2).is_ok());
+}
+
+#[cfg(unix)]
+#[test]
+fn fake_len_and_readonly_need_search_permission_on_parent() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_dir("/dir").unwrap();
+    fs.create_file("/dir/file", "contents").unwrap();
+    fs.set_mode("/dir", 0o644).unwrap();
+
+    fs.set_user(2000, 2000);
+
+    let result = fs.readonly("/dir/file");
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+    assert_eq!(fs.len("/dir/file"), 0);
+
+    fs.set_user(1000, 1000);
+    fs.set_mode("/dir", 0o755).unwrap();
+    fs.set_user(2000, 2000);
+
+    assert!(!fs.readonly("/dir/file").unwrap());
+    assert_eq!(fs.len("/dir/file"), 8);
+}
+
+#[cfg(unix)]
+#[test]
+fn fake_only_root_can_give_nodes_away() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_file("/file", "").unwrap();
+
+    let result = fs.chown("/file", Some(2000), None);
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    let result = fs.chown("/file", None, Some(2000));
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    fs.set_user(0, 0);
+    fs.chown("/file", Some(2000), Some(3000)).unwrap();
+
+    assert_eq!(fs.owner("/file").unwrap(), (2000, 3000));
+
+    fs.set_user(1000, 1000);
+
+    let result = fs.set_mode("/file", 0o777);
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    fs.set_user(2000, 1000);
+    fs.chown("/file", None, Some(1000)).unwrap();
+
+    assert_eq!(fs.owner("/file").unwrap(), (2000, 1000));
+}
+
+#[cfg(unix)]
+#[test]
+fn fake_lchown_changes_symlink_itself() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_file("/file", "").unwrap();
+    fs.symlink("/file", "/link").unwrap();
+    fs.set_user(0, 0);
+
+    fs.lchown("/link", Some(2000), Some(2000)).unwrap();
+
+    assert_eq!(fs.owner("/file").unwrap(), (1000, 1000));
+
+    fs.chown("/link", Some(3000), Some(3000)).unwrap();
+
+    assert_eq!(fs.owner("/file").unwrap(), (3000, 3000));
+}
+
+#[cfg(unix)]
+#[test]
+fn fake_root_bypasses_permissions() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_dir("/dir").unwrap();
+    fs.create_file("/dir/file", "contents").unwrap();
+    fs.create_file("/dir/script", "").unwrap();
+    fs.set_mode("/dir/file", 0o000).unwrap();
+    fs.set_mode("/dir/script", 0o100).unwrap();
+    fs.set_mode("/dir", 0o000).unwrap();
+    fs.set_user(0, 0);
+
+    assert_eq!(fs.read_file("/dir/file").unwrap(), b"contents");
+    assert!(fs.access("/dir/script", 1).is_ok());
+
+    let result = fs.access("/dir/file", 1);
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    fs.remove_file("/dir/file").unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn fake_umask_is_applied_to_new_nodes() {
+    let fs = FakeFileSystem::new();
+
+    assert_eq!(fs.umask(), 0o022);
+
+    fs.set_umask(0o027);
+
+    fs.create_file("/file", "").unwrap();
+    fs.create_dir("/dir").unwrap();
+    fs.create_file_with_mode("/private", "", 0o666).unwrap();
+    fs.create_dir_with_mode("/shared", 0o775).unwrap();
+
+    assert_eq!(fs.umask(), 0o027);
+    assert_eq!(fs.mode("/file").unwrap(), 0o100_640);
+    assert_eq!(fs.mode("/dir").unwrap(), 0o040_750);
+    assert_eq!(fs.mode("/private").unwrap(), 0o100_640);
+    assert_eq!(fs.mode("/shared").unwrap(), 0o040_750);
+
+    fs.set_mode("/file", 0o777).unwrap();
+
+    assert_eq!(fs.mode("/file").unwrap(), 0o100_777);
+}
+
+#[cfg(unix)]
+#[test]
+fn fake_umask_is_not_applied_to_new_symlinks() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_file("/file", "").unwrap();
+    fs.set_umask(0o222);
+    fs.symlink("/file", "/link").unwrap();
+
+    assert!(!fs.readonly("/file").unwrap());
+    assert!(!fs.readonly("/link").unwrap());
+}
+
+#[cfg(unix)]
+#[test]
+fn fake_sticky_dir_restricts_removal_to_owners() {
+    let fs = FakeFileSystem::new();
+
+    fs.set_user(0, 0);
+    fs.create_dir("/tmp").unwrap();
+    fs.set_mode("/tmp", 0o1777).unwrap();
+
+    fs.set_user(1000, 1000);
+    fs.create_file("/tmp/mine", "").unwrap();
+
+    fs.set_user(2000, 2000);
+    fs.create_file("/tmp/theirs", "").unwrap();
+
+    let result = fs.remove_file("/tmp/mine");
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    let result = fs.rename("/tmp/mine", "/tmp/taken");
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    let result = fs.rename("/tmp/theirs", "/tmp/mine");
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    fs.remove_file("/tmp/theirs").unwrap();
+
+    fs.set_user(0, 0);
+    fs.remove_file("/tmp/mine").unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn fake_setgid_dir_gives_new_nodes_its_group() {
+    let fs = FakeFileSystem::new();
+
+    fs.set_user(0, 0);
+    fs.create_dir("/shared").unwrap();
+    fs.chown("/shared", None, Some(3000)).unwrap();
+    fs.set_mode("/shared", 0o2777).unwrap();
+
+    fs.set_user(1000, 1000);
+    fs.create_file("/shared/file", "").unwrap();
+    fs.create_dir("/shared/dir").unwrap();
+    fs.symlink("/shared/file", "/shared/link").unwrap();
+    fs.create_file("/file", "").unwrap();
+
+    assert_eq!(fs.owner("/shared/file").unwrap(), (1000, 3000));
+    assert_eq!(fs.owner("/shared/dir").unwrap(), (1000, 3000));
+    assert_eq!(fs.mode("/shared/dir").unwrap(), 0o042_755);
+    assert_eq!(fs.owner("/file").unwrap(), (1000, 1000));
+}
+
+#[cfg(unix)]
+#[test]
+fn fake_write_by_non_owner_clears_setid_bits() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_file("/program", "").unwrap();
+    fs.set_mode("/program", 0o6777).unwrap();
+    fs.write_file("/program", "owner").unwrap();
+
+    assert_eq!(fs.mode("/program").unwrap(), 0o106_777);
+
+    fs.set_user(2000, 2000);
+    fs.append_file("/program", "other").unwrap();
+
+    assert_eq!(fs.mode("/program").unwrap(), 0o100_777);
+}
+
+#[cfg(unix)]
+#[test]
+fn fake_special_files_cannot_be_read_as_regular_files() {
+    let fs = FakeFileSystem::new();
+
+    fs.mkfifo("/fifo", 0o644).unwrap();
+    fs.mknod("/socket", FileType::Socket, 0o644, 0).unwrap();
+
+    for path in &["/fifo", "/socket"] {
+        let result = fs.read_file(path);
+
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        let result = fs.copy_file(path, "/copy");
+
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        let result = fs.write_file(path, "");
+
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        let result = fs.write_file_atomic(path, "");
+
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+
+    assert_eq!(fs.mode("/socket").unwrap(), 0o140_644);
+    assert_eq!(fs.len("/fifo"), 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn fake_only_root_can_create_devices() {
+    let fs = FakeFileSystem::new();
+
+    let result = fs.mknod("/null", FileType::CharDevice, 0o666, 0x0103);
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+    assert!(!fs.exists("/null"));
+
+    let result = fs.mknod("/dev/null", FileType::CharDevice, 0o666, 0x0103);
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+
+    fs.create_file("/file", "").unwrap();
+    let result = fs.mknod("/file", FileType::CharDevice, 0o666, 0x0103);
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::AlreadyExists);
+
+    fs.set_user(0, 0);
+    fs.mknod("/null", FileType::CharDevice, 0o666, 0x0103)
+        .unwrap();
+    fs.mknod("/sda", FileType::BlockDevice, 0o660, 0x0800)
+        .unwrap();
+    fs.mknod("/fifo", FileType::Fifo, 0o644, 0x0103).unwrap();
+
+    assert_eq!(fs.mode("/null").unwrap(), 0o020_644);
+    assert_eq!(fs.mode("/sda").unwrap(), 0o060_640);
+    assert_eq!(fs.owner("/null").unwrap(), (0, 0));
+    assert_eq!(fs.device_number("/null").unwrap(), 0x0103);
+    assert_eq!(fs.device_number("/sda").unwrap(), 0x0800);
+    assert_eq!(fs.device_number("/fifo").unwrap(), 0);
+    assert_eq!(fs.device_number("/file").unwrap(), 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn fake_xattrs_can_be_unsupported_per_volume() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_dir("/mnt").unwrap();
+    fs.mount("/mnt", FakeVolume::new().xattrs(false)).unwrap();
+    fs.create_file("/mnt/file", "").unwrap();
+    fs.create_file("/file", "").unwrap();
+
+    let result = fs.set_xattr("/mnt/file", "user.hash", "abc");
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::Unsupported);
+
+    let result = fs.list_xattrs("/mnt/file");
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::Unsupported);
+
+    fs.set_xattr("/file", "user.hash", "abc").unwrap();
+
+    assert_eq!(fs.get_xattr("/file", "user.hash").unwrap(), b"abc");
+}
+
+#[cfg(unix)]
+#[test]
+fn fake_xattrs_move_with_their_node() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_dir("/dir").unwrap();
+    fs.create_file("/dir/file", "").unwrap();
+    fs.set_xattr("/dir/file", "user.hash", "abc").unwrap();
+    fs.rename("/dir", "/moved").unwrap();
+
+    assert_eq!(fs.get_xattr("/moved/file", "user.hash").unwrap(), b"abc");
+
+    fs.copy_file("/moved/file", "/copy").unwrap();
+
+    assert!(fs.list_xattrs("/copy").unwrap().is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn fake_xattrs_check_namespace_permissions() {
+    let fs = FakeFileSystem::new();
+
+    fs.set_user(0, 0);
+    fs.create_file("/file", "").unwrap();
+    fs.set_mode("/file", 0o644).unwrap();
+    fs.set_xattr("/file", "user.hash", "abc").unwrap();
+    fs.set_xattr("/file", "trusted.key", "secret").unwrap();
+
+    assert_eq!(
+        fs.list_xattrs("/file").unwrap(),
+        vec!["trusted.key", "user.hash"]
+    );
+
+    fs.set_user(1000, 1000);
+
+    assert_eq!(fs.get_xattr("/file", "user.hash").unwrap(), b"abc");
+    assert_eq!(fs.list_xattrs("/file").unwrap(), vec!["user.hash"]);
+
+    let result = fs.get_xattr("/file", "trusted.key");
+
+    assert_eq!(result.unwrap_err().raw_os_error(), Some(libc::ENODATA));
+
+    let result = fs.set_xattr("/file", "user.hash", "def");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    let result = fs.remove_xattr("/file", "trusted.key");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
 }
@@ -0,0 +1,159 @@
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Tests for `FakeFileSystem` files whose contents are generated on demand
+//! instead of being stored.
+extern crate filesystem;
+
+use filesystem::{ContentSource, DirEntry, FakeFileSystem, FileSystem};
+
+const HUGE: u64 = 50 << 30;
+
+#[test]
+fn sparse_file_reports_len_without_materializing() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_generated_file("/huge", HUGE, ContentSource::Zeros)
+        .unwrap();
+
+    assert!(fs.is_file("/huge"));
+    assert_eq!(fs.len("/huge"), HUGE);
+    assert_eq!(fs.read_file_range("/huge", HUGE - 4, 8).unwrap(), [0; 4]);
+}
+
+#[test]
+fn sparse_file_keeps_written_extents() {
+    let fs = FakeFileSystem::new();
+    let offset = 40 << 30;
+
+    fs.create_generated_file("/huge", HUGE, ContentSource::Zeros)
+        .unwrap();
+    fs.write_file_at("/huge", offset, "data").unwrap();
+    fs.write_file_at("/huge", offset + 6, "more").unwrap();
+    fs.write_file_at("/huge", offset + 3, "ABCD").unwrap();
+
+    assert_eq!(
+        fs.read_file_range("/huge", offset - 1, 12).unwrap(),
+        b"\0datABCDore\0"
+    );
+    assert_eq!(fs.len("/huge"), HUGE);
+}
+
+#[test]
+fn pattern_file_repeats_pattern() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_generated_file("/file", 8, ContentSource::Pattern(b"abc".to_vec()))
+        .unwrap();
+
+    assert_eq!(fs.read_file("/file").unwrap(), b"abcabcab");
+    assert_eq!(fs.read_file_range("/file", 5, 6).unwrap(), b"cab");
+}
+
+#[test]
+fn random_file_is_deterministic_for_a_seed() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_generated_file("/a", 64, ContentSource::Random { seed: 1 })
+        .unwrap();
+    fs.create_generated_file("/b", 64, ContentSource::Random { seed: 1 })
+        .unwrap();
+    fs.create_generated_file("/c", 64, ContentSource::Random { seed: 2 })
+        .unwrap();
+
+    let contents = fs.read_file("/a").unwrap();
+
+    assert_eq!(contents, fs.read_file("/b").unwrap());
+    assert_ne!(contents, fs.read_file("/c").unwrap());
+    assert_eq!(
+        &contents[5..20],
+        &fs.read_file_range("/a", 5, 15).unwrap()[..]
+    );
+}
+
+#[test]
+fn generator_file_is_computed_per_range() {
+    let fs = FakeFileSystem::new();
+    let source = ContentSource::generator(|offset, buf| {
+        for (i, b) in buf.iter_mut().enumerate() {
+            *b = ((offset + i as u64) % 251) as u8;
+        }
+    });
+
+    fs.create_generated_file("/huge", HUGE, source).unwrap();
+
+    let offset = HUGE - 3;
+    let expected: Vec<u8> = (offset..HUGE).map(|i| (i % 251) as u8).collect();
+
+    assert_eq!(fs.read_file_range("/huge", offset, 10).unwrap(), expected);
+}
+
+#[test]
+fn generated_file_reads_zeros_after_truncating_and_extending() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_generated_file("/file", 6, ContentSource::Pattern(b"ab".to_vec()))
+        .unwrap();
+    fs.set_len("/file", 3).unwrap();
+    fs.set_len("/file", 5).unwrap();
+
+    assert_eq!(fs.read_file("/file").unwrap(), b"aba\0\0");
+}
+
+#[test]
+fn generated_file_can_be_appended_to() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_generated_file("/file", 4, ContentSource::Pattern(b"ab".to_vec()))
+        .unwrap();
+    fs.append_file("/file", "cd").unwrap();
+
+    assert_eq!(fs.read_file("/file").unwrap(), b"ababcd");
+}
+
+#[test]
+fn generated_file_is_copied_without_materializing() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_generated_file("/huge", HUGE, ContentSource::Pattern(b"xy".to_vec()))
+        .unwrap();
+    fs.copy_file("/huge", "/copy").unwrap();
+
+    assert_eq!(fs.len("/copy"), HUGE);
+    assert_eq!(fs.read_file_range("/copy", HUGE - 3, 3).unwrap(), b"yxy");
+}
+
+#[test]
+fn generated_file_is_listed_in_read_dir() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_dir("/dir").unwrap();
+    fs.create_generated_file("/dir/file", HUGE, ContentSource::Zeros)
+        .unwrap();
+
+    let entries: Vec<_> = fs
+        .read_dir("/dir")
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+
+    assert_eq!(entries, vec!["file"]);
+}
@@ -0,0 +1,93 @@
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Tests for `MockFileSystem`.
+extern crate filesystem;
+
+#[cfg(unix)]
+use std::ffi::OsStr;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+
+use filesystem::{DirEntry, FileSystem, FileType, MockDirEntry, MockFileSystem};
+
+#[cfg(unix)]
+fn non_utf8(bytes: &[u8]) -> &OsStr {
+    OsStr::from_bytes(bytes)
+}
+
+#[test]
+fn dir_entry_file_name_is_last_component_of_path() {
+    let entry = MockDirEntry::new("/dir/file", true);
+
+    assert_eq!(entry.file_name(), "file");
+    assert_eq!(entry.path(), PathBuf::from("/dir/file"));
+}
+
+#[test]
+fn dir_entry_file_name_is_whole_path_if_bare_name() {
+    let entry = MockDirEntry::new("file", true);
+
+    assert_eq!(entry.file_name(), "file");
+    assert_eq!(entry.path(), PathBuf::from("file"));
+}
+
+#[test]
+fn dir_entry_file_type_follows_is_file() {
+    let file = MockDirEntry::new("/dir/file", true);
+    let dir = MockDirEntry::new("/dir/sub", false);
+
+    assert_eq!(file.file_type().unwrap(), FileType::File);
+    assert_eq!(dir.file_type().unwrap(), FileType::Dir);
+}
+
+#[cfg(unix)]
+#[test]
+fn read_dir_round_trips_non_utf8_names() {
+    let fs = MockFileSystem::new();
+    let path = PathBuf::from("/dir").join(non_utf8(b"caf\xe9"));
+
+    fs.read_dir
+        .return_value(Ok(vec![Ok(MockDirEntry::new(&path, true))]));
+
+    let entries: Vec<_> = fs.read_dir("/dir").unwrap().map(Result::unwrap).collect();
+
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].file_name(), non_utf8(b"caf\xe9"));
+    assert_eq!(entries[0].path(), path);
+}
+
+#[cfg(unix)]
+#[test]
+fn create_file_and_rename_receive_non_utf8_paths() {
+    let fs = MockFileSystem::new();
+    let from = PathBuf::from("/dir").join(non_utf8(b"caf\xe9"));
+    let to = PathBuf::from("/dir").join(non_utf8(b"\xff\xfe"));
+
+    fs.create_file(&from, "contents").unwrap();
+    fs.rename(&from, &to).unwrap();
+
+    assert!(fs
+        .create_file
+        .called_with((from.clone(), b"contents".to_vec())));
+    assert!(fs.rename.called_with((from, to)));
+}
@@ -0,0 +1,203 @@
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Tests for volumes mounted inside `FakeFileSystem`.
+extern crate filesystem;
+
+use std::io::ErrorKind;
+
+#[cfg(unix)]
+use filesystem::UnixFileSystem;
+use filesystem::{FakeFileSystem, FakeVolume, FileSystem};
+
+fn fs_with_volume(volume: FakeVolume) -> FakeFileSystem {
+    let fs = FakeFileSystem::new();
+
+    fs.create_dir("/home").unwrap();
+    fs.create_dir("/mnt").unwrap();
+    fs.mount("/mnt", volume).unwrap();
+
+    fs
+}
+
+#[test]
+fn rename_within_a_volume_succeeds() {
+    let fs = fs_with_volume(FakeVolume::new());
+
+    fs.create_dir("/mnt/dir").unwrap();
+    fs.create_file("/mnt/file", "contents").unwrap();
+    fs.rename("/mnt/file", "/mnt/dir/file").unwrap();
+
+    assert_eq!(fs.read_file("/mnt/dir/file").unwrap(), b"contents");
+}
+
+#[test]
+fn rename_across_volumes_fails() {
+    let fs = fs_with_volume(FakeVolume::new());
+
+    fs.create_file("/home/file", "contents").unwrap();
+    fs.create_dir("/mnt/dir").unwrap();
+
+    let result = fs.rename("/home/file", "/mnt/file");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::CrossesDevices);
+    assert!(fs.is_file("/home/file"));
+
+    let result = fs.rename("/mnt/dir", "/home/dir");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::CrossesDevices);
+    assert!(fs.is_dir("/mnt/dir"));
+}
+
+#[test]
+fn rename_and_remove_of_a_mount_point_fail() {
+    let fs = fs_with_volume(FakeVolume::new());
+
+    let result = fs.rename("/mnt", "/other");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::ResourceBusy);
+
+    let result = fs.remove_dir("/mnt");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::ResourceBusy);
+
+    fs.create_dir("/mnt/dir").unwrap();
+
+    let result = fs.rename("/mnt/dir", "/mnt");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::ResourceBusy);
+}
+
+#[test]
+fn remove_dir_all_fails_if_a_volume_is_mounted_below() {
+    let fs = fs_with_volume(FakeVolume::new());
+
+    fs.create_dir("/mnt/dir").unwrap();
+    fs.create_dir_all("/parent/mnt").unwrap();
+    fs.mount("/parent/mnt", FakeVolume::new()).unwrap();
+
+    let result = fs.remove_dir_all("/parent");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::ResourceBusy);
+    assert!(fs.is_dir("/parent/mnt"));
+
+    fs.remove_dir_all("/mnt/dir").unwrap();
+}
+
+#[test]
+fn mount_fails_if_node_is_not_a_dir_or_already_mounted() {
+    let fs = fs_with_volume(FakeVolume::new());
+
+    fs.create_file("/home/file", "").unwrap();
+
+    let result = fs.mount("/mnt", FakeVolume::new());
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::ResourceBusy);
+
+    let result = fs.mount("/home/file", FakeVolume::new());
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotADirectory);
+
+    let result = fs.mount("/does_not_exist", FakeVolume::new());
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+#[test]
+fn readonly_volume_rejects_changes() {
+    let fs = fs_with_volume(FakeVolume::new());
+
+    fs.create_file("/mnt/file", "contents").unwrap();
+    fs.create_dir("/ro").unwrap();
+    fs.mount("/ro", FakeVolume::new().readonly(true)).unwrap();
+
+    let results = vec![
+        fs.create_file("/ro/file", ""),
+        fs.create_dir("/ro/dir"),
+        fs.copy_file("/mnt/file", "/ro/file"),
+        fs.set_readonly("/ro", true),
+    ];
+
+    for result in results {
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::ReadOnlyFilesystem);
+    }
+    assert!(fs.read_dir("/ro").unwrap().next().is_none());
+}
+
+#[test]
+fn readonly_volume_rejects_writes_to_existing_files() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_dir("/ro").unwrap();
+    fs.create_file("/ro/file", "contents").unwrap();
+    fs.mount("/ro", FakeVolume::new().readonly(true)).unwrap();
+
+    let results = vec![
+        fs.write_file("/ro/file", "new"),
+        fs.append_file("/ro/file", "new"),
+        fs.remove_file("/ro/file"),
+        fs.rename("/ro/file", "/ro/other"),
+    ];
+
+    for result in results {
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::ReadOnlyFilesystem);
+    }
+    assert_eq!(fs.read_file("/ro/file").unwrap(), b"contents");
+}
+
+#[test]
+fn each_volume_has_its_own_capacity() {
+    let fs = fs_with_volume(FakeVolume::new().capacity(10));
+
+    fs.set_capacity(Some(20));
+    fs.create_file("/mnt/file", "0123456789").unwrap();
+
+    let result = fs.create_file("/mnt/other", "0");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::StorageFull);
+
+    fs.create_file("/home/file", "0123456789").unwrap();
+
+    assert_eq!(fs.space("/mnt/file").unwrap().total, 10);
+    assert_eq!(fs.space("/mnt").unwrap().free, 0);
+    assert_eq!(fs.space("/home").unwrap().total, 20);
+    assert_eq!(fs.space("/home").unwrap().free, 10);
+}
+
+#[cfg(unix)]
+#[test]
+fn volumes_have_distinct_device_ids() {
+    let fs = fs_with_volume(FakeVolume::new());
+
+    fs.create_file("/mnt/file", "").unwrap();
+    fs.symlink("/mnt/file", "/home/link").unwrap();
+    fs.create_dir("/dev").unwrap();
+    fs.mount("/dev", FakeVolume::new().device(0x803)).unwrap();
+
+    let root = fs.device("/").unwrap();
+    let mnt = fs.device("/mnt").unwrap();
+
+    assert_ne!(root, mnt);
+    assert_eq!(fs.device("/home").unwrap(), root);
+    assert_eq!(fs.device("/mnt/file").unwrap(), mnt);
+    assert_eq!(fs.device("/home/link").unwrap(), mnt);
+    assert_eq!(fs.device("/dev").unwrap(), 0x803);
+}
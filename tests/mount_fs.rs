@@ -0,0 +1,304 @@
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Tests for `MountFileSystem`.
+extern crate filesystem;
+
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+#[cfg(unix)]
+use filesystem::UnixFileSystem;
+use filesystem::{
+    CopyOptions, DirEntry, FakeFileSystem, FileSystem, MountFileSystem, OsFileSystem, TempDir,
+    TempFileSystem,
+};
+
+/// Mounts a `FakeFileSystem` at `/` and another at `/scratch`.
+fn mounted() -> (MountFileSystem, FakeFileSystem, FakeFileSystem) {
+    let fs = MountFileSystem::new();
+    let root = FakeFileSystem::new();
+    let scratch = FakeFileSystem::new();
+
+    root.create_dir("/home").unwrap();
+    fs.mount("/", root.clone(), "/").unwrap();
+    fs.mount("/scratch", scratch.clone(), "/").unwrap();
+
+    (fs, root, scratch)
+}
+
+fn list(fs: &MountFileSystem, path: &str) -> Vec<PathBuf> {
+    let mut paths: Vec<_> = fs
+        .read_dir(path)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn routes_to_longest_prefix() {
+    let (fs, root, scratch) = mounted();
+
+    fs.write_file("/scratch/file", "scratch").unwrap();
+    fs.write_file("/home/file", "root").unwrap();
+
+    assert_eq!(scratch.read_file("/file").unwrap(), b"scratch");
+    assert_eq!(root.read_file("/home/file").unwrap(), b"root");
+    assert!(!root.exists("/scratch/file"));
+    assert!(fs.is_dir("/scratch"));
+    assert_eq!(fs.read_file("/scratch/file").unwrap(), b"scratch");
+}
+
+#[test]
+fn routes_to_root_of_mounted_dir() {
+    let fs = MountFileSystem::new();
+    let backend = FakeFileSystem::new();
+
+    backend.create_dir_all("/srv/data").unwrap();
+    fs.mount("/data", backend.clone(), "/srv/data").unwrap();
+    fs.create_file("/data/file", "contents").unwrap();
+
+    assert_eq!(backend.read_file("/srv/data/file").unwrap(), b"contents");
+    assert!(!fs.exists("/srv"));
+}
+
+#[test]
+fn read_dir_lists_mount_points_with_global_paths() {
+    let (fs, _, scratch) = mounted();
+
+    scratch.create_file("/file", "").unwrap();
+
+    assert_eq!(
+        list(&fs, "/"),
+        vec![PathBuf::from("/home"), PathBuf::from("/scratch")]
+    );
+    assert_eq!(list(&fs, "/scratch"), vec![PathBuf::from("/scratch/file")]);
+
+    let names: Vec<_> = fs
+        .read_dir("/")
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+
+    assert!(names.contains(&"scratch".into()));
+}
+
+#[test]
+fn read_dir_does_not_repeat_mount_points_that_exist_below() {
+    let (fs, root, _) = mounted();
+
+    root.create_dir("/scratch").unwrap();
+
+    assert_eq!(
+        list(&fs, "/"),
+        vec![PathBuf::from("/home"), PathBuf::from("/scratch")]
+    );
+}
+
+#[test]
+fn rename_within_a_mount_succeeds() {
+    let (fs, _, scratch) = mounted();
+
+    fs.create_file("/scratch/file", "contents").unwrap();
+    fs.rename("/scratch/file", "/scratch/renamed").unwrap();
+
+    assert_eq!(scratch.read_file("/renamed").unwrap(), b"contents");
+}
+
+#[test]
+fn rename_across_mounts_fails() {
+    let (fs, _, _) = mounted();
+
+    fs.create_file("/home/file", "contents").unwrap();
+
+    let result = fs.rename("/home/file", "/scratch/file");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::CrossesDevices);
+    assert!(fs.is_file("/home/file"));
+    assert!(!fs.exists("/scratch/file"));
+}
+
+#[test]
+fn rename_and_remove_of_mount_points_fail() {
+    let (fs, _, _) = mounted();
+
+    fs.create_dir("/scratch/dir").unwrap();
+
+    let result = fs.rename("/scratch", "/home/scratch");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::ResourceBusy);
+
+    let result = fs.rename("/scratch/dir", "/scratch");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::ResourceBusy);
+
+    let result = fs.remove_dir_all("/scratch");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::ResourceBusy);
+    assert!(fs.is_dir("/scratch/dir"));
+}
+
+#[test]
+fn copy_file_across_mounts_copies_contents() {
+    let (fs, root, _) = mounted();
+
+    fs.create_file("/scratch/file", "contents").unwrap();
+    fs.copy_file("/scratch/file", "/home/file").unwrap();
+
+    assert_eq!(root.read_file("/home/file").unwrap(), b"contents");
+}
+
+#[cfg(unix)]
+#[test]
+fn copy_dir_all_across_mounts_copies_tree() {
+    let fs = MountFileSystem::new();
+
+    fs.mount_unix("/", FakeFileSystem::new(), "/").unwrap();
+    fs.mount_unix("/scratch", FakeFileSystem::new(), "/")
+        .unwrap();
+    fs.create_dir_all("/src/dir").unwrap();
+    fs.create_file("/src/dir/file", "contents").unwrap();
+    fs.symlink("dir/file", "/src/link").unwrap();
+    fs.copy_dir_all("/src", "/scratch/dst", &CopyOptions::new())
+        .unwrap();
+
+    assert_eq!(fs.read_file("/scratch/dst/dir/file").unwrap(), b"contents");
+    assert_eq!(
+        fs.get_symlink_src("/scratch/dst/link").unwrap(),
+        PathBuf::from("dir/file")
+    );
+}
+
+#[test]
+fn relative_paths_use_own_current_dir() {
+    let (fs, _, scratch) = mounted();
+
+    fs.set_current_dir("/scratch").unwrap();
+    fs.create_dir("dir").unwrap();
+    fs.write_file("dir/../file", "contents").unwrap();
+
+    assert_eq!(fs.current_dir().unwrap(), PathBuf::from("/scratch"));
+    assert!(scratch.is_dir("/dir"));
+    assert!(scratch.is_file("/file"));
+
+    fs.write_file("../home/file", "root").unwrap();
+
+    assert!(fs.is_file("/home/file"));
+
+    let result = fs.set_current_dir("/scratch/file");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotADirectory);
+}
+
+#[test]
+fn paths_outside_every_mount_do_not_exist() {
+    let fs = MountFileSystem::new();
+
+    fs.mount("/scratch", FakeFileSystem::new(), "/").unwrap();
+
+    assert!(!fs.exists("/file"));
+    assert!(!fs.try_exists("/file").unwrap());
+
+    let result = fs.write_file("/file", "");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+#[test]
+fn mount_fails_for_relative_or_mounted_paths() {
+    let (fs, _, _) = mounted();
+
+    let result = fs.mount("scratch", FakeFileSystem::new(), "/");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidInput);
+
+    let result = fs.mount("/scratch/", FakeFileSystem::new(), "/");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::AlreadyExists);
+
+    fs.unmount("/scratch").unwrap();
+
+    assert!(!fs.exists("/scratch"));
+    assert_eq!(
+        fs.unmount("/scratch").unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+}
+
+#[test]
+fn mounts_an_os_directory() {
+    let os = OsFileSystem::new();
+    let temp_dir = os.temp_dir("mount_fs").unwrap();
+    let fs = MountFileSystem::new();
+
+    fs.mount("/", FakeFileSystem::new(), "/").unwrap();
+    fs.mount("/disk", OsFileSystem::new(), temp_dir.path())
+        .unwrap();
+    fs.create_dir("/disk/dir").unwrap();
+    fs.write_file("/disk/dir/file", "contents").unwrap();
+
+    assert_eq!(
+        os.read_file(temp_dir.path().join("dir/file")).unwrap(),
+        b"contents"
+    );
+    assert_eq!(
+        list(&fs, "/disk/dir"),
+        vec![PathBuf::from("/disk/dir/file")]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn unix_operations_need_mount_unix() {
+    let fs = MountFileSystem::new();
+
+    fs.mount("/plain", FakeFileSystem::new(), "/").unwrap();
+    fs.mount_unix("/unix", FakeFileSystem::new(), "/").unwrap();
+    fs.create_file("/plain/file", "").unwrap();
+    fs.create_file("/unix/file", "").unwrap();
+
+    let result = fs.mode("/plain/file");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::Unsupported);
+    assert!(fs.symlink_exists("/plain/file"));
+
+    fs.set_mode("/unix/file", 0o600).unwrap();
+
+    assert_eq!(fs.mode("/unix/file").unwrap(), 0o100_600);
+}
+
+#[cfg(unix)]
+#[test]
+fn xattrs_are_routed_to_mounted_file_system() {
+    let fs = MountFileSystem::new();
+    let scratch = FakeFileSystem::new();
+
+    fs.mount_unix("/", FakeFileSystem::new(), "/").unwrap();
+    fs.mount_unix("/scratch", scratch.clone(), "/").unwrap();
+
+    fs.create_file("/scratch/file", "").unwrap();
+    fs.set_xattr("/scratch/file", "user.hash", "abc").unwrap();
+
+    assert_eq!(scratch.get_xattr("/file", "user.hash").unwrap(), b"abc");
+    assert_eq!(fs.list_xattrs("/scratch/file").unwrap(), vec!["user.hash"]);
+}
@@ -0,0 +1,155 @@
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Tests for the Unicode normalization policies of `FakeFileSystem`.
+extern crate filesystem;
+
+use std::ffi::OsString;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use filesystem::{
+    CaseFolding, CaseSensitivity, DirEntry, FakeFileSystem, FileSystem, Normalization,
+};
+
+/// `café` with a precomposed `é`.
+const NFC: &str = "caf\u{e9}";
+/// `café` with `e` followed by a combining acute accent.
+const NFD: &str = "cafe\u{301}";
+
+fn with_normalization(normalization: Normalization) -> FakeFileSystem {
+    let fs = FakeFileSystem::new();
+
+    fs.set_normalization(normalization);
+
+    fs
+}
+
+fn names(fs: &FakeFileSystem, path: &str) -> Vec<OsString> {
+    let mut names: Vec<_> = fs
+        .read_dir(path)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn preserve_keeps_forms_apart() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_file(format!("/{}", NFC), "nfc").unwrap();
+    fs.create_file(format!("/{}", NFD), "nfd").unwrap();
+
+    assert_eq!(names(&fs, "/").len(), 2);
+    assert_eq!(fs.read_file(format!("/{}", NFC)).unwrap(), b"nfc");
+    assert_eq!(fs.read_file(format!("/{}", NFD)).unwrap(), b"nfd");
+}
+
+#[test]
+fn nfd_stores_decomposed_names() {
+    let fs = with_normalization(Normalization::Nfd);
+
+    fs.create_dir(format!("/{}", NFC)).unwrap();
+    fs.create_file(format!("/{}/{}", NFC, NFC), "contents")
+        .unwrap();
+
+    assert_eq!(names(&fs, "/"), vec![OsString::from(NFD)]);
+    assert_eq!(names(&fs, &format!("/{}", NFC)), vec![OsString::from(NFD)]);
+
+    let entry = fs
+        .read_dir(format!("/{}", NFD))
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(entry.path(), PathBuf::from(format!("/{}/{}", NFD, NFD)));
+}
+
+#[test]
+fn nfd_finds_nodes_by_either_form() {
+    let fs = with_normalization(Normalization::Nfd);
+
+    fs.create_file(format!("/{}", NFC), "contents").unwrap();
+
+    assert_eq!(fs.read_file(format!("/{}", NFD)).unwrap(), b"contents");
+    assert_eq!(fs.read_file(format!("/{}", NFC)).unwrap(), b"contents");
+
+    let result = fs.create_file(format!("/{}", NFD), "");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::AlreadyExists);
+}
+
+#[test]
+fn insensitive_preserves_names_and_matches_either_form() {
+    let fs = with_normalization(Normalization::Insensitive);
+
+    fs.create_file(format!("/{}", NFC), "contents").unwrap();
+
+    assert_eq!(names(&fs, "/"), vec![OsString::from(NFC)]);
+    assert_eq!(fs.read_file(format!("/{}", NFD)).unwrap(), b"contents");
+
+    let result = fs.create_file(format!("/{}", NFD), "");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::AlreadyExists);
+
+    fs.write_file(format!("/{}", NFD), "new").unwrap();
+
+    assert_eq!(names(&fs, "/"), vec![OsString::from(NFC)]);
+    assert_eq!(fs.read_file(format!("/{}", NFC)).unwrap(), b"new");
+}
+
+#[test]
+fn insensitive_rename_to_other_form_changes_name() {
+    let fs = with_normalization(Normalization::Insensitive);
+
+    fs.create_file(format!("/{}", NFC), "contents").unwrap();
+    fs.rename(format!("/{}", NFC), format!("/{}", NFD)).unwrap();
+
+    assert_eq!(names(&fs, "/"), vec![OsString::from(NFD)]);
+}
+
+#[test]
+fn nfd_rename_stores_decomposed_name() {
+    let fs = with_normalization(Normalization::Nfd);
+
+    fs.create_file("/file", "contents").unwrap();
+    fs.rename("/file", format!("/{}", NFC)).unwrap();
+
+    assert_eq!(names(&fs, "/"), vec![OsString::from(NFD)]);
+
+    fs.rename(format!("/{}", NFC), format!("/{}", NFD)).unwrap();
+
+    assert_eq!(names(&fs, "/"), vec![OsString::from(NFD)]);
+}
+
+#[test]
+fn normalization_combines_with_case_folding() {
+    let fs = with_normalization(Normalization::Insensitive);
+
+    fs.set_case_sensitivity(CaseSensitivity::Insensitive(CaseFolding::Unicode));
+    fs.create_file("/CAF\u{c9}", "contents").unwrap();
+
+    assert_eq!(fs.read_file(format!("/{}", NFD)).unwrap(), b"contents");
+    assert_eq!(names(&fs, "/"), vec![OsString::from("CAF\u{c9}")]);
+}
@@ -0,0 +1,258 @@
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Tests for the capacity, inode and quota limits of `FakeFileSystem`.
+extern crate filesystem;
+
+use std::io::ErrorKind;
+
+#[cfg(unix)]
+use filesystem::UnixFileSystem;
+use filesystem::{CopyOptions, FakeFileSystem, FileSystem, Space};
+
+#[test]
+fn unlimited_by_default() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_file("/file", vec![0; 1 << 20]).unwrap();
+
+    let space = fs.space("/file").unwrap();
+
+    assert_eq!(space.total, u64::MAX);
+    assert_eq!(space.free, u64::MAX - (1 << 20));
+    assert_eq!(space.available, space.free);
+}
+
+#[test]
+fn space_reports_capacity_and_usage() {
+    let fs = FakeFileSystem::new();
+
+    fs.set_capacity(Some(100));
+    fs.create_dir("/dir").unwrap();
+    fs.create_file("/dir/file", "0123456789").unwrap();
+
+    assert_eq!(
+        fs.space("/dir").unwrap(),
+        Space {
+            total: 100,
+            free: 90,
+            available: 90,
+        }
+    );
+}
+
+#[test]
+fn write_file_fails_if_capacity_is_exceeded() {
+    let fs = FakeFileSystem::new();
+
+    fs.set_capacity(Some(10));
+    fs.write_file("/file", "0123456789").unwrap();
+
+    let result = fs.write_file("/other", "a");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::StorageFull);
+    assert!(!fs.exists("/other"));
+}
+
+#[test]
+fn growing_a_file_fails_if_capacity_is_exceeded() {
+    let fs = FakeFileSystem::new();
+
+    fs.set_capacity(Some(10));
+    fs.create_file("/file", "01234").unwrap();
+
+    let result = fs.append_file("/file", "567890");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::StorageFull);
+    assert_eq!(fs.read_file("/file").unwrap(), b"01234");
+
+    let result = fs.set_len("/file", 11);
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::StorageFull);
+
+    fs.write_file("/file", "0123456789").unwrap();
+    fs.write_file("/file", "").unwrap();
+    fs.write_file("/file", "0123456789").unwrap();
+}
+
+#[test]
+fn create_file_fails_if_capacity_is_exceeded() {
+    let fs = FakeFileSystem::new();
+
+    fs.set_capacity(Some(4));
+
+    let result = fs.create_file("/file", "01234");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::StorageFull);
+    assert!(!fs.exists("/file"));
+}
+
+#[test]
+fn copy_file_fails_if_capacity_is_exceeded() {
+    let fs = FakeFileSystem::new();
+
+    fs.set_capacity(Some(15));
+    fs.create_file("/file", "0123456789").unwrap();
+
+    let result = fs.copy_file("/file", "/copy");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::StorageFull);
+    assert!(!fs.exists("/copy"));
+}
+
+#[test]
+fn copy_dir_fails_if_capacity_is_exceeded() {
+    let fs = FakeFileSystem::new();
+
+    fs.set_capacity(Some(15));
+    fs.create_dir("/dir").unwrap();
+    fs.create_file("/dir/file", "0123456789").unwrap();
+
+    let result = fs.copy_dir_all("/dir", "/copy", &CopyOptions::new());
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::StorageFull);
+    assert!(!fs.exists("/copy/file"));
+}
+
+#[test]
+fn creating_nodes_fails_if_max_inodes_is_exceeded() {
+    let fs = FakeFileSystem::new();
+
+    // The root and the temp dir count too.
+    fs.set_max_inodes(Some(fs.read_dir("/").unwrap().count() as u64 + 3));
+    fs.create_dir("/dir").unwrap();
+    fs.create_file("/dir/file", "").unwrap();
+
+    let result = fs.create_dir("/other");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::StorageFull);
+
+    let result = fs.create_file("/other", "");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::StorageFull);
+
+    fs.remove_file("/dir/file").unwrap();
+    fs.create_dir("/other").unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn symlink_fails_if_max_inodes_is_exceeded() {
+    let fs = FakeFileSystem::new();
+
+    fs.set_max_inodes(Some(fs.read_dir("/").unwrap().count() as u64 + 2));
+    fs.create_file("/file", "").unwrap();
+
+    let result = fs.symlink("/file", "/link");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::StorageFull);
+    assert!(!fs.symlink_exists("/link"));
+}
+
+#[test]
+fn writes_below_a_quota_dir_fail_if_quota_is_exceeded() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_dir_all("/home/user").unwrap();
+    fs.set_quota("/home/user", Some(10)).unwrap();
+    fs.create_file("/home/user/file", "01234").unwrap();
+
+    let result = fs.write_file("/home/user/other", "567890");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::QuotaExceeded);
+
+    let result = fs.copy_file("/home/user/file", "/home/user/copy");
+
+    assert!(result.is_ok());
+
+    let result = fs.append_file("/home/user/copy", "5");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::QuotaExceeded);
+
+    fs.write_file("/home/other", "0123456789").unwrap();
+}
+
+#[test]
+fn rename_into_a_quota_dir_fails_if_quota_is_exceeded() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_dir_all("/home/user").unwrap();
+    fs.create_dir("/tmp").unwrap();
+    fs.set_quota("/home/user", Some(5)).unwrap();
+    fs.create_file("/tmp/big", "0123456789").unwrap();
+
+    let result = fs.rename("/tmp/big", "/home/user/big");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::QuotaExceeded);
+    assert!(fs.is_file("/tmp/big"));
+    assert_eq!(fs.space("/home/user").unwrap().available, 5);
+
+    fs.create_dir("/tmp/dir").unwrap();
+    fs.create_file("/tmp/dir/small", "0123").unwrap();
+    fs.rename("/tmp/dir", "/home/user/dir").unwrap();
+    fs.rename("/home/user/dir/small", "/home/user/small")
+        .unwrap();
+
+    let result = fs.rename("/tmp/big", "/home/user/small");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::QuotaExceeded);
+    assert_eq!(fs.space("/home/user").unwrap().available, 1);
+}
+
+#[test]
+fn space_reports_quota_as_available() {
+    let fs = FakeFileSystem::new();
+
+    fs.set_capacity(Some(100));
+    fs.create_dir_all("/home/user").unwrap();
+    fs.set_quota("/home/user", Some(10)).unwrap();
+    fs.create_file("/home/user/file", "0123").unwrap();
+    fs.create_file("/home/file", "0123").unwrap();
+
+    assert_eq!(
+        fs.space("/home/user/file").unwrap(),
+        Space {
+            total: 100,
+            free: 92,
+            available: 6,
+        }
+    );
+    assert_eq!(fs.space("/home").unwrap().available, 92);
+
+    fs.set_quota("/home/user", None).unwrap();
+
+    assert_eq!(fs.space("/home/user").unwrap().available, 92);
+}
+
+#[test]
+fn set_quota_fails_if_node_is_not_a_dir() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_file("/file", "").unwrap();
+
+    let result = fs.set_quota("/file", Some(10));
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotADirectory);
+
+    let result = fs.set_quota("/does_not_exist", Some(10));
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+}
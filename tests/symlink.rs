@@ -29,7 +29,11 @@ extern crate filesystem;
 #[macro_use]
 mod utils;
 
+#[cfg(target_os = "linux")]
+use std::ffi::OsStr;
 use std::io::ErrorKind;
+#[cfg(target_os = "linux")]
+use std::os::unix::ffi::OsStrExt;
 use std::path::{Path, PathBuf};
 
 use filesystem::UnixFileSystem;
@@ -53,6 +57,8 @@ macro_rules! test_fs {
             make_test!(is_file_returns_false_if_node_is_broken_symlink, $fs);
 
             make_test!(symlink_fails_if_something_already_exists, $fs);
+            #[cfg(target_os = "linux")]
+            make_test!(symlink_round_trips_non_utf8_names, $fs);
             make_test!(create_dir_fails_if_parent_is_broken_symlink, $fs);
 
             make_test!(
@@ -68,6 +74,8 @@ macro_rules! test_fs {
             make_test!(remove_dir_fails_if_node_is_dir_symlink, $fs);
             make_test!(remove_dir_inside_symlink_works, $fs);
             make_test!(remove_dir_all_inside_symlink_works, $fs);
+            make_test!(remove_dir_all_removes_links_to_own_ancestors, $fs);
+            make_test!(remove_dir_all_does_not_follow_links_out_of_tree, $fs);
             make_test!(remove_file_inside_symlink_works, $fs);
 
             make_test!(read_dir_fails_if_node_is_broken_symlink, $fs);
@@ -78,6 +86,11 @@ macro_rules! test_fs {
                 $fs
             );
             make_test!(write_file_overwrites_contents_of_symlink_source_file, $fs);
+            make_test!(write_file_atomic_replaces_symlink_source_file, $fs);
+            make_test!(
+                write_file_atomic_creates_relative_broken_symlink_source,
+                $fs
+            );
 
             make_test!(read_file_returns_symlink_source_contents, $fs);
             make_test!(read_file_works_inside_symlink, $fs);
@@ -89,6 +102,25 @@ macro_rules! test_fs {
             make_test!(copy_file_fails_if_original_file_is_broken_symlink, $fs);
 
             make_test!(rename_renames_a_symlink, $fs);
+
+            make_test!(read_file_follows_symlink_chain, $fs);
+            make_test!(read_file_fails_if_node_is_symlink_loop, $fs);
+            make_test!(is_file_and_is_dir_return_false_if_node_is_symlink_loop, $fs);
+            make_test!(set_current_dir_fails_if_node_is_symlink_loop, $fs);
+            make_test!(create_dir_and_create_file_fail_inside_symlink_loop, $fs);
+            make_test!(rename_fails_through_symlink_loop, $fs);
+
+            make_test!(get_symlink_src_returns_relative_path_verbatim, $fs);
+            make_test!(read_file_resolves_relative_symlink_from_link_dir, $fs);
+            make_test!(relative_symlink_works_after_renaming_parent, $fs);
+
+            make_test!(exists_returns_false_if_node_is_broken_symlink, $fs);
+            make_test!(exists_returns_true_if_node_is_file_symlink, $fs);
+            make_test!(try_exists_fails_if_node_is_symlink_loop, $fs);
+            make_test!(is_symlink_returns_true_only_for_symlinks, $fs);
+            make_test!(symlink_exists_returns_true_if_node_is_broken_symlink, $fs);
+            make_test!(symlink_exists_returns_false_if_node_does_not_exist, $fs);
+            make_test!(broken_symlinks_can_be_found_and_removed, $fs);
         }
     };
 }
@@ -98,6 +130,58 @@ test_fs!(os, OsFileSystem::new);
 #[cfg(unix)]
 test_fs!(fake, FakeFileSystem::new);
 
+#[cfg(target_os = "linux")]
+fn symlink_round_trips_non_utf8_names<T>(fs: &T, parent: &Path)
+where
+    T: FileSystem + UnixFileSystem,
+{
+    let target = parent.join(OsStr::from_bytes(b"target\xe9"));
+    let link = parent.join(OsStr::from_bytes(b"link\xff"));
+
+    fs.create_file(&target, "contents").unwrap();
+    fs.symlink(&target, &link).unwrap();
+
+    assert_eq!(fs.get_symlink_src(&link).unwrap(), target);
+    assert_eq!(fs.read_file(&link).unwrap(), b"contents");
+
+    let mut names: Vec<_> = fs
+        .read_dir(parent)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        vec![link.file_name().unwrap(), target.file_name().unwrap()]
+    );
+}
+
+#[test]
+fn fake_max_symlink_depth_is_configurable() {
+    let fs = FakeFileSystem::new();
+    let temp_dir = fs.temp_dir("test").unwrap();
+    let parent = temp_dir.path();
+    let file = parent.join("file");
+
+    fs.create_file(&file, "contents").unwrap();
+    let mut target = file;
+    for i in 0..5 {
+        let link = parent.join(format!("link{}", i));
+        fs.symlink(&target, &link).unwrap();
+        target = link;
+    }
+
+    fs.set_max_symlink_depth(4);
+    let result = fs.read_file(&target);
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::FilesystemLoop);
+
+    fs.set_max_symlink_depth(5);
+
+    assert_eq!(fs.read_file(&target).unwrap(), b"contents");
+}
+
 fn set_current_dir_fails_if_node_is_broken_symlink<T: UnixFileSystem + FileSystem>(
     fs: &T,
     parent: &Path,
@@ -411,6 +495,47 @@ fn remove_dir_all_inside_symlink_works<T: UnixFileSystem + FileSystem>(fs: &T, p
     assert!(!fs.is_file(&file));
 }
 
+fn remove_dir_all_removes_links_to_own_ancestors<T: UnixFileSystem + FileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let dir = parent.join("dir");
+    let sub = dir.join("sub");
+
+    fs.create_dir(&dir).unwrap();
+    fs.create_dir(&sub).unwrap();
+    fs.symlink(".", dir.join("self")).unwrap();
+    fs.symlink("..", sub.join("up")).unwrap();
+    fs.symlink(&dir, sub.join("dir")).unwrap();
+
+    let result = fs.remove_dir_all(&dir);
+
+    assert!(result.is_ok());
+    assert!(!fs.symlink_exists(&dir));
+    assert!(fs.is_dir(parent));
+}
+
+fn remove_dir_all_does_not_follow_links_out_of_tree<T: UnixFileSystem + FileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let dir = parent.join("dir");
+    let outside = parent.join("outside");
+    let file = outside.join("file");
+
+    fs.create_dir(&dir).unwrap();
+    fs.create_dir(&outside).unwrap();
+    fs.create_file(&file, "keep").unwrap();
+    fs.symlink(&outside, dir.join("link")).unwrap();
+
+    let result = fs.remove_dir_all(&dir);
+
+    assert!(result.is_ok());
+    assert!(!fs.symlink_exists(&dir));
+    assert!(fs.is_dir(&outside));
+    assert_eq!(fs.read_file_to_string(&file).unwrap(), "keep");
+}
+
 fn remove_file_inside_symlink_works<T: UnixFileSystem + FileSystem>(fs: &T, parent: &Path) {
     let dir = parent.join("dir");
     let file = dir.join("file");
@@ -505,6 +630,35 @@ fn write_file_overwrites_contents_of_symlink_source_file<T: UnixFileSystem + Fil
     assert_eq!(contents, fs.read_file_to_string(&link).unwrap());
 }
 
+fn write_file_atomic_replaces_symlink_source_file<T: UnixFileSystem + FileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let link = parent.join("link");
+    let file = parent.join("file");
+
+    fs.create_file(&file, "old contents").unwrap();
+    fs.symlink(&file, &link).unwrap();
+    fs.write_file_atomic(&link, "new contents").unwrap();
+
+    assert!(fs.is_symlink(&link));
+    assert_eq!(fs.get_symlink_src(&link).unwrap(), file);
+    assert_eq!(fs.read_file(&file).unwrap(), b"new contents");
+}
+
+fn write_file_atomic_creates_relative_broken_symlink_source<T: UnixFileSystem + FileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let link = parent.join("link");
+
+    fs.symlink("file", &link).unwrap();
+    fs.write_file_atomic(&link, "new contents").unwrap();
+
+    assert!(fs.is_symlink(&link));
+    assert_eq!(fs.read_file(parent.join("file")).unwrap(), b"new contents");
+}
+
 fn read_file_returns_symlink_source_contents<T: UnixFileSystem + FileSystem>(
     fs: &T,
     parent: &Path,
@@ -666,3 +820,260 @@ fn rename_renames_a_symlink<T: UnixFileSystem + FileSystem>(fs: &T, parent: &Pat
     assert_eq!(1, entries.len());
     assert_eq!(to, entries[0]);
 }
+
+fn read_file_follows_symlink_chain<T: UnixFileSystem + FileSystem>(fs: &T, parent: &Path) {
+    let file = parent.join("file");
+
+    fs.create_file(&file, "contents").unwrap();
+    let mut target = file;
+    for i in 0..10 {
+        let link = parent.join(format!("link{}", i));
+        fs.symlink(&target, &link).unwrap();
+        target = link;
+    }
+
+    assert_eq!(fs.read_file(&target).unwrap(), b"contents");
+}
+
+fn read_file_fails_if_node_is_symlink_loop<T: UnixFileSystem + FileSystem>(fs: &T, parent: &Path) {
+    let a = parent.join("a");
+    let b = parent.join("b");
+
+    fs.symlink(&b, &a).unwrap();
+    fs.symlink(&a, &b).unwrap();
+
+    let result = fs.read_file(&a);
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::FilesystemLoop);
+}
+
+fn is_file_and_is_dir_return_false_if_node_is_symlink_loop<T: UnixFileSystem + FileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let link = parent.join("link");
+
+    fs.symlink(&link, &link).unwrap();
+
+    assert!(!fs.is_file(&link));
+    assert!(!fs.is_dir(&link));
+}
+
+fn set_current_dir_fails_if_node_is_symlink_loop<T: UnixFileSystem + FileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let a = parent.join("a");
+    let b = parent.join("b");
+
+    fs.symlink(&b, &a).unwrap();
+    fs.symlink(&a, &b).unwrap();
+
+    let result = fs.set_current_dir(&a);
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::FilesystemLoop);
+}
+
+fn create_dir_and_create_file_fail_inside_symlink_loop<T: UnixFileSystem + FileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let a = parent.join("a");
+    let b = parent.join("b");
+
+    fs.symlink(&b, &a).unwrap();
+    fs.symlink(&a, &b).unwrap();
+
+    let result = fs.create_dir(a.join("dir"));
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::FilesystemLoop);
+
+    let result = fs.create_file(a.join("file"), "");
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::FilesystemLoop);
+}
+
+fn rename_fails_through_symlink_loop<T: UnixFileSystem + FileSystem>(fs: &T, parent: &Path) {
+    let a = parent.join("a");
+    let b = parent.join("b");
+    let file = parent.join("file");
+
+    fs.symlink(&b, &a).unwrap();
+    fs.symlink(&a, &b).unwrap();
+    fs.create_file(&file, "").unwrap();
+
+    let result = fs.rename(a.join("file"), parent.join("renamed"));
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::FilesystemLoop);
+
+    let result = fs.rename(&file, a.join("file"));
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::FilesystemLoop);
+    assert!(fs.is_file(&file));
+}
+
+fn get_symlink_src_returns_relative_path_verbatim<T: UnixFileSystem + FileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let link = parent.join("link");
+    let src = Path::new("../lib/libfoo.so");
+
+    fs.symlink(src, &link).unwrap();
+
+    assert_eq!(fs.get_symlink_src(&link).unwrap(), src);
+}
+
+fn read_file_resolves_relative_symlink_from_link_dir<T: UnixFileSystem + FileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let lib = parent.join("lib");
+    let bin = parent.join("bin");
+    let link = bin.join("libfoo.so");
+
+    fs.create_dir(&lib).unwrap();
+    fs.create_dir(&bin).unwrap();
+    fs.create_file(lib.join("libfoo.so"), "foo").unwrap();
+    fs.symlink("../lib/libfoo.so", &link).unwrap();
+
+    assert!(fs.is_file(&link));
+    assert_eq!(fs.read_file(&link).unwrap(), b"foo");
+}
+
+fn relative_symlink_works_after_renaming_parent<T: UnixFileSystem + FileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let tree = parent.join("tree");
+    let moved = parent.join("moved");
+
+    fs.create_dir(&tree).unwrap();
+    fs.create_dir(tree.join("lib")).unwrap();
+    fs.create_dir(tree.join("bin")).unwrap();
+    fs.create_file(tree.join("lib").join("libfoo.so"), "foo")
+        .unwrap();
+    fs.symlink("../lib/libfoo.so", tree.join("bin").join("libfoo.so"))
+        .unwrap();
+
+    fs.rename(&tree, &moved).unwrap();
+
+    let link = moved.join("bin").join("libfoo.so");
+    assert_eq!(
+        fs.get_symlink_src(&link).unwrap(),
+        Path::new("../lib/libfoo.so")
+    );
+    assert_eq!(fs.read_file(&link).unwrap(), b"foo");
+}
+
+fn exists_returns_false_if_node_is_broken_symlink<T: UnixFileSystem + FileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let link = parent.join("link");
+
+    fs.symlink(parent.join("does_not_exist"), &link).unwrap();
+
+    assert!(!fs.exists(&link));
+    assert!(!fs.try_exists(&link).unwrap());
+}
+
+fn exists_returns_true_if_node_is_file_symlink<T: UnixFileSystem + FileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let file = parent.join("file");
+    let link = parent.join("link");
+
+    fs.create_file(&file, "").unwrap();
+    fs.symlink(&file, &link).unwrap();
+
+    assert!(fs.exists(&link));
+    assert!(fs.try_exists(&link).unwrap());
+}
+
+fn try_exists_fails_if_node_is_symlink_loop<T: UnixFileSystem + FileSystem>(fs: &T, parent: &Path) {
+    let link = parent.join("link");
+
+    fs.symlink(&link, &link).unwrap();
+
+    let result = fs.try_exists(&link);
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::FilesystemLoop);
+    assert!(!fs.exists(&link));
+}
+
+fn is_symlink_returns_true_only_for_symlinks<T: UnixFileSystem + FileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let file = parent.join("file");
+    let dir = parent.join("dir");
+    let file_link = parent.join("file_link");
+    let dir_link = parent.join("dir_link");
+    let broken_link = parent.join("broken_link");
+
+    fs.create_file(&file, "").unwrap();
+    fs.create_dir(&dir).unwrap();
+    fs.symlink(&file, &file_link).unwrap();
+    fs.symlink(&dir, &dir_link).unwrap();
+    fs.symlink(parent.join("does_not_exist"), &broken_link)
+        .unwrap();
+
+    assert!(!fs.is_symlink(&file));
+    assert!(!fs.is_symlink(&dir));
+    assert!(!fs.is_symlink(parent.join("does_not_exist")));
+    assert!(fs.is_symlink(&file_link));
+    assert!(fs.is_symlink(&dir_link));
+    assert!(fs.is_symlink(&broken_link));
+}
+
+fn symlink_exists_returns_true_if_node_is_broken_symlink<T: UnixFileSystem + FileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let link = parent.join("link");
+
+    fs.symlink(parent.join("does_not_exist"), &link).unwrap();
+
+    assert!(fs.symlink_exists(&link));
+}
+
+fn symlink_exists_returns_false_if_node_does_not_exist<T: UnixFileSystem + FileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    assert!(!fs.symlink_exists(parent.join("does_not_exist")));
+}
+
+fn broken_symlinks_can_be_found_and_removed<T: UnixFileSystem + FileSystem>(fs: &T, parent: &Path) {
+    let file = parent.join("file");
+    let link = parent.join("link");
+    let broken_link = parent.join("broken_link");
+
+    fs.create_file(&file, "").unwrap();
+    fs.symlink(&file, &link).unwrap();
+    fs.symlink(parent.join("does_not_exist"), &broken_link)
+        .unwrap();
+
+    let broken: Vec<PathBuf> = fs
+        .read_dir(parent)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| fs.is_symlink(p) && !fs.exists(p))
+        .collect();
+
+    assert_eq!(broken, vec![broken_link.clone()]);
+
+    fs.remove_file(&broken_link).unwrap();
+
+    assert!(!fs.symlink_exists(&broken_link));
+    assert!(fs.exists(&link));
+}
@@ -0,0 +1,416 @@
+#![cfg(unix)]
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Tests for syncing directory trees, both within a single file system and
+//! between two different implementations.
+extern crate filesystem;
+
+#[macro_use]
+mod utils;
+
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::{Duration, UNIX_EPOCH};
+
+use filesystem::{
+    sync, sync_unix, FakeFileSystem, FileSystem, OsFileSystem, SyncAction, SyncCompare,
+    SyncOptions, TempDir, TempFileSystem, UnixFileSystem,
+};
+
+macro_rules! test_fs {
+    ($name:ident, $fs:expr) => {
+        mod $name {
+            use super::*;
+
+            make_test!(sync_copies_new_tree, $fs);
+            make_test!(sync_does_nothing_when_up_to_date, $fs);
+            make_test!(sync_copies_changed_files, $fs);
+            make_test!(sync_compares_by_size_and_modified_time, $fs);
+            make_test!(sync_compares_by_content, $fs);
+            make_test!(sync_keeps_extraneous_entries_by_default, $fs);
+            make_test!(sync_deletes_extraneous_entries, $fs);
+            make_test!(sync_replaces_nodes_of_a_different_type, $fs);
+            make_test!(sync_preserves_symlinks_and_modes, $fs);
+            make_test!(sync_dry_run_does_not_change_destination, $fs);
+            make_test!(sync_dry_run_does_not_look_below_replaced_file, $fs);
+            make_test!(sync_fails_if_source_does_not_exist, $fs);
+            make_test!(sync_fails_if_destination_is_inside_source, $fs);
+
+            make_test!(sync_follows_symlinks_without_unix_support, $fs);
+            make_test!(sync_skips_dangling_symlinks_it_follows, $fs);
+            make_test!(sync_between_copies_to_fake, $fs);
+        }
+    };
+}
+
+test_fs!(os, OsFileSystem::new);
+test_fs!(fake, FakeFileSystem::new);
+
+fn make_tree<T: FileSystem + UnixFileSystem>(fs: &T, root: &Path) {
+    fs.create_dir(root).unwrap();
+    fs.create_dir(root.join("dir")).unwrap();
+    fs.create_file(root.join("file"), "file").unwrap();
+    fs.create_file(root.join("dir").join("nested"), "nested")
+        .unwrap();
+    fs.set_mode(root, 0o755).unwrap();
+    fs.set_mode(root.join("dir"), 0o755).unwrap();
+}
+
+fn sync_copies_new_tree<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    make_tree(fs, &from);
+
+    let report = sync_unix(fs, &from, fs, &to, &SyncOptions::new()).unwrap();
+
+    assert_eq!(fs.read_file(to.join("file")).unwrap(), b"file");
+    assert_eq!(
+        fs.read_file(to.join("dir").join("nested")).unwrap(),
+        b"nested"
+    );
+    assert_eq!(report.actions[0], SyncAction::CreateDir(to.clone()));
+    assert!(report
+        .actions
+        .contains(&SyncAction::CreateDir(to.join("dir"))));
+    assert!(report
+        .actions
+        .contains(&SyncAction::CopyFile(to.join("dir").join("nested"))));
+    assert!(report
+        .actions
+        .contains(&SyncAction::CopyFile(to.join("file"))));
+}
+
+fn sync_does_nothing_when_up_to_date<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    make_tree(fs, &from);
+    sync_unix(fs, &from, fs, &to, &SyncOptions::new()).unwrap();
+
+    let report = sync_unix(fs, &from, fs, &to, &SyncOptions::new()).unwrap();
+
+    assert!(report.is_empty());
+}
+
+fn sync_copies_changed_files<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    make_tree(fs, &from);
+    sync_unix(fs, &from, fs, &to, &SyncOptions::new()).unwrap();
+    fs.write_file(from.join("file"), "changed").unwrap();
+
+    let report = sync_unix(fs, &from, fs, &to, &SyncOptions::new()).unwrap();
+
+    assert_eq!(report.actions, vec![SyncAction::CopyFile(to.join("file"))]);
+    assert_eq!(fs.read_file(to.join("file")).unwrap(), b"changed");
+    assert_eq!(
+        fs.modified(to.join("file")).unwrap(),
+        fs.modified(from.join("file")).unwrap()
+    );
+}
+
+fn sync_compares_by_size_and_modified_time<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    let time = UNIX_EPOCH + Duration::from_secs(1_000_000);
+    make_tree(fs, &from);
+    sync_unix(fs, &from, fs, &to, &SyncOptions::new()).unwrap();
+    fs.write_file(from.join("file"), "FILE").unwrap();
+    fs.set_modified(from.join("file"), time).unwrap();
+    fs.set_modified(to.join("file"), time).unwrap();
+
+    let report = sync_unix(fs, &from, fs, &to, &SyncOptions::new()).unwrap();
+
+    assert!(report.is_empty());
+    assert_eq!(fs.read_file(to.join("file")).unwrap(), b"file");
+}
+
+fn sync_compares_by_content<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    let time = UNIX_EPOCH + Duration::from_secs(1_000_000);
+    let options = SyncOptions::new().compare(SyncCompare::Content);
+    make_tree(fs, &from);
+    sync_unix(fs, &from, fs, &to, &options).unwrap();
+    fs.write_file(from.join("file"), "FILE").unwrap();
+    fs.set_modified(from.join("file"), time).unwrap();
+    fs.set_modified(to.join("file"), time).unwrap();
+    fs.set_modified(to.join("dir").join("nested"), time)
+        .unwrap();
+
+    let report = sync_unix(fs, &from, fs, &to, &options).unwrap();
+
+    assert_eq!(report.actions, vec![SyncAction::CopyFile(to.join("file"))]);
+    assert_eq!(fs.read_file(to.join("file")).unwrap(), b"FILE");
+}
+
+fn sync_keeps_extraneous_entries_by_default<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    make_tree(fs, &from);
+    sync_unix(fs, &from, fs, &to, &SyncOptions::new()).unwrap();
+    fs.create_file(to.join("extra"), "").unwrap();
+
+    let report = sync_unix(fs, &from, fs, &to, &SyncOptions::new()).unwrap();
+
+    assert!(report.is_empty());
+    assert!(fs.is_file(to.join("extra")));
+}
+
+fn sync_deletes_extraneous_entries<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    let options = SyncOptions::new().delete(true);
+    make_tree(fs, &from);
+    sync_unix(fs, &from, fs, &to, &options).unwrap();
+    fs.create_file(to.join("extra"), "").unwrap();
+    fs.create_dir_all(to.join("dir").join("extra").join("deeper"))
+        .unwrap();
+
+    let report = sync_unix(fs, &from, fs, &to, &options).unwrap();
+
+    assert_eq!(
+        report.actions,
+        vec![
+            SyncAction::Remove(to.join("dir").join("extra")),
+            SyncAction::Remove(to.join("extra")),
+        ]
+    );
+    assert!(!fs.exists(to.join("extra")));
+    assert!(!fs.exists(to.join("dir").join("extra")));
+    assert!(fs.is_file(to.join("dir").join("nested")));
+}
+
+fn sync_replaces_nodes_of_a_different_type<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    make_tree(fs, &from);
+    fs.create_dir(&to).unwrap();
+    fs.set_mode(&to, 0o755).unwrap();
+    fs.create_dir(to.join("file")).unwrap();
+    fs.create_file(to.join("dir"), "").unwrap();
+
+    sync_unix(fs, &from, fs, &to, &SyncOptions::new()).unwrap();
+
+    assert_eq!(fs.read_file(to.join("file")).unwrap(), b"file");
+    assert_eq!(
+        fs.read_file(to.join("dir").join("nested")).unwrap(),
+        b"nested"
+    );
+}
+
+fn sync_preserves_symlinks_and_modes<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    make_tree(fs, &from);
+    fs.symlink("file", from.join("link")).unwrap();
+    fs.set_mode(from.join("file"), 0o600).unwrap();
+
+    sync_unix(fs, &from, fs, &to, &SyncOptions::new()).unwrap();
+
+    assert_eq!(
+        fs.get_symlink_src(to.join("link")).unwrap(),
+        Path::new("file")
+    );
+    assert_eq!(fs.mode(to.join("file")).unwrap() & 0o7777, 0o600);
+
+    fs.set_mode(from.join("file"), 0o640).unwrap();
+    fs.remove_file(from.join("link")).unwrap();
+    fs.symlink("dir", from.join("link")).unwrap();
+
+    let report = sync_unix(fs, &from, fs, &to, &SyncOptions::new()).unwrap();
+
+    assert_eq!(
+        report.actions,
+        vec![
+            SyncAction::SetMode(to.join("file")),
+            SyncAction::Remove(to.join("link")),
+            SyncAction::CreateSymlink(to.join("link")),
+        ]
+    );
+    assert_eq!(
+        fs.get_symlink_src(to.join("link")).unwrap(),
+        Path::new("dir")
+    );
+    assert_eq!(fs.mode(to.join("file")).unwrap() & 0o7777, 0o640);
+}
+
+fn sync_dry_run_does_not_change_destination<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    make_tree(fs, &from);
+    let options = SyncOptions::new().preserve_mode(false);
+    let dry_run = options.clone().dry_run(true);
+
+    let report = sync_unix(fs, &from, fs, &to, &dry_run).unwrap();
+
+    assert!(!fs.exists(&to));
+    assert_eq!(report, sync_unix(fs, &from, fs, &to, &options).unwrap());
+
+    fs.write_file(from.join("file"), "changed").unwrap();
+    fs.create_file(to.join("extra"), "").unwrap();
+
+    let report = sync_unix(fs, &from, fs, &to, &dry_run.delete(true)).unwrap();
+
+    assert_eq!(
+        report.actions,
+        vec![
+            SyncAction::CopyFile(to.join("file")),
+            SyncAction::Remove(to.join("extra")),
+        ]
+    );
+    assert_eq!(fs.read_file(to.join("file")).unwrap(), b"file");
+    assert!(fs.exists(to.join("extra")));
+}
+
+fn sync_dry_run_does_not_look_below_replaced_file<T: FileSystem + UnixFileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    make_tree(fs, &from);
+    fs.create_dir(&to).unwrap();
+    fs.create_file(to.join("dir"), "").unwrap();
+    let dry_run = SyncOptions::new().preserve_mode(false).dry_run(true);
+
+    let report = sync(fs, &from, fs, &to, &dry_run).unwrap();
+
+    assert!(report
+        .actions
+        .contains(&SyncAction::CopyFile(to.join("dir").join("nested"))));
+    assert!(fs.is_file(to.join("dir")));
+
+    let report = sync_unix(fs, &from, fs, &to, &dry_run).unwrap();
+
+    assert!(report
+        .actions
+        .contains(&SyncAction::CopyFile(to.join("dir").join("nested"))));
+    assert!(fs.is_file(to.join("dir")));
+}
+
+fn sync_fails_if_source_does_not_exist<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let result = sync_unix(
+        fs,
+        parent.join("from"),
+        fs,
+        parent.join("to"),
+        &SyncOptions::new(),
+    );
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+fn sync_fails_if_destination_is_inside_source<T: FileSystem + UnixFileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let from = parent.join("from");
+    make_tree(fs, &from);
+
+    let result = sync(fs, &from, fs, from.join("to"), &SyncOptions::new());
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidInput);
+
+    let to = from.join("dir").join("..").join("to");
+    let result = sync_unix(fs, &from, fs, to, &SyncOptions::new());
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidInput);
+    assert!(!fs.exists(from.join("to")));
+}
+
+fn sync_follows_symlinks_without_unix_support<T: FileSystem + UnixFileSystem>(
+    fs: &T,
+    parent: &Path,
+) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    make_tree(fs, &from);
+    fs.symlink("file", from.join("link")).unwrap();
+
+    sync(fs, &from, fs, &to, &SyncOptions::new()).unwrap();
+
+    assert!(!fs.is_symlink(to.join("link")));
+    assert_eq!(fs.read_file(to.join("link")).unwrap(), b"file");
+}
+
+fn sync_skips_dangling_symlinks_it_follows<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let to = parent.join("to");
+    make_tree(fs, &from);
+    fs.symlink("does_not_exist", from.join("broken")).unwrap();
+
+    let report = sync(fs, &from, fs, &to, &SyncOptions::new()).unwrap();
+
+    assert_eq!(report.skipped, vec![from.join("broken")]);
+    assert!(!fs.symlink_exists(to.join("broken")));
+    assert_eq!(fs.read_file(to.join("file")).unwrap(), b"file");
+
+    let options = SyncOptions::new().follow_symlinks(false);
+    let report = sync_unix(fs, &from, fs, &to, &options).unwrap();
+
+    assert!(report.skipped.is_empty());
+    assert!(fs.is_symlink(to.join("broken")));
+}
+
+fn sync_between_copies_to_fake<T: FileSystem + UnixFileSystem>(fs: &T, parent: &Path) {
+    let from = parent.join("from");
+    let fake = FakeFileSystem::new();
+    let fake_dir = fake.temp_dir("sync").unwrap();
+    let to = fake_dir.path().join("to");
+    make_tree(fs, &from);
+    fs.symlink("file", from.join("link")).unwrap();
+
+    sync_unix(fs, &from, &fake, &to, &SyncOptions::new()).unwrap();
+
+    assert_eq!(fake.read_file(to.join("file")).unwrap(), b"file");
+    assert_eq!(
+        fake.get_symlink_src(to.join("link")).unwrap(),
+        Path::new("file")
+    );
+
+    fs.write_file(from.join("dir").join("nested"), "changed")
+        .unwrap();
+
+    let report = sync_unix(fs, &from, &fake, &to, &SyncOptions::new()).unwrap();
+
+    assert_eq!(
+        report.actions,
+        vec![SyncAction::CopyFile(to.join("dir").join("nested"))]
+    );
+}
+
+#[test]
+fn sync_copies_below_the_same_path_on_another_fake() {
+    let src = FakeFileSystem::new();
+    let dst = FakeFileSystem::new();
+    src.create_dir("/data").unwrap();
+    src.create_file("/data/file", "file").unwrap();
+    dst.create_dir("/data").unwrap();
+
+    sync_unix(&src, "/data", &dst, "/data/out", &SyncOptions::new()).unwrap();
+
+    assert_eq!(dst.read_file("/data/out/file").unwrap(), b"file");
+    assert!(!src.exists("/data/out"));
+}
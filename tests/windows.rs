@@ -0,0 +1,253 @@
+// Copyright (c) 2017 Isobel Redelmeier
+// Copyright (c) 2021 Miguel Barreto
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Tests for `FakeFileSystem`s with the Windows flavour.
+
+extern crate filesystem;
+
+use std::ffi::OsString;
+use std::io::ErrorKind;
+
+use filesystem::{DirEntry, FakeFileSystem, FileSystem, Flavor};
+
+fn windows() -> FakeFileSystem {
+    let fs = FakeFileSystem::new();
+
+    fs.set_flavor(Flavor::Windows);
+    fs.create_dir("C:\\").unwrap();
+
+    fs
+}
+
+fn names(fs: &FakeFileSystem, path: &str) -> Vec<OsString> {
+    let mut names: Vec<_> = fs
+        .read_dir(path)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn backslashes_separate_components() {
+    let fs = windows();
+
+    fs.create_dir_all("C:\\dir\\sub").unwrap();
+    fs.create_file("C:\\dir\\sub\\file", "contents").unwrap();
+
+    assert!(fs.is_dir("/C:/dir/sub"));
+    assert_eq!(fs.read_file("C:/dir/sub/file").unwrap(), b"contents");
+
+    fs.set_current_dir("C:\\dir").unwrap();
+
+    assert_eq!(fs.read_file("sub\\file").unwrap(), b"contents");
+}
+
+#[test]
+fn reserved_names_are_invalid() {
+    let fs = windows();
+
+    for name in &["CON", "nul", "Aux.txt", "COM1", "lpt9.log", "PRN .txt"] {
+        let result = fs.create_file(format!("C:\\{}", name), "");
+
+        assert_eq!(
+            result.unwrap_err().kind(),
+            ErrorKind::InvalidFilename,
+            "{}",
+            name
+        );
+    }
+    for name in &["COM0", "CONSOLE", "nul_", "xCOM1"] {
+        fs.create_file(format!("C:\\{}", name), "").unwrap();
+    }
+}
+
+#[test]
+fn forbidden_characters_are_invalid() {
+    let fs = windows();
+
+    for name in &["a<b", "a>b", "a:b", "a\"b", "a|b", "a?b", "a*b", "a\tb"] {
+        let result = fs.create_dir(format!("C:\\{}", name));
+
+        assert_eq!(
+            result.unwrap_err().kind(),
+            ErrorKind::InvalidFilename,
+            "{}",
+            name
+        );
+    }
+    assert_eq!(names(&fs, "C:\\"), Vec::<OsString>::new());
+}
+
+#[test]
+fn trailing_dots_and_spaces_are_stripped() {
+    let fs = windows();
+
+    fs.create_file("C:\\file. .", "contents").unwrap();
+
+    assert_eq!(names(&fs, "C:\\"), vec![OsString::from("file")]);
+    assert_eq!(fs.read_file("C:\\file").unwrap(), b"contents");
+    assert_eq!(fs.read_file("C:\\file  ").unwrap(), b"contents");
+
+    let result = fs.create_dir("C:\\ ..");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidFilename);
+}
+
+#[test]
+fn long_paths_are_invalid() {
+    let fs = windows();
+    let dir = format!("C:\\{}", "d".repeat(250));
+    let file = format!("{}\\{}", dir, "f".repeat(6));
+
+    fs.create_dir(&dir).unwrap();
+
+    assert_eq!(file.len(), 260);
+    let result = fs.create_file(&file, "");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidFilename);
+
+    fs.create_file(&file[..file.len() - 1], "").unwrap();
+}
+
+#[test]
+fn verbatim_paths_opt_out_of_win32_rules() {
+    let fs = windows();
+    let long = format!("\\\\?\\C:\\{}\\{}", "d".repeat(250), "f".repeat(20));
+
+    fs.create_dir_all(&long).unwrap();
+    fs.create_file("\\\\?\\C:\\CON", "device").unwrap();
+    fs.create_file("\\\\?\\C:\\file.", "dotted").unwrap();
+    fs.create_file("C:\\file.", "plain").unwrap();
+
+    assert!(fs.is_dir(&long));
+    assert_eq!(fs.read_file("\\\\?\\C:\\CON").unwrap(), b"device");
+    assert_eq!(fs.read_file("\\\\?\\C:\\file.").unwrap(), b"dotted");
+    assert_eq!(fs.read_file("C:\\file").unwrap(), b"plain");
+
+    let result = fs.create_file("\\\\?\\C:\\a?b", "");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::InvalidFilename);
+}
+
+#[test]
+fn readonly_nodes_cannot_be_removed() {
+    let fs = windows();
+
+    fs.create_dir("C:\\dir").unwrap();
+    fs.create_file("C:\\file", "").unwrap();
+    fs.create_file("C:\\other", "").unwrap();
+    fs.set_readonly("C:\\dir", true).unwrap();
+    fs.set_readonly("C:\\file", true).unwrap();
+
+    let result = fs.remove_file("C:\\file");
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    let result = fs.rename("C:\\other", "C:\\file");
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    let result = fs.remove_dir("C:\\dir");
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::PermissionDenied);
+
+    fs.rename("C:\\file", "C:\\renamed").unwrap();
+    fs.set_readonly("C:\\renamed", false).unwrap();
+    fs.remove_file("C:\\renamed").unwrap();
+}
+
+#[test]
+fn readonly_nodes_can_be_removed_with_unix_flavor() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_file("/file", "").unwrap();
+    fs.set_readonly("/file", true).unwrap();
+
+    fs.remove_file("/file").unwrap();
+}
+
+#[test]
+fn open_nodes_cannot_be_removed_or_renamed() {
+    let fs = windows();
+
+    fs.create_dir_all("C:\\dir\\sub").unwrap();
+    fs.create_file("C:\\dir\\sub\\file", "").unwrap();
+    fs.create_file("C:\\other", "").unwrap();
+    let handle = fs.open_handle("C:\\dir\\sub\\file").unwrap();
+
+    let result = fs.remove_file("C:\\dir\\sub\\file");
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::ResourceBusy);
+
+    let result = fs.rename("C:\\dir\\sub\\file", "C:\\renamed");
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::ResourceBusy);
+
+    let result = fs.rename("C:\\other", "C:\\dir\\sub\\file");
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::ResourceBusy);
+
+    let result = fs.rename("C:\\dir", "C:\\moved");
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::ResourceBusy);
+
+    let result = fs.remove_dir_all("C:\\dir");
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::ResourceBusy);
+
+    assert_eq!(fs.read_file("C:\\dir\\sub\\file").unwrap(), b"");
+
+    drop(handle);
+
+    fs.rename("C:\\dir", "C:\\moved").unwrap();
+    fs.remove_dir_all("C:\\moved").unwrap();
+}
+
+#[test]
+fn handles_are_counted() {
+    let fs = windows();
+
+    fs.create_file("C:\\file", "").unwrap();
+    let first = fs.open_handle("C:\\file").unwrap();
+    let second = fs.open_handle("C:\\file").unwrap();
+
+    drop(first);
+
+    let result = fs.remove_file("C:\\file");
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::ResourceBusy);
+
+    drop(second);
+
+    fs.remove_file("C:\\file").unwrap();
+}
+
+#[test]
+fn open_nodes_can_be_removed_with_unix_flavor() {
+    let fs = FakeFileSystem::new();
+
+    fs.create_file("/file", "").unwrap();
+    let _handle = fs.open_handle("/file").unwrap();
+
+    fs.remove_file("/file").unwrap();
+}
+
+#[test]
+fn open_handle_fails_if_path_does_not_exist() {
+    let fs = windows();
+
+    let result = fs.open_handle("C:\\missing");
+
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+}